@@ -0,0 +1,51 @@
+use std::{fs, path::Path};
+
+use crate::error::{Error, Result};
+
+use self::backend::with_backend;
+
+mod backend;
+mod format;
+pub mod null;
+#[cfg(test)]
+pub mod recording;
+pub mod system;
+
+pub use self::{
+    backend::{set_backend, AudioBackend},
+    format::SoundFormat,
+};
+
+/// A sound loaded by the current backend. Sounds stay loaded for as long as the backend is used.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Sound(u32);
+
+/// Loads a WAV or OGG file, checking first that it is one.
+pub fn load_sound(path: &Path) -> Result<Sound> {
+    let data = fs::read(path).map_err(|source| Error::Io {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    let format = SoundFormat::detect(&data).map_err(|reason| Error::Sound {
+        path: path.to_path_buf(),
+        reason,
+    })?;
+    Ok(Sound(with_backend(|backend| backend.load(path, format))))
+}
+
+impl Sound {
+    /// Plays the sound once, on top of whatever else is playing.
+    pub fn play(self) {
+        with_backend(|backend| backend.play(self.0));
+    }
+
+    /// Plays the sound over and over, like background music, for as long as the backend is used.
+    pub fn play_looped(self) {
+        with_backend(|backend| backend.play_looped(self.0));
+    }
+}
+
+/// Sets the volume of every sound, from 0 (silent) to 1 (as recorded).
+pub fn set_volume(volume: f32) {
+    with_backend(|backend| backend.set_volume(volume.clamp(0.0, 1.0)));
+}
@@ -0,0 +1,30 @@
+use std::{cell::RefCell, path::Path};
+
+use super::{format::SoundFormat, null::NullBackend};
+
+/// What the `audio` wrappers play sounds through. Sounds are plain ids handed out by `load`, so a
+/// backend without a sound card only has to count.
+pub trait AudioBackend {
+    /// Gets the sound file at `path`, already checked to be in `format`, ready to play.
+    fn load(&mut self, path: &Path, format: SoundFormat) -> u32;
+    /// Plays the sound once, on top of whatever else is playing.
+    fn play(&mut self, sound: u32);
+    /// Plays the sound over and over, starting it over if it is already looping.
+    fn play_looped(&mut self, sound: u32);
+    /// Sets the volume of every sound from now on, from 0 (silent) to 1 (as recorded).
+    fn set_volume(&mut self, volume: f32);
+}
+
+thread_local! {
+    static BACKEND: RefCell<Box<dyn AudioBackend>> = RefCell::new(Box::new(NullBackend::default()));
+}
+
+/// Replaces the backend the `audio` wrappers of this thread go through, returning the previous one.
+/// Every thread starts out silent, with the null backend.
+pub fn set_backend(backend: Box<dyn AudioBackend>) -> Box<dyn AudioBackend> {
+    BACKEND.with(|current| current.replace(backend))
+}
+
+pub(super) fn with_backend<R>(f: impl FnOnce(&mut dyn AudioBackend) -> R) -> R {
+    BACKEND.with(|backend| f(backend.borrow_mut().as_mut()))
+}
@@ -0,0 +1,144 @@
+/// What a sound file holds, as far as the backends need to know.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SoundFormat {
+    /// Uncompressed PCM samples in a RIFF WAVE file.
+    Wav {
+        channels: u16,
+        sample_rate: u32,
+        bits_per_sample: u16,
+        /// Samples per channel.
+        frames: u32,
+    },
+    /// An Ogg Vorbis file, left for the player to decode.
+    Ogg,
+}
+
+const WAVE_FORMAT_PCM: u16 = 1;
+
+impl SoundFormat {
+    /// Tells the format of a sound file from its contents. A WAV file has to hold 8 or 16 bit PCM
+    /// samples, which every player can play.
+    pub fn detect(data: &[u8]) -> Result<SoundFormat, String> {
+        if data.starts_with(b"OggS") {
+            return Ok(SoundFormat::Ogg);
+        }
+        if data.len() < 12 || &data[0..4] != b"RIFF" || &data[8..12] != b"WAVE" {
+            return Err("not a WAV or OGG file".to_string());
+        }
+
+        let mut fmt = None;
+        let mut data_size = None;
+        let mut rest = &data[12..];
+        while rest.len() >= 8 {
+            let id = &rest[0..4];
+            let size = u32::from_le_bytes([rest[4], rest[5], rest[6], rest[7]]) as usize;
+            let body = rest
+                .get(8..8 + size)
+                .ok_or_else(|| format!("the {} chunk is cut off", String::from_utf8_lossy(id)))?;
+            match id {
+                b"fmt " if size >= 16 => fmt = Some(body),
+                b"fmt " => return Err("the fmt chunk is too short".to_string()),
+                b"data" => data_size = Some(size),
+                _ => {}
+            }
+            // chunks are padded to an even size
+            rest = rest.get(8 + size + size % 2..).unwrap_or_default();
+        }
+
+        let fmt = fmt.ok_or("no fmt chunk")?;
+        let data_size = data_size.ok_or("no data chunk")?;
+        let u16_at = |i: usize| u16::from_le_bytes([fmt[i], fmt[i + 1]]);
+        let encoding = u16_at(0);
+        let channels = u16_at(2);
+        let sample_rate = u32::from_le_bytes([fmt[4], fmt[5], fmt[6], fmt[7]]);
+        let block_align = u16_at(12);
+        let bits_per_sample = u16_at(14);
+
+        if encoding != WAVE_FORMAT_PCM {
+            return Err(format!("unsupported encoding {}, only PCM is", encoding));
+        }
+        if bits_per_sample != 8 && bits_per_sample != 16 {
+            return Err(format!(
+                "unsupported {} bit samples, only 8 and 16 bit are",
+                bits_per_sample
+            ));
+        }
+        if channels == 0 || sample_rate == 0 || block_align != channels * bits_per_sample / 8 {
+            return Err("invalid fmt chunk".to_string());
+        }
+
+        Ok(SoundFormat::Wav {
+            channels,
+            sample_rate,
+            bits_per_sample,
+            frames: (data_size / block_align as usize) as u32,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A WAV file with a `LIST` chunk before the samples, like many editors write.
+    fn wav(encoding: u16, channels: u16, bits_per_sample: u16, samples: &[u8]) -> Vec<u8> {
+        let block_align = channels * bits_per_sample / 8;
+        let mut fmt = Vec::new();
+        fmt.extend(encoding.to_le_bytes());
+        fmt.extend(channels.to_le_bytes());
+        fmt.extend(8000u32.to_le_bytes());
+        fmt.extend((8000 * block_align as u32).to_le_bytes());
+        fmt.extend(block_align.to_le_bytes());
+        fmt.extend(bits_per_sample.to_le_bytes());
+
+        let mut chunks = Vec::new();
+        for (id, body) in [(b"fmt ", &fmt[..]), (b"LIST", &[1, 2, 3][..]), (b"data", samples)] {
+            chunks.extend(id);
+            chunks.extend((body.len() as u32).to_le_bytes());
+            chunks.extend(body);
+            if body.len() % 2 == 1 {
+                chunks.push(0);
+            }
+        }
+
+        let mut file = b"RIFF".to_vec();
+        file.extend((4 + chunks.len() as u32).to_le_bytes());
+        file.extend(b"WAVE");
+        file.extend(chunks);
+        file
+    }
+
+    #[test]
+    fn reads_the_layout_of_pcm_wav_files() {
+        let format = SoundFormat::detect(&wav(1, 2, 16, &[0; 8000 * 4 / 2])).unwrap();
+        assert_eq!(
+            format,
+            SoundFormat::Wav {
+                channels: 2,
+                sample_rate: 8000,
+                bits_per_sample: 16,
+                frames: 4000,
+            }
+        );
+    }
+
+    #[test]
+    fn recognizes_ogg_files() {
+        assert_eq!(SoundFormat::detect(b"OggS\0\x02rest"), Ok(SoundFormat::Ogg));
+    }
+
+    #[test]
+    fn rejects_what_it_can_not_play() {
+        let error = |data: &[u8]| SoundFormat::detect(data).unwrap_err();
+        assert_eq!(error(b"ID3\x04 an mp3"), "not a WAV or OGG file");
+        assert_eq!(error(&wav(3, 1, 32, &[0; 8])), "unsupported encoding 3, only PCM is");
+        assert_eq!(
+            error(&wav(1, 1, 24, &[0; 6])),
+            "unsupported 24 bit samples, only 8 and 16 bit are"
+        );
+
+        let mut truncated = wav(1, 1, 8, &[0; 100]);
+        truncated.truncate(truncated.len() - 10);
+        assert_eq!(error(&truncated), "the data chunk is cut off");
+    }
+}
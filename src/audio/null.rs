@@ -0,0 +1,22 @@
+use std::path::Path;
+
+use super::{backend::AudioBackend, format::SoundFormat};
+
+/// Backend that plays nothing, for machines without a sound card.
+#[derive(Debug, Default)]
+pub struct NullBackend {
+    last_id: u32,
+}
+
+impl AudioBackend for NullBackend {
+    fn load(&mut self, _path: &Path, _format: SoundFormat) -> u32 {
+        self.last_id += 1;
+        self.last_id
+    }
+
+    fn play(&mut self, _sound: u32) {}
+
+    fn play_looped(&mut self, _sound: u32) {}
+
+    fn set_volume(&mut self, _volume: f32) {}
+}
@@ -0,0 +1,98 @@
+use std::{
+    cell::RefCell,
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+
+use super::{
+    backend::{set_backend, AudioBackend},
+    format::SoundFormat,
+};
+
+/// An audio call as seen by the `RecordingBackend`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AudioCall {
+    Load { sound: u32, path: PathBuf },
+    Play(u32),
+    PlayLooped(u32),
+    SetVolume(f32),
+}
+
+#[derive(Debug, Default)]
+struct Log {
+    calls: Vec<AudioCall>,
+    /// The file of every sound, indexed by id - 1.
+    paths: Vec<PathBuf>,
+}
+
+/// Backend for tests. Plays nothing and logs every call, so tests can check which sounds were
+/// played.
+pub struct RecordingBackend {
+    log: Rc<RefCell<Log>>,
+}
+
+impl RecordingBackend {
+    /// Makes a new recording backend current for this thread and returns a handle to its log.
+    pub fn install() -> Recorder {
+        let log = Rc::new(RefCell::new(Log::default()));
+        set_backend(Box::new(RecordingBackend { log: Rc::clone(&log) }));
+        Recorder { log }
+    }
+
+    fn log(&self, call: AudioCall) {
+        self.log.borrow_mut().calls.push(call);
+    }
+}
+
+/// Read access to the calls logged by an installed `RecordingBackend`.
+#[derive(Clone)]
+pub struct Recorder {
+    log: Rc<RefCell<Log>>,
+}
+
+impl Recorder {
+    pub fn calls(&self) -> Vec<AudioCall> {
+        self.log.borrow().calls.clone()
+    }
+
+    /// The files of the sounds played once, in the order they were played.
+    pub fn played(&self) -> Vec<PathBuf> {
+        let log = self.log.borrow();
+        log.calls
+            .iter()
+            .filter_map(|call| match call {
+                AudioCall::Play(sound) => log.paths.get(*sound as usize - 1).cloned(),
+                _ => None,
+            })
+            .collect()
+    }
+
+    pub fn clear(&self) {
+        self.log.borrow_mut().calls.clear();
+    }
+}
+
+impl AudioBackend for RecordingBackend {
+    fn load(&mut self, path: &Path, _format: SoundFormat) -> u32 {
+        let mut log = self.log.borrow_mut();
+        log.paths.push(path.to_path_buf());
+        let sound = log.paths.len() as u32;
+        log.calls.push(AudioCall::Load {
+            sound,
+            path: path.to_path_buf(),
+        });
+        sound
+    }
+
+    fn play(&mut self, sound: u32) {
+        self.log(AudioCall::Play(sound));
+    }
+
+    fn play_looped(&mut self, sound: u32) {
+        self.log(AudioCall::PlayLooped(sound));
+    }
+
+    fn set_volume(&mut self, volume: f32) {
+        self.log(AudioCall::SetVolume(volume));
+    }
+}
@@ -0,0 +1,164 @@
+use std::{
+    fs,
+    io::Cursor,
+    path::Path,
+    sync::mpsc::{self, Receiver, Sender},
+    thread::{self, JoinHandle},
+};
+
+use rodio::{source::Buffered, Decoder, OutputStream, OutputStreamHandle, Sink, Source};
+
+use super::{backend::AudioBackend, format::SoundFormat};
+
+/// A sound file decoded once, the first time it is played, and shared by all its playbacks.
+type Clip = Buffered<Decoder<Cursor<Vec<u8>>>>;
+
+/// What the audio thread is asked to do.
+enum Command {
+    Play { sound: u32, clip: Clip, looped: bool },
+    SetVolume(f32),
+}
+
+/// A playback on the audio thread, stopped when dropped.
+struct Playback {
+    sound: u32,
+    looped: bool,
+    sink: Sink,
+}
+
+/// Plays sounds on the default output device through rodio, which uses ALSA on Linux, Core Audio
+/// on macOS and WASAPI on Windows. Plays WAV and OGG files.
+///
+/// The device is opened on a thread of its own that owns every playback, so the game only sends
+/// it commands. Every playback has a sink of its own, which lets sounds overlap and change volume while playing.
+pub struct SystemBackend {
+    commands: Option<Sender<Command>>,
+    thread: Option<JoinHandle<()>>,
+    /// The clip of every sound, indexed by id - 1, `None` for the files that could not be decoded.
+    clips: Vec<Option<Clip>>,
+}
+
+impl SystemBackend {
+    /// Starts the audio thread on the default output device, if the system has one.
+    pub fn open() -> Option<SystemBackend> {
+        let (commands, received) = mpsc::channel();
+        let (opened, opening) = mpsc::channel();
+        let thread = thread::Builder::new()
+            .name("audio".to_string())
+            .spawn(move || {
+                // the stream has to stay on the thread that opened it
+                let (_stream, output) = match OutputStream::try_default() {
+                    Ok(stream) => stream,
+                    Err(error) => {
+                        let _ = opened.send(Err(error));
+                        return;
+                    }
+                };
+                let _ = opened.send(Ok(()));
+                run(&output, received);
+            })
+            .ok()?;
+
+        match opening.recv() {
+            Ok(Ok(())) => Some(SystemBackend {
+                commands: Some(commands),
+                thread: Some(thread),
+                clips: Vec::new(),
+            }),
+            Ok(Err(error)) => {
+                eprintln!("Failed to open the audio device: {}", error);
+                None
+            }
+            Err(_) => None,
+        }
+    }
+
+    fn send(&self, command: Command) {
+        if let Some(commands) = &self.commands {
+            // the thread only ends once the backend is dropped
+            let _ = commands.send(command);
+        }
+    }
+
+    fn play_clip(&self, sound: u32, looped: bool) {
+        if let Some(Some(clip)) = self.clips.get(sound as usize - 1) {
+            let clip = clip.clone();
+            self.send(Command::Play { sound, clip, looped });
+        }
+    }
+}
+
+/// Carries out the commands until the backend hangs up, then stops everything still playing.
+fn run(output: &OutputStreamHandle, commands: Receiver<Command>) {
+    let mut volume = 1.0;
+    let mut playing: Vec<Playback> = Vec::new();
+    for command in commands {
+        // forget the playbacks that are over
+        playing.retain(|playback| !playback.sink.empty());
+        match command {
+            Command::Play { sound, clip, looped } => {
+                if looped {
+                    playing.retain(|playback| !(playback.looped && playback.sound == sound));
+                }
+                let sink = match Sink::try_new(output) {
+                    Ok(sink) => sink,
+                    Err(error) => {
+                        eprintln!("Failed to play a sound: {}", error);
+                        continue;
+                    }
+                };
+                sink.set_volume(volume);
+                if looped {
+                    sink.append(clip.repeat_infinite());
+                } else {
+                    sink.append(clip);
+                }
+                playing.push(Playback { sound, looped, sink });
+            }
+            Command::SetVolume(new_volume) => {
+                volume = new_volume;
+                for playback in &playing {
+                    playback.sink.set_volume(volume);
+                }
+            }
+        }
+    }
+}
+
+impl AudioBackend for SystemBackend {
+    fn load(&mut self, path: &Path, _format: SoundFormat) -> u32 {
+        let clip = fs::read(path)
+            .map_err(|error| error.to_string())
+            .and_then(|data| Decoder::new(Cursor::new(data)).map_err(|error| error.to_string()));
+        match clip {
+            Ok(decoder) => self.clips.push(Some(decoder.buffered())),
+            Err(error) => {
+                eprintln!("Failed to decode {}: {}", path.display(), error);
+                self.clips.push(None);
+            }
+        }
+        self.clips.len() as u32
+    }
+
+    fn play(&mut self, sound: u32) {
+        self.play_clip(sound, false);
+    }
+
+    fn play_looped(&mut self, sound: u32) {
+        self.play_clip(sound, true);
+    }
+
+    fn set_volume(&mut self, volume: f32) {
+        self.send(Command::SetVolume(volume));
+    }
+}
+
+impl Drop for SystemBackend {
+    fn drop(&mut self) {
+        // hanging up ends the thread, which stops the sounds still playing
+        self.commands.take();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
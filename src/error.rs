@@ -0,0 +1,128 @@
+use std::{fmt, io, path::PathBuf};
+
+use gl::types::GLenum;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShaderStage {
+    Vertex,
+    Fragment,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AssetKind {
+    Material,
+    Texture,
+    Atlas,
+    SpriteSheet,
+    Sound,
+}
+
+/// Everything that can go wrong while loading the game's assets.
+#[derive(Debug)]
+pub enum Error {
+    Io {
+        path: PathBuf,
+        source: io::Error,
+    },
+    Image {
+        path: PathBuf,
+        reason: String,
+    },
+    Sound {
+        path: PathBuf,
+        reason: String,
+    },
+    Preprocess {
+        path: PathBuf,
+        line: usize,
+        reason: String,
+    },
+    ShaderCompile {
+        stage: ShaderStage,
+        log: String,
+    },
+    Link {
+        log: String,
+    },
+    /// `glCheckFramebufferStatus` did not return `FRAMEBUFFER_COMPLETE`.
+    Framebuffer {
+        status: GLenum,
+    },
+    DuplicateAsset {
+        kind: AssetKind,
+        name: String,
+    },
+    Atlas {
+        name: String,
+        reason: String,
+    },
+    InvalidLevel {
+        path: PathBuf,
+        reason: String,
+    },
+    InvalidSpriteSheet {
+        path: PathBuf,
+        reason: String,
+    },
+    InvalidManifest {
+        path: PathBuf,
+        reason: String,
+    },
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl fmt::Display for ShaderStage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ShaderStage::Vertex => write!(f, "vertex"),
+            ShaderStage::Fragment => write!(f, "fragment"),
+        }
+    }
+}
+
+impl fmt::Display for AssetKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AssetKind::Material => write!(f, "Material"),
+            AssetKind::Texture => write!(f, "Texture"),
+            AssetKind::Atlas => write!(f, "Atlas"),
+            AssetKind::SpriteSheet => write!(f, "Sprite sheet"),
+            AssetKind::Sound => write!(f, "Sound"),
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Io { path, source } => write!(f, "Failed to read file {}: {}", path.display(), source),
+            Error::Image { path, reason } => write!(f, "Error reading image file {}: {}", path.display(), reason),
+            Error::Sound { path, reason } => write!(f, "Error reading sound file {}: {}", path.display(), reason),
+            Error::Preprocess { path, line, reason } => {
+                write!(f, "Error preprocessing {} line {}: {}", path.display(), line, reason)
+            }
+            Error::ShaderCompile { stage, log } => write!(f, "Error during compilation of {} shader:\n{}", stage, log),
+            Error::Link { log } => write!(f, "Error during linking:\n{}", log),
+            Error::Framebuffer { status } => write!(f, "Framebuffer is not complete: status {:#x}", status),
+            Error::DuplicateAsset { kind, name } => write!(f, "{} already exists: {}", kind, name),
+            Error::Atlas { name, reason } => write!(f, "Error packing atlas {}: {}", name, reason),
+            Error::InvalidLevel { path, reason } => write!(f, "Invalid level file {}: {}", path.display(), reason),
+            Error::InvalidSpriteSheet { path, reason } => {
+                write!(f, "Invalid sprite sheet {}: {}", path.display(), reason)
+            }
+            Error::InvalidManifest { path, reason } => {
+                write!(f, "Invalid asset manifest {}: {}", path.display(), reason)
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
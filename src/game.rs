@@ -1,154 +1,960 @@
-use std::{cmp, path::Path};
+use std::path::{Path, PathBuf};
 
-use glam::{vec2, vec3a, Mat4, Vec2};
-use glfw::ffi::{KEY_A, KEY_D};
+use glam::{vec2, vec3a, Mat4, Vec2, Vec3A};
+use glfw::ffi::{KEY_A, KEY_D, KEY_ENTER, KEY_M, KEY_R, KEY_S, KEY_SPACE, KEY_W};
 
-use self::{entity::Entity, level::Level, loader::Loader, sprite_renderer::SpriteRenderer};
+use crate::{
+    audio,
+    error::{Error, Result},
+    opengl::{blend_func, enable},
+    util::{read_file, Rng},
+};
 
+use self::{
+    animation::Animation,
+    ball::Ball,
+    bitmap_font::BitmapFont,
+    entity::Entity,
+    level::Level,
+    loader::Loader,
+    manifest::TextureOptions,
+    particle_renderer::ParticleRenderer,
+    particles::{Emitter, ParticleGenerator, ParticleSettings},
+    post_processor::{Effect, Effects, PostProcessor},
+    power_ups::{PowerUpKind, PowerUps},
+    sounds::{SoundEvent, Sounds},
+    sprite_renderer::SpriteRenderer,
+    state::{Event, GameState, StateMachine},
+    text_renderer::TextRenderer,
+};
+
+mod animation;
+mod atlas;
+mod ball;
+mod bitmap_font;
+mod collision;
 mod entity;
 mod level;
 pub mod loader;
+mod manifest;
 mod material;
+mod particle_renderer;
+mod particles;
+mod post_processor;
+mod power_ups;
+pub mod preprocessor;
+mod sounds;
+mod sprite_batch;
 mod sprite_renderer;
+mod state;
+mod text_renderer;
 mod texture2d;
 
-pub enum GameState {
-    Active,
-    Menu,
-    Win,
-}
-
 pub struct Game {
-    state: GameState,
+    state: StateMachine,
     keys: [bool; 1024],
+    keys_processed: [bool; 1024],
     width: u32,
     height: u32,
     sprite_renderer: SpriteRenderer,
+    text_renderer: TextRenderer,
+    particle_renderer: ParticleRenderer,
+    post_processor: PostProcessor,
+    effects: Effects,
+    /// Seconds the game has been running for, driving the post-processing effects.
+    time: f32,
     levels: Vec<Level>,
-    current_level: usize,
     player: Entity,
-}
-
-pub struct Wee {
-    hello: String,
-}
-
-fn some() -> Wee {
-    let mut str = String::new();
-    str.push_str("hel");
-
-    Wee { hello: str }
-}
-
-macro_rules! load_textures {
-    ($loader:ident, $(($name:expr, $file_name:expr, $alpha:expr)),+ $(,)?) => {{
-        $(
-            $loader.load_texture($name, Path::new(concat!("resources/textures/", $file_name)), $alpha);
-        )+
-    }};
+    ball: Ball,
+    /// Where the paddle and the ball were before the last step, to draw them in between.
+    previous_player: Vec2,
+    previous_ball: Vec2,
+    trail: ParticleGenerator,
+    debris: ParticleGenerator,
+    power_ups: PowerUps,
+    sounds: Sounds,
+    lives: u32,
 }
 
 const PLAYER_SIZE: Vec2 = vec2(100.0, 20.0);
+const BALL_RADIUS: f32 = 12.5;
+const INITIAL_BALL_VELOCITY: Vec2 = vec2(100.0, -350.0);
+const INITIAL_LIVES: u32 = 3;
+const TRAIL_PARTICLES: usize = 500;
+const TRAIL_PARTICLES_PER_UPDATE: usize = 2;
+const TRAIL: ParticleSettings = ParticleSettings {
+    life: 1.0,
+    size: 10.0,
+    spread: 5.0,
+    gravity: Vec2::ZERO,
+};
+const DEBRIS_PARTICLES: usize = 300;
+const DEBRIS_PARTICLES_PER_BRICK: usize = 12;
+const DEBRIS: ParticleSettings = ParticleSettings {
+    life: 0.6,
+    size: 6.0,
+    spread: 0.0,
+    gravity: vec2(0.0, 600.0),
+};
+/// How much faster the ball moves with the speed power-up.
+const SPEED_FACTOR: f32 = 1.2;
+/// How much wider the paddle gets with the pad size power-up.
+const PAD_SIZE_INCREASE: f32 = 50.0;
+/// Tints showing the sticky paddle and the pass-through ball.
+const STICKY_COLOR: Vec3A = vec3a(1.0, 0.5, 1.0);
+const PASS_THROUGH_COLOR: Vec3A = vec3a(1.0, 0.5, 0.5);
+/// Samples per pixel the scene is rendered with before post-processing.
+const POST_PROCESS_SAMPLES: i32 = 4;
+/// Seconds the screen shakes for after the ball hits a solid brick.
+const SHAKE_DURATION: f32 = 0.05;
+/// Volume of the music and sounds on the menu and the end screens, quieter than in a level.
+const MENU_VOLUME: f32 = 0.4;
+/// Seconds of game time every `Game::step` simulates, 120 steps a second.
+pub const STEP: f32 = 1.0 / 120.0;
+const MANIFEST_PATH: &str = "resources/assets.toml";
+const FONT_PATH: &str = "resources/fonts/dejavu_sans.fnt";
 
 impl Game {
-    pub fn new(width: u32, height: u32, loader: &mut Loader) -> Game {
-        // load shaders
-        let material = loader.load_material("sprite", "src/shaders/sprite.vert", "src/shaders/sprite.frag");
+    pub fn new(width: u32, height: u32, loader: &mut Loader) -> Result<Game> {
+        Game::with_seed(width, height, loader, Rng::from_time().next_u64())
+    }
+
+    /// A game whose particles and power-up drops are random from `seed`, so stepping it with the
+    /// same input always plays out the same.
+    pub fn with_seed(width: u32, height: u32, loader: &mut Loader, seed: u64) -> Result<Game> {
+        // load shaders and textures
+        let manifest = loader.load_manifest(Path::new(MANIFEST_PATH))?;
+        if manifest.levels.is_empty() {
+            return Err(Error::InvalidManifest {
+                path: MANIFEST_PATH.into(),
+                reason: "no levels".to_string(),
+            });
+        }
+        let material = loader.get_material("sprite").clone();
+        let batch_material = loader.get_material("sprite_batch").clone();
+        let instanced_material = loader.get_material("sprite_instanced").clone();
 
         // configure shaders
         let projection = Mat4::orthographic_lh(0.0, width as f32, height as f32, 0.0, -1.0, 1.0);
         material.use_();
         material.set_integer("image", 0);
         material.set_matrix4f("projection", projection);
+        batch_material.use_();
+        batch_material.set_integer("image", 0);
+        batch_material.set_matrix4f("projection", projection);
+        instanced_material.use_();
+        instanced_material.set_integer("image", 0);
+        instanced_material.set_matrix4f("projection", projection);
+
+        // sprites with an alpha channel (the ball) need blending
+        enable(gl::BLEND);
+        blend_func(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
 
         // renderer
-        let sprite_renderer = SpriteRenderer::new(material.clone());
-
-        // load textures
-        load_textures!(
-            loader,
-            ("background", "background.jpg", false),
-            ("face", "awesomeface.png", true),
-            ("block", "block.png", false),
-            ("block_solid", "block_solid.png", false),
-            ("paddle", "paddle.png", true),
-        );
+        let sprite_renderer = SpriteRenderer::new(material, batch_material, instanced_material);
+
+        // text
+        let text_material = loader.get_material("text");
+        text_material.use_();
+        text_material.set_integer("image", 0);
+        text_material.set_matrix4f("projection", projection);
+        let text_material = text_material.clone();
+
+        let font = BitmapFont::parse(&read_file(Path::new(FONT_PATH))?);
+        let font_texture = loader
+            .load_texture(
+                "font",
+                &Path::new(FONT_PATH).with_file_name(&font.page),
+                TextureOptions::new(true),
+            )?
+            .clone();
+        let text_renderer = TextRenderer::new(text_material, font, font_texture);
+
+        // particles
+        let particle_material = loader.get_material("particle");
+        particle_material.use_();
+        particle_material.set_integer("image", 0);
+        particle_material.set_matrix4f("projection", projection);
+        let particle_renderer = ParticleRenderer::new(particle_material.clone(), loader.get_sprite("particle"));
+        let mut rng = Rng::new(seed);
+        let trail = ParticleGenerator::new(TRAIL_PARTICLES, TRAIL, Rng::new(rng.next_u64()));
+        let debris = ParticleGenerator::new(DEBRIS_PARTICLES, DEBRIS, Rng::new(rng.next_u64()));
+        let power_ups = PowerUps::new(Rng::new(rng.next_u64()));
+
+        // post-processing
+        let post_processor = PostProcessor::new(
+            loader.get_material("post_process").clone(),
+            width,
+            height,
+            POST_PROCESS_SAMPLES,
+        )?;
+
+        // sounds
+        let sounds = Sounds::new(loader);
+        // the game opens on the menu
+        audio::set_volume(volume_in(GameState::Menu));
+        sounds.start_music();
 
         // load levels
-        let levels = load_levels(loader, width, height, 1);
+        let levels = load_levels(loader, &manifest.levels, width, height)?;
 
         // player
-        let player_pos = vec2(width as f32 / 2.0 - PLAYER_SIZE.x / 2.0, height as f32 - PLAYER_SIZE.y);
-        let player = Entity::new(
-            player_pos,
+        let mut player = Entity::new(
+            player_start_position(width, height),
             PLAYER_SIZE,
-            loader.get_texture("paddle").clone(),
+            loader.get_sprite("paddle"),
             vec3a(1.0, 1.0, 1.0),
         );
+        player.set_animation(Animation::new(loader.get_sprite_sheet("paddle").clone()));
 
-        Game {
-            state: GameState::Active,
+        // ball
+        let ball = Ball::new(
+            ball_position_on(&player),
+            BALL_RADIUS,
+            INITIAL_BALL_VELOCITY,
+            loader.get_sprite("face"),
+            vec3a(1.0, 1.0, 1.0),
+        );
+
+        Ok(Game {
+            state: StateMachine::new(levels.len()),
             keys: [false; 1024],
+            keys_processed: [false; 1024],
             width,
             height,
             levels,
-            current_level: 0,
             sprite_renderer,
+            text_renderer,
+            particle_renderer,
+            post_processor,
+            effects: Effects::default(),
+            time: 0.0,
+            previous_player: player.position,
+            previous_ball: ball.entity.position,
             player,
+            ball,
+            trail,
+            debris,
+            power_ups,
+            sounds,
+            lives: INITIAL_LIVES,
+        })
+    }
+
+    /// Advances the game by one `STEP`, reacting to the keys held down.
+    pub fn step(&mut self, loader: &Loader) {
+        self.previous_player = self.player.position;
+        self.previous_ball = self.ball.entity.position;
+        self.process_input(STEP, loader);
+        self.update(STEP);
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        // animations, particles and effects finish playing after the game is won or lost
+        self.time += dt;
+        self.effects.update(dt);
+        self.player.update(dt);
+        self.levels[self.state.level()].update(dt);
+        self.debris.simulate(dt);
+
+        if self.state.state() != GameState::Active {
+            self.trail.simulate(dt);
+            return;
+        }
+
+        if self.ball.stuck {
+            self.ball.entity.position = ball_position_on(&self.player);
+        }
+        let speed = if self.power_ups.is_active(PowerUpKind::Speed) {
+            SPEED_FACTOR
+        } else {
+            1.0
+        };
+        self.ball.move_(dt * speed, self.width);
+        let pass_through = self.power_ups.is_active(PowerUpKind::PassThrough);
+        for brick in self.levels[self.state.level()].collide_ball(&mut self.ball, pass_through) {
+            if brick.is_solid() {
+                self.effects.enable(Effect::Shake, SHAKE_DURATION);
+                self.sounds.play(SoundEvent::SolidHit);
+            } else if brick.is_destroyed() {
+                self.sounds.play(SoundEvent::BrickHit);
+                self.debris
+                    .burst(brick.position, brick.size, brick.color(), DEBRIS_PARTICLES_PER_BRICK);
+                self.power_ups.roll_drop(brick.position + brick.size / 2.0);
+            }
+        }
+        if !self.ball.stuck && self.ball.bounce_off_paddle(&self.player) {
+            self.player.play("glow");
+            self.sounds.play(SoundEvent::PaddleHit);
+            self.ball.stuck = self.power_ups.is_active(PowerUpKind::Sticky);
+        }
+        for kind in self.power_ups.update(dt, &self.player.aabb(), self.height as f32) {
+            self.sounds.play(SoundEvent::PowerUpPickup);
+            match kind {
+                PowerUpKind::Confuse => self.effects.enable(Effect::Confuse, kind.duration()),
+                PowerUpKind::Chaos => self.effects.enable(Effect::Chaos, kind.duration()),
+                _ => {}
+            }
+        }
+        self.apply_power_ups();
+        let new_particles = if self.ball.stuck { 0 } else { TRAIL_PARTICLES_PER_UPDATE };
+        self.trail.update(dt, Emitter::of(&self.ball.entity), new_particles);
+
+        if self.ball.entity.position.y >= self.height as f32 {
+            self.lives = self.lives.saturating_sub(1);
+            if self.lives == 0 {
+                self.change_state(Event::OutOfLives);
+            } else {
+                self.reset_player();
+            }
+        }
+
+        if self.levels[self.state.level()].is_completed() {
+            self.change_state(Event::LevelCompleted);
         }
     }
 
-    pub fn update(&self, dt: f32) {}
+    pub fn lives(&self) -> u32 {
+        self.lives
+    }
 
-    pub fn process_input(&mut self, dt: f32) {
+    pub fn process_input(&mut self, dt: f32, loader: &Loader) {
         const PLAYER_VELOCITY: f32 = 500.0;
-        match self.state {
+        match self.state.state() {
+            GameState::Menu => {
+                if self.key_pressed_once(KEY_ENTER) {
+                    self.handle_event(Event::Start, loader);
+                }
+                if self.key_pressed_once(KEY_W) {
+                    self.handle_event(Event::NextLevel, loader);
+                }
+                if self.key_pressed_once(KEY_S) {
+                    self.handle_event(Event::PreviousLevel, loader);
+                }
+            }
+            GameState::Win | GameState::GameOver => {
+                if self.key_pressed_once(KEY_R) {
+                    self.handle_event(Event::Restart, loader);
+                }
+                if self.key_pressed_once(KEY_M) {
+                    self.handle_event(Event::OpenMenu, loader);
+                }
+            }
             GameState::Active => {
                 let velocity = PLAYER_VELOCITY * dt;
 
                 if self.keys[KEY_A as usize] {
                     self.player.position.x = (self.player.position.x - velocity).max(0.0);
                 } else if self.keys[KEY_D as usize] {
-                    self.player.position.x = (self.player.position.x + velocity).min(self.width as f32 - PLAYER_SIZE.x);
+                    self.player.position.x =
+                        (self.player.position.x + velocity).min(self.width as f32 - self.player.size.x);
+                }
+
+                if self.keys[KEY_SPACE as usize] {
+                    self.ball.stuck = false;
                 }
             }
-            _ => todo!(),
         }
     }
 
-    pub fn render(&self, loader: &mut Loader) {
-        match self.state {
+    /// Draws the game `alpha` of the way from the step before to the last one, so the movement
+    /// looks smooth at frame rates the steps don't divide.
+    pub fn render(&mut self, loader: &mut Loader, alpha: f32) {
+        // the menu previews the selected level behind a dimmed background
+        let state = self.state.state();
+        let background_color = match state {
+            GameState::Active => vec3a(1., 1., 1.),
+            GameState::Menu => vec3a(0.4, 0.4, 0.4),
+            GameState::Win => vec3a(0.6, 1., 0.6),
+            GameState::GameOver => vec3a(1., 0.4, 0.4),
+        };
+
+        self.post_processor.begin_render();
+        self.sprite_renderer.draw(
+            &loader.get_sprite("background"),
+            vec2(0., 0.),
+            vec2(self.width as f32, self.height as f32),
+            0.0,
+            background_color,
+        );
+        self.levels[self.state.level()].draw(&mut self.sprite_renderer);
+        if state == GameState::Active {
+            self.power_ups.draw(&mut self.sprite_renderer, loader);
+        }
+
+        if state != GameState::Menu {
+            let position = self.previous_player.lerp(self.player.position, alpha);
+            self.player.draw_at(&mut self.sprite_renderer, position);
+        }
+        if state == GameState::Active || state == GameState::Win {
+            let position = self.previous_ball.lerp(self.ball.entity.position, alpha);
+            self.ball.entity.draw_at(&mut self.sprite_renderer, position);
+        }
+        self.sprite_renderer.flush();
+        self.particle_renderer.draw(&self.trail);
+        self.particle_renderer.draw(&self.debris);
+        self.post_processor.end_render();
+        self.post_processor.render(&self.effects, self.time);
+
+        let white = vec3a(1., 1., 1.);
+        let center_y = self.height as f32 / 2.0;
+        match state {
             GameState::Active => {
-                self.sprite_renderer.draw(
-                    loader.get_texture("background"),
-                    vec2(0., 0.),
-                    vec2(self.width as f32, self.height as f32),
-                    0.0,
-                    vec3a(1., 1., 1.),
-                );
-                self.levels[self.current_level].draw(&self.sprite_renderer);
-                self.player.draw(&self.sprite_renderer);
+                self.text_renderer
+                    .draw(&format!("Lives: {}", self.lives()), vec2(5.0, 5.0), 0.6, white);
+            }
+            GameState::Menu => {
+                let level = format!("Level {} of {}", self.state.level() + 1, self.levels.len());
+                self.draw_centered_text(&level, center_y - 60.0, 0.8, white);
+                self.draw_centered_text("Press ENTER to start", center_y, 1.0, white);
+                self.draw_centered_text("Press W or S to select level", center_y + 45.0, 0.6, white);
+            }
+            GameState::Win => {
+                self.draw_centered_text("You WON!!!", center_y - 40.0, 1.0, vec3a(0., 1., 0.));
+                self.draw_centered_text("Press R to retry or M for the menu", center_y + 10.0, 0.6, white);
+            }
+            GameState::GameOver => {
+                self.draw_centered_text("Game over", center_y - 40.0, 1.0, vec3a(1., 0.2, 0.2));
+                self.draw_centered_text("Press R to retry or M for the menu", center_y + 10.0, 0.6, white);
             }
-            _ => todo!(),
         }
     }
 
+    /// Rebuilds every level whose file was edited, keeping the bricks already destroyed.
+    /// Returns the level files it tried to reload.
+    pub fn reload_changed_levels(&mut self, loader: &Loader) -> Vec<(PathBuf, Result<()>)> {
+        self.levels
+            .iter_mut()
+            .filter_map(|level| {
+                let result = level.reload_if_changed(loader)?;
+                Some((level.file_path().to_path_buf(), result))
+            })
+            .collect()
+    }
+
+    fn draw_centered_text(&self, text: &str, y: f32, scale: f32, color: Vec3A) {
+        let width = self.text_renderer.measure(text, scale).x;
+        self.text_renderer
+            .draw(text, vec2((self.width as f32 - width) / 2.0, y), scale, color);
+    }
+
+    /// Draws the game stretched over a window framebuffer of `width` by `height` pixels.
+    pub fn resize(&mut self, width: i32, height: i32) {
+        self.post_processor.resize_window(width, height);
+    }
+
     pub fn set_bool(&mut self, key: usize, value: bool) {
         self.keys[key] = value;
+        if !value {
+            self.keys_processed[key] = false;
+        }
+    }
+
+    /// Returns true only once per key press, for actions that must not repeat while the key is held.
+    fn key_pressed_once(&mut self, key: i32) -> bool {
+        let key = key as usize;
+        if self.keys[key] && !self.keys_processed[key] {
+            self.keys_processed[key] = true;
+            return true;
+        }
+        false
+    }
+
+    fn handle_event(&mut self, event: Event, loader: &Loader) {
+        if self.change_state(event) {
+            self.reset_level(loader);
+        }
+    }
+
+    /// Passes `event` to the state machine and turns the sound to the volume of the new state.
+    /// Returns true when the level has to be (re)started.
+    fn change_state(&mut self, event: Event) -> bool {
+        let restart = self.state.handle(event);
+        audio::set_volume(volume_in(self.state.state()));
+        restart
+    }
+
+    /// Brings back every brick of the current level and refills the lives.
+    /// If the level file can no longer be read the level is played on as it is.
+    fn reset_level(&mut self, loader: &Loader) {
+        if let Err(error) = self.levels[self.state.level()].reload(loader) {
+            eprintln!("{}", error);
+        }
+        self.lives = INITIAL_LIVES;
+        self.trail.clear();
+        self.debris.clear();
+        self.reset_player();
+    }
+
+    /// Recentres the paddle with the ball stuck on top of it, without any power-ups or effects.
+    fn reset_player(&mut self) {
+        self.power_ups.clear();
+        self.effects = Effects::default();
+        self.apply_power_ups();
+        self.player.position = player_start_position(self.width, self.height);
+        self.ball.reset(ball_position_on(&self.player), INITIAL_BALL_VELOCITY);
+        // jump to the start instead of sliding there
+        self.previous_player = self.player.position;
+        self.previous_ball = self.ball.entity.position;
+    }
+
+    /// Sizes and tints the paddle and the ball for the power-ups active right now, so the ones
+    /// that ran out are undone. The paddle keeps its centre as it grows or shrinks.
+    fn apply_power_ups(&mut self) {
+        let is_active = |kind| self.power_ups.is_active(kind);
+        let width = if is_active(PowerUpKind::PadSizeIncrease) {
+            PLAYER_SIZE.x + PAD_SIZE_INCREASE
+        } else {
+            PLAYER_SIZE.x
+        };
+        let center = self.player.position.x + self.player.size.x / 2.0;
+        self.player.size.x = width;
+        self.player.position.x = (center - width / 2.0).clamp(0.0, self.width as f32 - width);
+
+        let white = vec3a(1.0, 1.0, 1.0);
+        let sticky = is_active(PowerUpKind::Sticky);
+        let pass_through = is_active(PowerUpKind::PassThrough);
+        self.player.set_color(if sticky { STICKY_COLOR } else { white });
+        self.ball
+            .entity
+            .set_color(if pass_through { PASS_THROUGH_COLOR } else { white });
+    }
+}
+
+fn player_start_position(width: u32, height: u32) -> Vec2 {
+    vec2(width as f32 / 2.0 - PLAYER_SIZE.x / 2.0, height as f32 - PLAYER_SIZE.y)
+}
+
+fn volume_in(state: GameState) -> f32 {
+    if state == GameState::Active {
+        1.0
+    } else {
+        MENU_VOLUME
     }
 }
 
-fn load_levels(loader: &Loader, width: u32, height: u32, number: usize) -> Vec<Level> {
-    let mut levels = Vec::with_capacity(number);
-    for i in 1..=number {
+/// Position of a ball resting on the centre of the paddle.
+fn ball_position_on(player: &Entity) -> Vec2 {
+    player.position + vec2(player.size.x / 2.0 - BALL_RADIUS, -BALL_RADIUS * 2.0)
+}
+
+fn load_levels(loader: &Loader, paths: &[PathBuf], width: u32, height: u32) -> Result<Vec<Level>> {
+    let mut levels = Vec::with_capacity(paths.len());
+    for path in paths {
         let mut level = Level::new();
-        level.load(
-            Path::new(&format!("resources/levels/level_{}.lvl", i)),
-            width,
-            height / 2,
-            loader,
-        );
+        level.load(path, width, height / 2, loader)?;
         levels.push(level);
     }
-    levels
+    Ok(levels)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use stb_image::image::{self, LoadResult};
+
+    use crate::{
+        audio::recording::{AudioCall, RecordingBackend as AudioRecordingBackend},
+        opengl::{
+            clear, clear_color,
+            recording::{GlCall, RecordingBackend},
+            software::{Framebuffer, SoftwareBackend},
+        },
+    };
+
+    use super::*;
+
+    fn triangles(first: i32, count: usize) -> GlCall {
+        GlCall::DrawArrays {
+            mode: gl::TRIANGLES,
+            first,
+            count,
+        }
+    }
+
+    /// Largest difference per channel still accepted, to allow for rounding.
+    const GOLDEN_TOLERANCE: u8 = 2;
+
+    /// Compares the framebuffer with `resources/golden/<name>.png`. Run the tests with
+    /// `UPDATE_GOLDEN=1` to write the current output as the new golden image instead.
+    fn assert_matches_golden(framebuffer: &Framebuffer, name: &str) {
+        let path = PathBuf::from(format!("resources/golden/{}.png", name));
+        let actual = framebuffer.to_rgba();
+        if env::var_os("UPDATE_GOLDEN").is_some() {
+            framebuffer.save_png(&path);
+            return;
+        }
+
+        let LoadResult::ImageU8(golden) = image::load(&path) else {
+            panic!("Error reading golden image: {}", path.display());
+        };
+        assert_eq!(
+            (golden.width, golden.height),
+            (framebuffer.width(), framebuffer.height()),
+            "Size differs from {}",
+            path.display()
+        );
+
+        let different = actual
+            .chunks_exact(4)
+            .zip(golden.data.chunks_exact(4))
+            .filter(|(actual, golden)| {
+                actual
+                    .iter()
+                    .zip(*golden)
+                    .any(|(a, g)| a.abs_diff(*g) > GOLDEN_TOLERANCE)
+            })
+            .count();
+        if different > 0 {
+            let actual_path = env::temp_dir().join(format!("{}.actual.png", name));
+            framebuffer.save_png(&actual_path);
+            panic!(
+                "{} pixels differ from {}, the output was written to {}",
+                different,
+                path.display(),
+                actual_path.display()
+            );
+        }
+    }
+
+    fn render_headless(width: usize, height: usize, events: &[Event]) -> Framebuffer {
+        let framebuffer = SoftwareBackend::install(width, height);
+        let mut loader = Loader::new();
+        let mut game = Game::new(width as u32, height as u32, &mut loader).unwrap();
+        for &event in events {
+            game.handle_event(event, &loader);
+        }
+
+        clear_color(0.0, 0.0, 0.0, 1.0);
+        clear(gl::COLOR_BUFFER_BIT);
+        game.render(&mut loader, 1.0);
+        framebuffer
+    }
+
+    #[test]
+    fn renders_the_menu_like_the_golden_image() {
+        assert_matches_golden(&render_headless(400, 300, &[]), "menu");
+    }
+
+    #[test]
+    fn renders_a_level_like_the_golden_image() {
+        assert_matches_golden(&render_headless(400, 300, &[Event::Start]), "level_1");
+    }
+
+    #[test]
+    fn rendering_a_level_draws_the_bricks_instanced() {
+        let recorder = RecordingBackend::install();
+        let mut loader = Loader::new();
+        let mut game = Game::new(800, 600, &mut loader).unwrap();
+        game.handle_event(Event::Start, &loader);
+
+        recorder.clear();
+        game.render(&mut loader, 1.0);
+
+        // the background, then level 1's 99 breakable and 7 solid bricks from the sprite atlas in
+        // one instanced call, then the paddle and the ball
+        assert_eq!(
+            recorder.draw_calls(),
+            vec![
+                triangles(0, 6),
+                GlCall::DrawArraysInstanced {
+                    mode: gl::TRIANGLES,
+                    first: 0,
+                    count: 6,
+                    instance_count: 99 + 7,
+                },
+                triangles(0, 2 * 6),
+                // the scene through the post-processing effects
+                triangles(0, 6),
+                // "Lives: 3", the space has no quad
+                triangles(0, 7 * 6),
+            ]
+        );
+    }
+
+    #[test]
+    fn the_released_ball_leaves_a_trail_drawn_additively() {
+        let recorder = RecordingBackend::install();
+        let mut loader = Loader::new();
+        let mut game = Game::new(800, 600, &mut loader).unwrap();
+        game.handle_event(Event::Start, &loader);
+
+        // no trail while the ball is stuck to the paddle
+        game.update(0.01);
+        assert_eq!(game.trail.live().count(), 0);
+
+        game.set_bool(KEY_SPACE as usize, true);
+        game.process_input(0.01, &loader);
+        for _ in 0..3 {
+            game.update(0.01);
+        }
+        assert_eq!(game.trail.live().count(), 3 * TRAIL_PARTICLES_PER_UPDATE);
+
+        recorder.clear();
+        game.render(&mut loader, 1.0);
+        let calls = recorder.calls();
+        let draw = calls
+            .iter()
+            .rposition(|call| matches!(call, GlCall::DrawArraysInstanced { .. }))
+            .expect("the trail is drawn instanced");
+        assert_eq!(
+            calls[draw],
+            GlCall::DrawArraysInstanced {
+                mode: gl::TRIANGLES,
+                first: 0,
+                count: 6,
+                instance_count: 3 * TRAIL_PARTICLES_PER_UPDATE,
+            }
+        );
+        assert!(calls[..draw].contains(&GlCall::BlendFunc {
+            s_factor: gl::SRC_ALPHA,
+            d_factor: gl::ONE,
+        }));
+        assert_eq!(
+            calls[draw + 1],
+            GlCall::BlendFunc {
+                s_factor: gl::SRC_ALPHA,
+                d_factor: gl::ONE_MINUS_SRC_ALPHA,
+            }
+        );
+    }
+
+    #[test]
+    fn overlapping_power_ups_are_undone_as_they_run_out() {
+        let _recorder = RecordingBackend::install();
+        let mut loader = Loader::new();
+        let mut game = Game::new(800, 600, &mut loader).unwrap();
+        game.handle_event(Event::Start, &loader);
+        let center = game.player.aabb().center();
+        let white = vec3a(1.0, 1.0, 1.0);
+
+        game.power_ups.activate(PowerUpKind::PadSizeIncrease);
+        game.update(5.0);
+        game.power_ups.activate(PowerUpKind::Sticky);
+        game.update(0.0);
+        assert_eq!(game.player.size.x, PLAYER_SIZE.x + PAD_SIZE_INCREASE);
+        assert_eq!(game.player.aabb().center(), center);
+        assert_eq!(game.player.color(), STICKY_COLOR);
+
+        // the wider paddle runs out first, the sticky one stays on
+        game.update(5.0);
+        assert_eq!(game.player.size, PLAYER_SIZE);
+        assert_eq!(game.player.aabb().center(), center);
+        assert_eq!(game.player.color(), STICKY_COLOR);
+
+        game.update(15.0);
+        assert_eq!(game.player.color(), white);
+        assert_eq!(game.ball.entity.color(), white);
+    }
+
+    #[test]
+    fn hitting_bricks_plays_their_sounds() {
+        let _recorder = RecordingBackend::install();
+        let sounds = AudioRecordingBackend::install();
+        let mut loader = Loader::new();
+        let mut game = Game::new(800, 600, &mut loader).unwrap();
+        game.handle_event(Event::Start, &loader);
+
+        // right below the solid brick in the middle of level 1's fourth row
+        game.ball.reset(vec2(387.5, 150.0), vec2(0.0, -350.0));
+        game.ball.stuck = false;
+        game.update(0.02);
+        assert_eq!(sounds.played(), vec![PathBuf::from("resources/audio/solid_hit.wav")]);
+
+        // right below the first brick of the bottom row
+        sounds.clear();
+        game.ball.reset(vec2(10.0, 300.0), vec2(0.0, -350.0));
+        game.ball.stuck = false;
+        game.update(0.02);
+        assert_eq!(sounds.played(), vec![PathBuf::from("resources/audio/brick_hit.wav")]);
+    }
+
+    #[test]
+    fn plays_quieter_outside_of_a_level() {
+        let _recorder = RecordingBackend::install();
+        let sounds = AudioRecordingBackend::install();
+        let mut loader = Loader::new();
+        let mut game = Game::new(800, 600, &mut loader).unwrap();
+        let volumes = || {
+            let calls = sounds.calls();
+            sounds.clear();
+            calls
+                .into_iter()
+                .filter(|call| matches!(call, AudioCall::SetVolume(_)))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(volumes(), [AudioCall::SetVolume(MENU_VOLUME)]);
+
+        game.handle_event(Event::Start, &loader);
+        assert_eq!(volumes(), [AudioCall::SetVolume(1.0)]);
+
+        game.lives = 1;
+        drop_the_ball(&mut game, &loader);
+        assert_eq!(game.state.state(), GameState::GameOver);
+        assert_eq!(volumes(), [AudioCall::SetVolume(MENU_VOLUME)]);
+    }
+
+    #[test]
+    fn stepping_with_the_same_seed_plays_out_the_same() {
+        let _recorder = RecordingBackend::install();
+        let play = || {
+            let mut loader = Loader::new();
+            let mut game = Game::with_seed(800, 600, &mut loader, 3).unwrap();
+            game.handle_event(Event::Start, &loader);
+            game.set_bool(KEY_SPACE as usize, true);
+            game.set_bool(KEY_A as usize, true);
+            for _ in 0..10 * 120 {
+                game.step(&loader);
+            }
+            (
+                game.ball.entity.position,
+                game.ball.entity.velocity,
+                game.player.position,
+                game.lives,
+                game.power_ups.falling().to_vec(),
+            )
+        };
+
+        let first = play();
+        assert_ne!(first.1, INITIAL_BALL_VELOCITY, "the ball bounced around");
+        assert_eq!(play(), first);
+    }
+
+    #[test]
+    fn the_ball_rides_on_the_paddle_until_launched() {
+        let _recorder = RecordingBackend::install();
+        let mut loader = Loader::new();
+        let mut game = Game::new(800, 600, &mut loader).unwrap();
+        game.handle_event(Event::Start, &loader);
+
+        let start = game.player.position;
+        game.set_bool(KEY_D as usize, true);
+        for _ in 0..10 {
+            game.step(&loader);
+        }
+        assert!(game.player.position.x > start.x);
+        assert!(game.ball.stuck);
+        assert_eq!(game.ball.entity.position, ball_position_on(&game.player));
+
+        game.set_bool(KEY_D as usize, false);
+        game.set_bool(KEY_SPACE as usize, true);
+        let launched_from = game.ball.entity.position;
+        game.step(&loader);
+        assert!(!game.ball.stuck);
+        assert_eq!(game.ball.entity.velocity, INITIAL_BALL_VELOCITY);
+        assert_eq!(game.ball.entity.position, launched_from + INITIAL_BALL_VELOCITY * STEP);
+    }
+
+    /// Sends the launched ball below the bottom edge and steps the game once.
+    fn drop_the_ball(game: &mut Game, loader: &Loader) {
+        game.ball.stuck = false;
+        game.ball.entity.position = vec2(400.0, 650.0);
+        game.ball.entity.velocity = vec2(0.0, 350.0);
+        game.step(loader);
+    }
+
+    #[test]
+    fn dropping_the_ball_takes_a_life_and_recentres_the_paddle_and_ball() {
+        let _recorder = RecordingBackend::install();
+        let mut loader = Loader::new();
+        let mut game = Game::new(800, 600, &mut loader).unwrap();
+        game.handle_event(Event::Start, &loader);
+        game.player.position.x = 0.0;
+
+        drop_the_ball(&mut game, &loader);
+
+        assert_eq!(game.lives(), INITIAL_LIVES - 1);
+        assert_eq!(game.state.state(), GameState::Active);
+        assert_eq!(game.player.position, player_start_position(800, 600));
+        assert!(game.ball.stuck);
+        assert_eq!(game.ball.entity.position, ball_position_on(&game.player));
+        assert_eq!(game.ball.entity.velocity, INITIAL_BALL_VELOCITY);
+    }
+
+    #[test]
+    fn dropping_the_ball_with_the_last_life_ends_the_game() {
+        let _recorder = RecordingBackend::install();
+        let mut loader = Loader::new();
+        let mut game = Game::new(800, 600, &mut loader).unwrap();
+        game.handle_event(Event::Start, &loader);
+
+        for lives in (0..INITIAL_LIVES).rev() {
+            drop_the_ball(&mut game, &loader);
+            assert_eq!(game.lives(), lives);
+        }
+        assert_eq!(game.state.state(), GameState::GameOver);
+    }
+
+    #[test]
+    fn restarting_brings_back_the_bricks_and_refills_the_lives() {
+        let _recorder = RecordingBackend::install();
+        let mut loader = Loader::new();
+        let mut game = Game::new(800, 600, &mut loader).unwrap();
+        game.handle_event(Event::Start, &loader);
+        let hits = collide_with_level(&mut game, vec2(79.5, 303.0), vec2(100.0, -350.0));
+        assert_eq!(hits, vec![(true, false)]);
+        for _ in 0..INITIAL_LIVES {
+            drop_the_ball(&mut game, &loader);
+        }
+        assert_eq!(game.state.state(), GameState::GameOver);
+
+        game.set_bool(KEY_R as usize, true);
+        game.step(&loader);
+
+        assert_eq!(game.state.state(), GameState::Active);
+        assert_eq!(game.lives(), INITIAL_LIVES);
+        assert!(game.ball.stuck);
+        // the brick broken before is back
+        let hits = collide_with_level(&mut game, vec2(79.5, 303.0), vec2(100.0, -350.0));
+        assert_eq!(hits, vec![(true, false)]);
+    }
+
+    /// Puts the ball's centre at `center` heading along `velocity`, then collides it with level 1,
+    /// whose bricks are 53 by 37 pixels. Returns whether each brick hit is destroyed and solid.
+    fn collide_with_level(game: &mut Game, center: Vec2, velocity: Vec2) -> Vec<(bool, bool)> {
+        game.ball.reset(center - BALL_RADIUS, velocity);
+        game.ball.stuck = false;
+        game.levels[0]
+            .collide_ball(&mut game.ball, false)
+            .iter()
+            .map(|brick| (brick.is_destroyed(), brick.is_solid()))
+            .collect()
+    }
+
+    #[test]
+    fn the_ball_breaks_bricks_and_bounces_off_them() {
+        let _recorder = RecordingBackend::install();
+        let mut loader = Loader::new();
+        let mut game = Game::new(800, 600, &mut loader).unwrap();
+        game.handle_event(Event::Start, &loader);
+
+        // from below into the second brick of the bottom row, which ends at y = 296
+        let hits = collide_with_level(&mut game, vec2(79.5, 303.0), vec2(100.0, -350.0));
+        assert_eq!(hits, vec![(true, false)]);
+        assert_eq!(game.ball.entity.velocity, vec2(100.0, 350.0));
+        assert_eq!(game.ball.circle().center, vec2(79.5, 296.0 + BALL_RADIUS));
+
+        // the destroyed brick is out of the way
+        let hits = collide_with_level(&mut game, vec2(79.5, 303.0), vec2(100.0, -350.0));
+        assert_eq!(hits, vec![]);
+        assert_eq!(game.ball.entity.velocity, vec2(100.0, -350.0));
+    }
+
+    #[test]
+    fn the_ball_bounces_off_solid_bricks_without_breaking_them() {
+        let _recorder = RecordingBackend::install();
+        let mut loader = Loader::new();
+        let mut game = Game::new(800, 600, &mut loader).unwrap();
+        game.handle_event(Event::Start, &loader);
+
+        // from the left into the solid brick in the middle of the fourth row, which starts at x = 371
+        for _ in 0..2 {
+            let hits = collide_with_level(&mut game, vec2(364.0, 129.5), vec2(350.0, 0.0));
+            assert_eq!(hits, vec![(false, true)]);
+            assert_eq!(game.ball.entity.velocity, vec2(-350.0, 0.0));
+            assert_eq!(game.ball.circle().center, vec2(371.0 - BALL_RADIUS, 129.5));
+        }
+        assert!(!game.levels[0].is_completed());
+    }
 }
@@ -0,0 +1,388 @@
+use std::{collections::HashMap, rc::Rc};
+
+use super::{
+    atlas::{Rect, UvRect},
+    sprite_renderer::Sprite,
+};
+
+/// What a clip does after its last frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Playback {
+    /// Starts over from the first frame.
+    Loop,
+    /// Stays on the last frame.
+    Once,
+    /// Plays backwards to the first frame, then forwards again.
+    PingPong,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Frame {
+    pub uv: UvRect,
+    /// Seconds the frame is shown for.
+    pub duration: f32,
+}
+
+/// A named sequence of the frames of a sheet, by index. A frame can be used more than once.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Clip {
+    pub frames: Vec<usize>,
+    pub playback: Playback,
+}
+
+/// The frames of an animated sprite, all cut out of one image, and the clips they are played in.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpriteSheet {
+    sprite: Sprite,
+    frames: Vec<Frame>,
+    clips: HashMap<String, Clip>,
+}
+
+impl SpriteSheet {
+    /// Reads the frames and clips of a sheet cut out of `sprite`, a texture or an image packed into
+    /// an atlas. One entry per line, `#` starts a comment:
+    ///
+    /// ```text
+    /// # x y width height in texels of the image, then the seconds the frame is shown for
+    /// frame 0 0 128 128 0.05
+    /// frame 128 0 128 128 0.05
+    /// # name, loop, once or ping_pong, then the frames by index from 0, defined above
+    /// clip crack once 0 1
+    /// ```
+    pub fn parse(source: &str, sprite: Sprite) -> Result<SpriteSheet, String> {
+        let size = sprite.size().round();
+        let (image_width, image_height) = (size.x as u32, size.y as u32);
+        let mut frames = Vec::new();
+        let mut clips = HashMap::new();
+
+        for (line_no, line) in source.lines().enumerate() {
+            let error = |reason: String| format!("line {}: {}", line_no + 1, reason);
+            let mut words = line.split('#').next().unwrap_or("").split_whitespace();
+
+            match words.next() {
+                None => continue,
+                Some("frame") => {
+                    let words: Vec<&str> = words.collect();
+                    let [x, y, width, height, duration] = words[..] else {
+                        return Err(error("expected frame x y width height seconds".to_string()));
+                    };
+                    let texels = |word: &str| {
+                        word.parse::<u32>()
+                            .map_err(|_| error(format!("invalid texel coordinate {}", word)))
+                    };
+                    let rect = Rect {
+                        x: texels(x)?,
+                        y: texels(y)?,
+                        width: texels(width)?,
+                        height: texels(height)?,
+                    };
+                    let duration = duration
+                        .parse::<f32>()
+                        .ok()
+                        .filter(|duration| *duration > 0.0)
+                        .ok_or_else(|| error(format!("invalid duration {}, expected seconds above 0", duration)))?;
+
+                    if rect.width == 0 || rect.height == 0 {
+                        return Err(error("empty frame".to_string()));
+                    }
+                    if rect.x + rect.width > image_width || rect.y + rect.height > image_height {
+                        return Err(error(format!(
+                            "frame {}x{} at {},{} is outside the {}x{} image",
+                            rect.width, rect.height, rect.x, rect.y, image_width, image_height
+                        )));
+                    }
+                    frames.push(Frame {
+                        uv: sprite.uv.sub(UvRect::of(rect, image_width, image_height)),
+                        duration,
+                    });
+                }
+                Some("clip") => {
+                    let (Some(name), Some(playback)) = (words.next(), words.next()) else {
+                        return Err(error("expected clip name playback frames".to_string()));
+                    };
+                    let playback = match playback {
+                        "loop" => Playback::Loop,
+                        "once" => Playback::Once,
+                        "ping_pong" => Playback::PingPong,
+                        _ => return Err(error(format!("unknown playback {}", playback))),
+                    };
+                    let clip_frames = words
+                        .map(|word| {
+                            word.parse::<usize>()
+                                .ok()
+                                .filter(|&frame| frame < frames.len())
+                                .ok_or_else(|| error(format!("no frame {}", word)))
+                        })
+                        .collect::<Result<Vec<_>, _>>()?;
+                    if clip_frames.is_empty() {
+                        return Err(error(format!("clip {} has no frames", name)));
+                    }
+
+                    let clip = Clip {
+                        frames: clip_frames,
+                        playback,
+                    };
+                    if clips.insert(name.to_string(), clip).is_some() {
+                        return Err(error(format!("clip {} is defined twice", name)));
+                    }
+                }
+                Some(entry) => return Err(error(format!("unknown entry {}", entry))),
+            }
+        }
+
+        if frames.is_empty() {
+            return Err("no frames".to_string());
+        }
+        Ok(SpriteSheet { sprite, frames, clips })
+    }
+
+    pub fn clip(&self, name: &str) -> Option<&Clip> {
+        self.clips.get(name)
+    }
+
+    /// The sprite showing only the given frame.
+    pub fn sprite(&self, frame: usize) -> Sprite {
+        Sprite {
+            texture: self.sprite.texture.clone(),
+            uv: self.frames[frame].uv,
+        }
+    }
+}
+
+/// Plays the clips of a sheet, one at a time, as time is passed to `update`.
+/// Shows the first frame of the sheet until a clip is played.
+#[derive(Debug, Clone)]
+pub struct Animation {
+    sheet: Rc<SpriteSheet>,
+    clip: Option<String>,
+    /// Index into the frames of the clip.
+    position: usize,
+    /// Seconds the current frame has been shown for.
+    elapsed: f32,
+    /// Whether a ping-pong clip is playing forwards.
+    forward: bool,
+    finished: bool,
+}
+
+impl Animation {
+    pub fn new(sheet: Rc<SpriteSheet>) -> Animation {
+        Animation {
+            sheet,
+            clip: None,
+            position: 0,
+            elapsed: 0.0,
+            forward: true,
+            finished: false,
+        }
+    }
+
+    /// Starts the clip from its first frame, even if it is already playing.
+    pub fn play(&mut self, clip: &str) {
+        if self.sheet.clip(clip).is_none() {
+            panic!("Clip not found: {}", clip);
+        }
+        self.clip = Some(clip.to_string());
+        self.position = 0;
+        self.elapsed = 0.0;
+        self.forward = true;
+        self.finished = false;
+    }
+
+    /// True while a clip is playing, until a clip played once reaches its last frame.
+    pub fn is_playing(&self) -> bool {
+        self.clip.is_some() && !self.finished
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        let sheet = self.sheet.clone();
+        let Some(clip) = self.clip.as_deref().and_then(|clip| sheet.clip(clip)) else {
+            return;
+        };
+        if self.finished {
+            return;
+        }
+
+        // a long frame time can skip several frames
+        self.elapsed += dt;
+        loop {
+            let duration = sheet.frames[clip.frames[self.position]].duration;
+            if self.elapsed < duration {
+                break;
+            }
+            self.elapsed -= duration;
+            if !self.advance(clip) {
+                self.finished = true;
+                self.elapsed = 0.0;
+                break;
+            }
+        }
+    }
+
+    /// Moves on to the next frame of the clip, returns false when a clip played once has ended.
+    fn advance(&mut self, clip: &Clip) -> bool {
+        let last = clip.frames.len() - 1;
+        match clip.playback {
+            Playback::Loop => self.position = if self.position == last { 0 } else { self.position + 1 },
+            Playback::Once => {
+                if self.position == last {
+                    return false;
+                }
+                self.position += 1;
+            }
+            Playback::PingPong => {
+                if last == 0 {
+                    return true;
+                }
+                if self.position == last {
+                    self.forward = false;
+                } else if self.position == 0 {
+                    self.forward = true;
+                }
+                if self.forward {
+                    self.position += 1;
+                } else {
+                    self.position -= 1;
+                }
+            }
+        }
+        true
+    }
+
+    /// The frame of the sheet shown right now.
+    pub fn frame(&self) -> usize {
+        match self.clip.as_deref().and_then(|clip| self.sheet.clip(clip)) {
+            Some(clip) => clip.frames[self.position],
+            None => 0,
+        }
+    }
+
+    pub fn sprite(&self) -> Sprite {
+        self.sheet.sprite(self.frame())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::vec2;
+
+    use crate::{game::texture2d::Texture2D, opengl::recording::RecordingBackend};
+
+    use super::*;
+
+    /// A sprite showing the top right quarter of a 256x256 texture, like an image in an atlas.
+    fn packed_sprite() -> Sprite {
+        let texture = Texture2D::new(
+            gl::RGBA,
+            gl::RGBA,
+            gl::CLAMP_TO_EDGE,
+            gl::CLAMP_TO_EDGE,
+            gl::LINEAR,
+            gl::LINEAR,
+        );
+        texture.generate(256, 256, vec![0; 256 * 256 * 4]);
+        Sprite {
+            texture: Rc::new(texture),
+            uv: UvRect {
+                min: vec2(0.5, 0.0),
+                max: vec2(1.0, 0.5),
+            },
+        }
+    }
+
+    /// Plays `clip` of a sheet of three frames, each shown for a quarter of a second, and
+    /// returns the frame shown after each of `steps` quarter seconds.
+    fn frames_played(clip: &str, steps: usize) -> Vec<usize> {
+        let _recorder = RecordingBackend::install();
+        let sheet = SpriteSheet::parse(
+            "frame 0 0 32 32 0.25\nframe 32 0 32 32 0.25\nframe 64 0 32 32 0.25
+            clip spin loop 0 1 2
+            clip break once 0 1 2
+            clip pulse ping_pong 0 1 2",
+            packed_sprite(),
+        )
+        .unwrap();
+
+        let mut animation = Animation::new(Rc::new(sheet));
+        animation.play(clip);
+        (0..steps)
+            .map(|_| {
+                animation.update(0.25);
+                animation.frame()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn cuts_frames_out_of_the_image_the_sheet_was_packed_into() {
+        let _recorder = RecordingBackend::install();
+        let sheet = SpriteSheet::parse("# a comment\nframe 64 0 64 32 0.1 # the second half", packed_sprite()).unwrap();
+
+        assert_eq!(
+            sheet.frames[0].uv,
+            UvRect {
+                min: vec2(0.75, 0.0),
+                max: vec2(1.0, 0.125),
+            }
+        );
+    }
+
+    #[test]
+    fn plays_clips_in_each_playback_mode() {
+        assert_eq!(frames_played("spin", 7), vec![1, 2, 0, 1, 2, 0, 1]);
+        assert_eq!(frames_played("break", 4), vec![1, 2, 2, 2]);
+        assert_eq!(frames_played("pulse", 7), vec![1, 2, 1, 0, 1, 2, 1]);
+    }
+
+    #[test]
+    fn clips_played_once_finish_on_their_last_frame() {
+        let _recorder = RecordingBackend::install();
+        let sheet = SpriteSheet::parse(
+            "frame 0 0 8 8 0.1\nframe 8 0 8 8 0.1\nclip crack once 1 0",
+            packed_sprite(),
+        )
+        .unwrap();
+        let mut animation = Animation::new(Rc::new(sheet));
+        assert!(!animation.is_playing());
+
+        animation.play("crack");
+        animation.update(0.05);
+        assert!(animation.is_playing());
+        assert_eq!(animation.frame(), 1);
+
+        // skips straight past the last frame
+        animation.update(1.0);
+        assert!(!animation.is_playing());
+        assert_eq!(animation.frame(), 0);
+        assert_eq!(animation.clip.as_deref(), Some("crack"));
+    }
+
+    #[test]
+    fn rejects_invalid_sheets() {
+        let _recorder = RecordingBackend::install();
+        let parse = |source: &str| SpriteSheet::parse(source, packed_sprite()).unwrap_err();
+
+        assert_eq!(parse(""), "no frames");
+        assert_eq!(
+            parse("frame 0 0 8 8"),
+            "line 1: expected frame x y width height seconds"
+        );
+        assert_eq!(
+            parse("frame 0 0 8 8 0"),
+            "line 1: invalid duration 0, expected seconds above 0"
+        );
+        assert_eq!(
+            parse("frame 100 0 64 8 0.1"),
+            "line 1: frame 64x8 at 100,0 is outside the 128x128 image"
+        );
+        assert_eq!(parse("frame 0 0 8 8 0.1\nclip a once 0 1"), "line 2: no frame 1");
+        assert_eq!(
+            parse("frame 0 0 8 8 0.1\nclip a backwards 0"),
+            "line 2: unknown playback backwards"
+        );
+        assert_eq!(
+            parse("frame 0 0 8 8 0.1\nclip a loop 0\nclip a once 0"),
+            "line 3: clip a is defined twice"
+        );
+        assert_eq!(parse("frames 0 0 8 8 0.1"), "line 1: unknown entry frames");
+    }
+}
@@ -0,0 +1,411 @@
+use std::collections::HashMap;
+
+use glam::{vec2, vec4, Vec2, Vec4};
+
+/// A rectangle of texels, with its origin at the top left.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rect {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// The part of a texture a sprite is drawn with, in texture coordinates.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct UvRect {
+    pub min: Vec2,
+    pub max: Vec2,
+}
+
+impl UvRect {
+    /// The whole texture.
+    pub const FULL: UvRect = UvRect {
+        min: Vec2::ZERO,
+        max: Vec2::ONE,
+    };
+
+    /// `rect` of a texture `width` by `height` texels in size.
+    pub fn of(rect: Rect, width: u32, height: u32) -> UvRect {
+        let size = vec2(width as f32, height as f32);
+        UvRect {
+            min: vec2(rect.x as f32, rect.y as f32) / size,
+            max: vec2((rect.x + rect.width) as f32, (rect.y + rect.height) as f32) / size,
+        }
+    }
+
+    /// `min` in xy and `max` in zw, as the shaders take it.
+    pub fn to_vec4(self) -> Vec4 {
+        vec4(self.min.x, self.min.y, self.max.x, self.max.y)
+    }
+
+    /// Maps a point of the unit square onto this rectangle.
+    pub fn lerp(&self, point: Vec2) -> Vec2 {
+        self.min + (self.max - self.min) * point
+    }
+
+    /// The part of this rectangle that `rect` covers of the unit square, e.g. a frame of a sprite
+    /// sheet that was itself packed into an atlas.
+    pub fn sub(&self, rect: UvRect) -> UvRect {
+        UvRect {
+            min: self.lerp(rect.min),
+            max: self.lerp(rect.max),
+        }
+    }
+}
+
+/// Where `pack` put a rectangle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Placement {
+    pub page: usize,
+    pub rect: Rect,
+}
+
+/// A row of a page, as high as the first rectangle put on it.
+#[derive(Debug)]
+struct Shelf {
+    y: u32,
+    height: u32,
+    used_width: u32,
+}
+
+/// Packs rectangles of the given sizes onto as many `page_size` square pages as it takes, with
+/// `padding` texels of space around each one. Returns where each size went, in the same order.
+///
+/// Shelf packing, first fit, tallest first: the rectangles are sorted by height, then width,
+/// then their index, so the same sizes always end up in the same places.
+pub fn pack(sizes: &[(u32, u32)], page_size: u32, padding: u32) -> Result<Vec<Placement>, String> {
+    let mut order: Vec<usize> = (0..sizes.len()).collect();
+    order.sort_by_key(|&i| (std::cmp::Reverse(sizes[i].1), std::cmp::Reverse(sizes[i].0), i));
+
+    let mut pages: Vec<Vec<Shelf>> = Vec::new();
+    let mut placements = vec![None; sizes.len()];
+    for i in order {
+        let (width, height) = sizes[i];
+        let (padded_width, padded_height) = (width + 2 * padding, height + 2 * padding);
+        if padded_width > page_size || padded_height > page_size {
+            return Err(format!(
+                "a {}x{} image does not fit on a {}x{} page",
+                width, height, page_size, page_size
+            ));
+        }
+
+        let fits_on_shelf =
+            |shelf: &Shelf| padded_height <= shelf.height && shelf.used_width + padded_width <= page_size;
+        let fits_below = |shelves: &Vec<Shelf>| {
+            shelves.last().map_or(0, |shelf| shelf.y + shelf.height) + padded_height <= page_size
+        };
+
+        let mut placed = None;
+        for (page, shelves) in pages.iter_mut().enumerate() {
+            if let Some(shelf) = shelves.iter_mut().find(|shelf| fits_on_shelf(shelf)) {
+                placed = Some((page, shelf.used_width, shelf.y));
+                shelf.used_width += padded_width;
+                break;
+            }
+            if fits_below(shelves) {
+                let y = shelves.last().map_or(0, |shelf| shelf.y + shelf.height);
+                shelves.push(Shelf {
+                    y,
+                    height: padded_height,
+                    used_width: padded_width,
+                });
+                placed = Some((page, 0, y));
+                break;
+            }
+        }
+        let (page, x, y) = placed.unwrap_or_else(|| {
+            pages.push(vec![Shelf {
+                y: 0,
+                height: padded_height,
+                used_width: padded_width,
+            }]);
+            (pages.len() - 1, 0, 0)
+        });
+
+        placements[i] = Some(Placement {
+            page,
+            rect: Rect {
+                x: x + padding,
+                y: y + padding,
+                width,
+                height,
+            },
+        });
+    }
+
+    Ok(placements.into_iter().map(Option::unwrap).collect())
+}
+
+/// An RGBA8 image, rows from top to bottom.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Bitmap {
+    pub width: u32,
+    pub height: u32,
+    pub rgba: Vec<u8>,
+}
+
+impl Bitmap {
+    pub fn new(width: u32, height: u32) -> Bitmap {
+        Bitmap {
+            width,
+            height,
+            rgba: vec![0; width as usize * height as usize * 4],
+        }
+    }
+
+    /// Converts 8 bit image data with 1 to 4 channels, as `stb_image` loads it.
+    pub fn from_channels(width: u32, height: u32, channels: usize, data: &[u8]) -> Bitmap {
+        let rgba = data
+            .chunks_exact(channels)
+            .flat_map(|texel| match *texel {
+                [l] => [l, l, l, 255],
+                [l, a] => [l, l, l, a],
+                [r, g, b] => [r, g, b, 255],
+                [r, g, b, a] => [r, g, b, a],
+                _ => unreachable!("Images have 1 to 4 channels"),
+            })
+            .collect();
+        Bitmap { width, height, rgba }
+    }
+
+    /// Copies `image` with its top left corner at (`x`, `y`) and repeats its edge texels `padding`
+    /// texels outwards, so linear filtering at the edge of a sprite doesn't pick up its neighbours.
+    fn blit(&mut self, image: &Bitmap, x: u32, y: u32, padding: u32) {
+        let padding = padding as i64;
+        for row in -padding..image.height as i64 + padding {
+            for column in -padding..image.width as i64 + padding {
+                let source_x = column.clamp(0, image.width as i64 - 1) as usize;
+                let source_y = row.clamp(0, image.height as i64 - 1) as usize;
+                let source = (source_y * image.width as usize + source_x) * 4;
+
+                let target_x = (x as i64 + column) as usize;
+                let target_y = (y as i64 + row) as usize;
+                let target = (target_y * self.width as usize + target_x) * 4;
+                self.rgba[target..target + 4].copy_from_slice(&image.rgba[source..source + 4]);
+            }
+        }
+    }
+}
+
+/// Where an image ended up in an `Atlas`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Region {
+    pub page: usize,
+    pub rect: Rect,
+    pub uv: UvRect,
+}
+
+/// Images packed onto a few large pages, so sprites using any of them can be drawn without
+/// switching textures. Pages are cut down to the power of two sizes they need.
+#[derive(Debug, Clone)]
+pub struct Atlas {
+    pub pages: Vec<Bitmap>,
+    regions: HashMap<String, Region>,
+    padding: u32,
+}
+
+impl Atlas {
+    pub fn build(images: &[(String, Bitmap)], page_size: u32, padding: u32) -> Result<Atlas, String> {
+        let sizes: Vec<_> = images.iter().map(|(_, image)| (image.width, image.height)).collect();
+        let placements = pack(&sizes, page_size, padding)?;
+
+        let page_count = placements.iter().map(|placement| placement.page + 1).max().unwrap_or(0);
+        let mut pages: Vec<Bitmap> = (0..page_count)
+            .map(|page| {
+                let on_page = placements.iter().filter(|placement| placement.page == page);
+                let (width, height) = on_page.fold((1, 1), |(width, height), placement| {
+                    let rect = placement.rect;
+                    (
+                        width.max(rect.x + rect.width + padding),
+                        height.max(rect.y + rect.height + padding),
+                    )
+                });
+                Bitmap::new(width.next_power_of_two(), height.next_power_of_two())
+            })
+            .collect();
+
+        let mut regions = HashMap::new();
+        for ((name, image), placement) in images.iter().zip(placements) {
+            let page = &mut pages[placement.page];
+            page.blit(image, placement.rect.x, placement.rect.y, padding);
+            let region = Region {
+                page: placement.page,
+                rect: placement.rect,
+                uv: UvRect::of(placement.rect, page.width, page.height),
+            };
+            if regions.insert(name.clone(), region).is_some() {
+                return Err(format!("{} is packed twice", name));
+            }
+        }
+
+        Ok(Atlas {
+            pages,
+            regions,
+            padding,
+        })
+    }
+
+    pub fn region(&self, name: &str) -> Option<&Region> {
+        self.regions.get(name)
+    }
+
+    /// Copies a new version of a packed image over the old one and returns the page it is on.
+    /// The image has to keep its size, as everything else on the page stays where it is.
+    pub fn replace(&mut self, name: &str, image: &Bitmap) -> Result<usize, String> {
+        let region = *self
+            .regions
+            .get(name)
+            .ok_or_else(|| format!("{} is not in the atlas", name))?;
+        if (image.width, image.height) != (region.rect.width, region.rect.height) {
+            return Err(format!(
+                "the image changed size from {}x{} to {}x{}, restart to repack the atlas",
+                region.rect.width, region.rect.height, image.width, image.height
+            ));
+        }
+
+        self.pages[region.page].blit(image, region.rect.x, region.rect.y, self.padding);
+        Ok(region.page)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn overlap(a: Rect, b: Rect) -> bool {
+        a.x < b.x + b.width && b.x < a.x + a.width && a.y < b.y + b.height && b.y < a.y + a.height
+    }
+
+    fn solid(width: u32, height: u32, texel: [u8; 4]) -> Bitmap {
+        Bitmap {
+            width,
+            height,
+            rgba: texel.repeat((width * height) as usize),
+        }
+    }
+
+    #[test]
+    fn packs_tallest_first_on_shelves() {
+        let placements = pack(&[(10, 10), (30, 20), (20, 20), (50, 5)], 64, 0).unwrap();
+        let rects: Vec<_> = placements.iter().map(|placement| placement.rect).collect();
+
+        assert!(placements.iter().all(|placement| placement.page == 0));
+        assert_eq!(
+            rects,
+            vec![
+                Rect {
+                    x: 50,
+                    y: 0,
+                    width: 10,
+                    height: 10
+                },
+                Rect {
+                    x: 0,
+                    y: 0,
+                    width: 30,
+                    height: 20
+                },
+                Rect {
+                    x: 30,
+                    y: 0,
+                    width: 20,
+                    height: 20
+                },
+                Rect {
+                    x: 0,
+                    y: 20,
+                    width: 50,
+                    height: 5
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn padded_rects_never_overlap_and_spill_onto_new_pages() {
+        let sizes: Vec<_> = (0..40).map(|i| (5 + i * 7 % 23, 4 + i * 11 % 17)).collect();
+        let placements = pack(&sizes, 64, 1).unwrap();
+
+        assert!(placements.iter().any(|placement| placement.page > 0));
+        for (i, a) in placements.iter().enumerate() {
+            assert!(a.rect.x >= 1 && a.rect.x + a.rect.width < 64);
+            assert!(a.rect.y >= 1 && a.rect.y + a.rect.height < 64);
+            for b in &placements[i + 1..] {
+                let padded = |rect: Rect| Rect {
+                    x: rect.x - 1,
+                    y: rect.y - 1,
+                    width: rect.width + 2,
+                    height: rect.height + 2,
+                };
+                assert!(a.page != b.page || !overlap(padded(a.rect), padded(b.rect)));
+            }
+        }
+        assert_eq!(pack(&sizes, 64, 1).unwrap(), placements);
+    }
+
+    #[test]
+    fn images_larger_than_a_page_are_an_error() {
+        assert_eq!(
+            pack(&[(64, 10)], 64, 1).unwrap_err(),
+            "a 64x10 image does not fit on a 64x64 page"
+        );
+    }
+
+    #[test]
+    fn atlas_copies_images_with_extruded_edges() {
+        let red = [255, 0, 0, 255];
+        let blue = [0, 0, 255, 128];
+        let images = [
+            ("red".to_string(), solid(4, 4, red)),
+            ("blue".to_string(), solid(2, 2, blue)),
+        ];
+        let atlas = Atlas::build(&images, 16, 1).unwrap();
+
+        assert_eq!(atlas.pages.len(), 1);
+        let page = &atlas.pages[0];
+        assert_eq!((page.width, page.height), (16, 8));
+        let texel = |x: usize, y: usize| &page.rgba[(y * 16 + x) * 4..][..4];
+
+        let red_region = atlas.region("red").unwrap();
+        assert_eq!(
+            red_region.rect,
+            Rect {
+                x: 1,
+                y: 1,
+                width: 4,
+                height: 4
+            }
+        );
+        assert_eq!(red_region.uv.min, vec2(1.0 / 16.0, 1.0 / 8.0));
+        assert_eq!(red_region.uv.max, vec2(5.0 / 16.0, 5.0 / 8.0));
+        assert_eq!(texel(0, 0), red);
+        assert_eq!(texel(5, 5), red);
+
+        let blue_region = atlas.region("blue").unwrap();
+        assert_eq!((blue_region.rect.x, blue_region.rect.y), (7, 1));
+        assert_eq!(texel(6, 0), blue);
+        assert_eq!(texel(9, 3), blue);
+        assert_eq!(texel(10, 3), [0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn replacing_an_image_keeps_its_place() {
+        let images = [("a".to_string(), solid(2, 2, [1, 2, 3, 4]))];
+        let mut atlas = Atlas::build(&images, 8, 0).unwrap();
+
+        assert_eq!(atlas.replace("a", &solid(2, 2, [9, 9, 9, 9])), Ok(0));
+        assert_eq!(atlas.pages[0].rgba[..4], [9, 9, 9, 9]);
+        assert!(atlas.replace("a", &solid(3, 2, [0; 4])).is_err());
+        assert!(atlas.replace("b", &solid(2, 2, [0; 4])).is_err());
+    }
+
+    #[test]
+    fn grey_and_rgb_images_become_rgba() {
+        assert_eq!(Bitmap::from_channels(1, 1, 1, &[7]).rgba, [7, 7, 7, 255]);
+        assert_eq!(
+            Bitmap::from_channels(2, 1, 3, &[1, 2, 3, 4, 5, 6]).rgba,
+            [1, 2, 3, 255, 4, 5, 6, 255]
+        );
+    }
+}
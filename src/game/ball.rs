@@ -0,0 +1,241 @@
+use glam::{vec2, Vec2, Vec3A};
+
+use super::{
+    collision::{circle_aabb, Aabb, Circle, Collision, Direction},
+    entity::Entity,
+    sprite_renderer::Sprite,
+};
+
+/// Angle from vertical, in degrees, at which the ball leaves the paddle when it lands on its very edge.
+const MAX_PADDLE_DEFLECTION: f32 = 60.0;
+
+#[derive(Debug)]
+pub struct Ball {
+    pub entity: Entity,
+    pub radius: f32,
+    pub stuck: bool,
+}
+
+impl Ball {
+    pub fn new(position: Vec2, radius: f32, velocity: Vec2, sprite: Sprite, color: Vec3A) -> Ball {
+        let mut entity = Entity::new(position, vec2(radius * 2.0, radius * 2.0), sprite, color);
+        entity.velocity = velocity;
+
+        Ball {
+            entity,
+            radius,
+            stuck: true,
+        }
+    }
+
+    /// Moves the ball by its velocity and bounces it off the left, right and top walls.
+    /// A ball stuck to the paddle stays where it is.
+    pub fn move_(&mut self, dt: f32, window_width: u32) -> Vec2 {
+        if self.stuck {
+            return self.entity.position;
+        }
+
+        let entity = &mut self.entity;
+        entity.position += entity.velocity * dt;
+
+        let max_x = window_width as f32 - entity.size.x;
+        if entity.position.x <= 0.0 {
+            entity.velocity.x = -entity.velocity.x;
+            entity.position.x = 0.0;
+        } else if entity.position.x >= max_x {
+            entity.velocity.x = -entity.velocity.x;
+            entity.position.x = max_x;
+        }
+
+        if entity.position.y <= 0.0 {
+            entity.velocity.y = -entity.velocity.y;
+            entity.position.y = 0.0;
+        }
+
+        entity.position
+    }
+
+    /// Pushes the ball out of whatever it hit and reflects its velocity along the contact axis.
+    pub fn bounce(&mut self, collision: &Collision) {
+        let entity = &mut self.entity;
+        entity.position += collision.penetration;
+
+        match collision.direction {
+            Direction::Up => entity.velocity.y = -entity.velocity.y.abs(),
+            Direction::Down => entity.velocity.y = entity.velocity.y.abs(),
+            Direction::Left => entity.velocity.x = -entity.velocity.x.abs(),
+            Direction::Right => entity.velocity.x = entity.velocity.x.abs(),
+        }
+    }
+
+    /// Sends the ball back up at an angle depending on where it landed on the paddle.
+    /// Returns false if the ball did not touch the paddle.
+    pub fn bounce_off_paddle(&mut self, paddle: &Entity) -> bool {
+        match paddle_bounce(&self.circle(), self.entity.velocity, &paddle.aabb()) {
+            Some((center, velocity)) => {
+                self.entity.position = center - self.radius;
+                self.entity.velocity = velocity;
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn reset(&mut self, position: Vec2, velocity: Vec2) {
+        self.entity.position = position;
+        self.entity.velocity = velocity;
+        self.stuck = true;
+    }
+}
+
+/// Returns the new centre and velocity of a ball that hit the paddle, or `None` if it missed.
+///
+/// The further from the paddle centre the ball lands, the flatter it leaves, while keeping its speed.
+/// The ball always leaves upwards from above the paddle, so a ball that got into the paddle from the
+/// side can't get stuck inside it flipping its velocity every frame.
+pub fn paddle_bounce(ball: &Circle, velocity: Vec2, paddle: &Aabb) -> Option<(Vec2, Vec2)> {
+    circle_aabb(ball, paddle)?;
+
+    let offset = ((ball.center.x - paddle.center().x) / (paddle.size.x / 2.0)).clamp(-1.0, 1.0);
+    let angle = (offset * MAX_PADDLE_DEFLECTION).to_radians();
+    let velocity = vec2(angle.sin(), -angle.cos()) * velocity.length();
+    let center = vec2(ball.center.x, ball.center.y.min(paddle.min().y - ball.radius));
+
+    Some((center, velocity))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use crate::{game::texture2d::Texture2D, opengl::recording::RecordingBackend};
+
+    use super::*;
+
+    const EPSILON: f32 = 1e-3;
+
+    /// A ball 25 pixels across at `position`, already launched.
+    fn ball(position: Vec2, velocity: Vec2) -> Ball {
+        let texture = Texture2D::new(gl::RGBA, gl::RGBA, gl::REPEAT, gl::REPEAT, gl::LINEAR, gl::LINEAR);
+        let mut ball = Ball::new(position, 12.5, velocity, Sprite::whole(Rc::new(texture)), Vec3A::ONE);
+        ball.stuck = false;
+        ball
+    }
+
+    #[test]
+    fn a_stuck_ball_does_not_move() {
+        let _recorder = RecordingBackend::install();
+        let mut ball = ball(vec2(100.0, 100.0), vec2(100.0, -350.0));
+        ball.stuck = true;
+        assert_eq!(ball.move_(1.0, 800), vec2(100.0, 100.0));
+        assert_eq!(ball.entity.velocity, vec2(100.0, -350.0));
+    }
+
+    #[test]
+    fn moves_by_its_velocity() {
+        let _recorder = RecordingBackend::install();
+        let mut ball = ball(vec2(100.0, 100.0), vec2(100.0, -50.0));
+        assert_eq!(ball.move_(0.5, 800), vec2(150.0, 75.0));
+        assert_eq!(ball.entity.velocity, vec2(100.0, -50.0));
+    }
+
+    #[test]
+    fn bounces_off_the_side_walls() {
+        let _recorder = RecordingBackend::install();
+        let mut ball = ball(vec2(10.0, 100.0), vec2(-100.0, 50.0));
+        assert_eq!(ball.move_(0.5, 800), vec2(0.0, 125.0));
+        assert_eq!(ball.entity.velocity, vec2(100.0, 50.0));
+
+        let mut ball = self::ball(vec2(770.0, 100.0), vec2(100.0, 50.0));
+        assert_eq!(ball.move_(0.5, 800), vec2(775.0, 125.0));
+        assert_eq!(ball.entity.velocity, vec2(-100.0, 50.0));
+    }
+
+    #[test]
+    fn bounces_off_the_top_wall_but_not_the_bottom() {
+        let _recorder = RecordingBackend::install();
+        let mut ball = ball(vec2(100.0, 10.0), vec2(50.0, -100.0));
+        assert_eq!(ball.move_(0.5, 800), vec2(125.0, 0.0));
+        assert_eq!(ball.entity.velocity, vec2(50.0, 100.0));
+
+        // falling out of the bottom is for the game to notice
+        ball.entity.position.y = 590.0;
+        assert_eq!(ball.move_(0.5, 800), vec2(150.0, 640.0));
+        assert_eq!(ball.entity.velocity, vec2(50.0, 100.0));
+    }
+
+    fn paddle() -> Aabb {
+        Aabb::new(vec2(100.0, 580.0), vec2(100.0, 20.0))
+    }
+
+    fn incoming() -> Vec2 {
+        vec2(100.0, 350.0)
+    }
+
+    #[test]
+    fn ball_that_misses_the_paddle_is_not_bounced() {
+        let ball = Circle::new(vec2(150.0, 500.0), 12.5);
+        assert_eq!(paddle_bounce(&ball, incoming(), &paddle()), None);
+    }
+
+    #[test]
+    fn centre_hit_goes_straight_up() {
+        let ball = Circle::new(vec2(150.0, 570.0), 12.5);
+        let (center, velocity) = paddle_bounce(&ball, incoming(), &paddle()).unwrap();
+
+        assert!(velocity.abs_diff_eq(vec2(0.0, -incoming().length()), EPSILON));
+        assert_eq!(center, vec2(150.0, 567.5));
+    }
+
+    #[test]
+    fn edge_hits_leave_at_the_maximum_angle() {
+        let speed = incoming().length();
+        let angle = MAX_PADDLE_DEFLECTION.to_radians();
+
+        let right = Circle::new(vec2(200.0, 570.0), 12.5);
+        let (_, velocity) = paddle_bounce(&right, incoming(), &paddle()).unwrap();
+        assert!(velocity.abs_diff_eq(vec2(angle.sin(), -angle.cos()) * speed, EPSILON));
+
+        let left = Circle::new(vec2(100.0, 570.0), 12.5);
+        let (_, velocity) = paddle_bounce(&left, incoming(), &paddle()).unwrap();
+        assert!(velocity.abs_diff_eq(vec2(-angle.sin(), -angle.cos()) * speed, EPSILON));
+    }
+
+    #[test]
+    fn hit_between_centre_and_edge_keeps_the_speed() {
+        let ball = Circle::new(vec2(175.0, 570.0), 12.5);
+        let (_, velocity) = paddle_bounce(&ball, incoming(), &paddle()).unwrap();
+
+        assert!(velocity.x > 0.0 && velocity.y < 0.0);
+        assert!((velocity.length() - incoming().length()).abs() < EPSILON);
+        let angle = velocity.x.atan2(-velocity.y).to_degrees();
+        assert!((angle - MAX_PADDLE_DEFLECTION / 2.0).abs() < EPSILON);
+    }
+
+    #[test]
+    fn corner_hit_is_clamped_to_the_maximum_angle() {
+        let ball = Circle::new(vec2(208.0, 576.0), 12.5);
+        let (center, velocity) = paddle_bounce(&ball, incoming(), &paddle()).unwrap();
+
+        let angle = velocity.x.atan2(-velocity.y).to_degrees();
+        assert!((angle - MAX_PADDLE_DEFLECTION).abs() < EPSILON);
+        assert!(center.y + 12.5 <= paddle().min().y);
+    }
+
+    #[test]
+    fn ball_inside_the_paddle_does_not_get_stuck() {
+        let paddle = paddle();
+        let mut ball = Circle::new(vec2(110.0, 590.0), 12.5);
+        let mut velocity = incoming();
+
+        let (center, new_velocity) = paddle_bounce(&ball, velocity, &paddle).unwrap();
+        assert!(new_velocity.y < 0.0);
+        assert_eq!(center.y, paddle.min().y - ball.radius);
+
+        // a single step away, the ball is already clear of the paddle
+        ball.center = center;
+        velocity = new_velocity;
+        ball.center += velocity * (1.0 / 60.0);
+        assert_eq!(paddle_bounce(&ball, velocity, &paddle), None);
+    }
+}
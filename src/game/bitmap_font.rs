@@ -0,0 +1,285 @@
+use std::collections::HashMap;
+
+use glam::{vec2, Vec2};
+
+/// Where a glyph sits in the font atlas and how it is placed relative to the pen, in pixels.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Glyph {
+    pub atlas_position: Vec2,
+    pub size: Vec2,
+    pub offset: Vec2,
+    pub advance: f32,
+}
+
+/// A laid out glyph: the screen rectangle to draw and the atlas rectangle in texture coordinates.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GlyphQuad {
+    pub position: Vec2,
+    pub size: Vec2,
+    pub uv_min: Vec2,
+    pub uv_max: Vec2,
+}
+
+/// Font in the text BMFont format: a single atlas page plus glyph metrics.
+#[derive(Debug)]
+pub struct BitmapFont {
+    pub page: String,
+    line_height: f32,
+    atlas_size: Vec2,
+    glyphs: HashMap<char, Glyph>,
+    kernings: HashMap<(char, char), f32>,
+}
+
+const FALLBACK_CHAR: char = '?';
+
+impl BitmapFont {
+    pub fn parse(source: &str) -> BitmapFont {
+        let mut font = BitmapFont {
+            page: String::new(),
+            line_height: 0.0,
+            atlas_size: Vec2::ZERO,
+            glyphs: HashMap::new(),
+            kernings: HashMap::new(),
+        };
+
+        for line in source.lines() {
+            let Some(tag) = line.split_whitespace().next() else {
+                continue;
+            };
+            let attributes = parse_attributes(line[tag.len()..].trim());
+            let number = |key: &str| -> f32 {
+                attributes
+                    .get(key)
+                    .and_then(|value| value.parse().ok())
+                    .unwrap_or_else(|| panic!("Invalid font data, missing {} in: {}", key, line))
+            };
+
+            match tag {
+                "common" => {
+                    font.line_height = number("lineHeight");
+                    font.atlas_size = vec2(number("scaleW"), number("scaleH"));
+                }
+                "page" => {
+                    if number("id") != 0.0 {
+                        panic!("Only single page fonts are supported");
+                    }
+                    font.page = attributes
+                        .get("file")
+                        .cloned()
+                        .unwrap_or_else(|| panic!("Invalid font data, missing file in: {}", line));
+                }
+                "char" => {
+                    let glyph = Glyph {
+                        atlas_position: vec2(number("x"), number("y")),
+                        size: vec2(number("width"), number("height")),
+                        offset: vec2(number("xoffset"), number("yoffset")),
+                        advance: number("xadvance"),
+                    };
+                    font.glyphs.insert(to_char(number("id")), glyph);
+                }
+                "kerning" => {
+                    let pair = (to_char(number("first")), to_char(number("second")));
+                    font.kernings.insert(pair, number("amount"));
+                }
+                _ => {}
+            }
+        }
+
+        if font.glyphs.is_empty() || font.atlas_size == Vec2::ZERO {
+            panic!("Invalid font file");
+        }
+
+        font
+    }
+
+    /// Lays out `text` with its top left corner at `position`. `\n` starts a new line,
+    /// characters missing from the font are drawn as `?`.
+    pub fn layout(&self, text: &str, position: Vec2, scale: f32) -> Vec<GlyphQuad> {
+        let mut quads = Vec::with_capacity(text.len());
+        self.walk(text, scale, |pen, glyph| {
+            if glyph.size.x > 0.0 && glyph.size.y > 0.0 {
+                quads.push(GlyphQuad {
+                    position: position + pen + glyph.offset * scale,
+                    size: glyph.size * scale,
+                    uv_min: glyph.atlas_position / self.atlas_size,
+                    uv_max: (glyph.atlas_position + glyph.size) / self.atlas_size,
+                });
+            }
+        });
+        quads
+    }
+
+    /// Size of the box `text` takes up: the widest line by the number of lines times the line height.
+    pub fn measure(&self, text: &str, scale: f32) -> Vec2 {
+        let mut width: f32 = 0.0;
+        let end = self.walk(text, scale, |pen, glyph| {
+            width = width.max(pen.x + glyph.advance * scale)
+        });
+        vec2(width, end.y + self.line_height * scale)
+    }
+
+    fn glyph(&self, c: char) -> Option<&Glyph> {
+        self.glyphs.get(&c).or_else(|| self.glyphs.get(&FALLBACK_CHAR))
+    }
+
+    /// Calls `f` with the pen position of every glyph and returns where the pen ended up.
+    fn walk(&self, text: &str, scale: f32, mut f: impl FnMut(Vec2, &Glyph)) -> Vec2 {
+        let mut pen = Vec2::ZERO;
+        let mut previous: Option<char> = None;
+
+        for c in text.chars() {
+            if c == '\n' {
+                pen = vec2(0.0, pen.y + self.line_height * scale);
+                previous = None;
+                continue;
+            }
+
+            let Some(glyph) = self.glyph(c) else {
+                continue;
+            };
+            if let Some(kerning) = previous.and_then(|previous| self.kernings.get(&(previous, c))) {
+                pen.x += kerning * scale;
+            }
+
+            f(pen, glyph);
+            pen.x += glyph.advance * scale;
+            previous = Some(c);
+        }
+
+        pen
+    }
+}
+
+fn to_char(id: f32) -> char {
+    char::from_u32(id as u32).unwrap_or_else(|| panic!("Invalid character id: {}", id))
+}
+
+/// Splits `key=value key="quoted value"` pairs.
+fn parse_attributes(line: &str) -> HashMap<&str, String> {
+    let mut attributes = HashMap::new();
+    let mut rest = line;
+
+    while let Some(equals) = rest.find('=') {
+        let key = rest[..equals].trim();
+        rest = &rest[equals + 1..];
+
+        let value = if let Some(quoted) = rest.strip_prefix('"') {
+            let end = quoted.find('"').unwrap_or(quoted.len());
+            rest = quoted.get(end + 1..).unwrap_or("");
+            &quoted[..end]
+        } else {
+            let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+            let value = &rest[..end];
+            rest = &rest[end..];
+            value
+        };
+
+        attributes.insert(key, value.to_string());
+        rest = rest.trim_start();
+    }
+
+    attributes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FONT: &str = r#"info face="Test Font" size=10
+common lineHeight=12 base=9 scaleW=64 scaleH=32 pages=1 packed=0
+page id=0 file="test font.png"
+chars count=4
+char id=32 x=0  y=0 width=0 height=0  xoffset=0 yoffset=9 xadvance=4 page=0 chnl=15
+char id=63 x=0  y=0 width=5 height=9  xoffset=1 yoffset=0 xadvance=6 page=0 chnl=15
+char id=65 x=8  y=0 width=8 height=9  xoffset=0 yoffset=0 xadvance=8 page=0 chnl=15
+char id=86 x=16 y=8 width=8 height=10 xoffset=0 yoffset=-1 xadvance=8 page=0 chnl=15
+kernings count=1
+kerning first=65 second=86 amount=-2
+"#;
+
+    fn font() -> BitmapFont {
+        BitmapFont::parse(FONT)
+    }
+
+    #[test]
+    fn parses_the_page_and_metrics() {
+        let font = font();
+        assert_eq!(font.page, "test font.png");
+        assert_eq!(font.line_height, 12.0);
+        assert_eq!(font.glyphs.len(), 4);
+        assert_eq!(font.kernings[&('A', 'V')], -2.0);
+    }
+
+    #[test]
+    fn parses_the_bundled_font() {
+        let font = BitmapFont::parse(&std::fs::read_to_string("resources/fonts/dejavu_sans.fnt").unwrap());
+        assert_eq!(font.page, "dejavu_sans.png");
+        assert!(('!'..='~').all(|c| font.glyphs.contains_key(&c)));
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid font file")]
+    fn font_without_glyphs_is_rejected() {
+        BitmapFont::parse("common lineHeight=12 base=9 scaleW=64 scaleH=32\n");
+    }
+
+    #[test]
+    fn lays_out_glyphs_with_offsets_and_atlas_uvs() {
+        let quads = font().layout("A", vec2(100.0, 50.0), 1.0);
+        assert_eq!(
+            quads,
+            vec![GlyphQuad {
+                position: vec2(100.0, 50.0),
+                size: vec2(8.0, 9.0),
+                uv_min: vec2(8.0 / 64.0, 0.0),
+                uv_max: vec2(16.0 / 64.0, 9.0 / 32.0),
+            }]
+        );
+    }
+
+    #[test]
+    fn applies_kerning_between_pairs() {
+        let quads = font().layout("AV", Vec2::ZERO, 1.0);
+        assert_eq!(quads[1].position, vec2(6.0, -1.0));
+
+        let quads = font().layout("VA", Vec2::ZERO, 1.0);
+        assert_eq!(quads[1].position, vec2(8.0, 0.0));
+    }
+
+    #[test]
+    fn spaces_advance_without_producing_quads() {
+        let quads = font().layout("A A", Vec2::ZERO, 1.0);
+        assert_eq!(quads.len(), 2);
+        assert_eq!(quads[1].position.x, 12.0);
+    }
+
+    #[test]
+    fn scales_positions_and_sizes() {
+        let quads = font().layout("AV", vec2(10.0, 10.0), 2.0);
+        assert_eq!(quads[0].size, vec2(16.0, 18.0));
+        assert_eq!(quads[1].position, vec2(22.0, 8.0));
+        assert_eq!(quads[1].uv_min, vec2(16.0 / 64.0, 8.0 / 32.0));
+    }
+
+    #[test]
+    fn newline_starts_a_new_line_without_kerning() {
+        let quads = font().layout("A\nV", Vec2::ZERO, 1.0);
+        assert_eq!(quads[1].position, vec2(0.0, 11.0));
+    }
+
+    #[test]
+    fn unknown_characters_fall_back_to_question_mark() {
+        let quads = font().layout("Ä", Vec2::ZERO, 1.0);
+        assert_eq!(quads.len(), 1);
+        assert_eq!(quads[0].size, vec2(5.0, 9.0));
+        assert_eq!(quads[0].position, vec2(1.0, 0.0));
+    }
+
+    #[test]
+    fn measures_the_widest_line() {
+        let font = font();
+        assert_eq!(font.measure("", 1.0), vec2(0.0, 12.0));
+        assert_eq!(font.measure("AV", 1.0), vec2(14.0, 12.0));
+        assert_eq!(font.measure("A\nAAA", 2.0), vec2(48.0, 48.0));
+    }
+}
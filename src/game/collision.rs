@@ -0,0 +1,269 @@
+use glam::{vec2, Vec2};
+
+use super::{ball::Ball, entity::Entity};
+
+/// Direction in which the first shape of a collision has to be pushed to get out of the second one.
+/// Uses screen coordinates, so `Up` points towards negative y.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Up,
+    Right,
+    Down,
+    Left,
+}
+
+impl Direction {
+    /// Picks the direction along the dominant axis of `v`. Ties go to the vertical axis.
+    fn of(v: Vec2) -> Direction {
+        if v.y.abs() >= v.x.abs() {
+            if v.y <= 0.0 {
+                Direction::Up
+            } else {
+                Direction::Down
+            }
+        } else if v.x < 0.0 {
+            Direction::Left
+        } else {
+            Direction::Right
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Collision {
+    pub direction: Direction,
+    /// Vector that moves the first shape out of the second one.
+    pub penetration: Vec2,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Aabb {
+    pub position: Vec2,
+    pub size: Vec2,
+}
+
+impl Aabb {
+    pub fn new(position: Vec2, size: Vec2) -> Aabb {
+        Aabb { position, size }
+    }
+
+    pub fn min(&self) -> Vec2 {
+        self.position
+    }
+
+    pub fn max(&self) -> Vec2 {
+        self.position + self.size
+    }
+
+    pub fn center(&self) -> Vec2 {
+        self.position + self.size / 2.0
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Circle {
+    pub center: Vec2,
+    pub radius: f32,
+}
+
+impl Circle {
+    pub fn new(center: Vec2, radius: f32) -> Circle {
+        Circle { center, radius }
+    }
+}
+
+/// Shapes that only touch along an edge or a corner do not collide.
+pub fn aabb_aabb(one: &Aabb, two: &Aabb) -> Option<Collision> {
+    let overlap = one.max().min(two.max()) - one.min().max(two.min());
+    if overlap.x <= 0.0 || overlap.y <= 0.0 {
+        return None;
+    }
+
+    let offset = one.center() - two.center();
+    let penetration = if overlap.x < overlap.y {
+        vec2(overlap.x.copysign(offset.x), 0.0)
+    } else {
+        vec2(0.0, overlap.y.copysign(offset.y))
+    };
+
+    Some(Collision {
+        direction: Direction::of(penetration),
+        penetration,
+    })
+}
+
+/// A circle whose centre ended up inside the box (e.g. after a large step) is pushed out
+/// through the nearest side.
+pub fn circle_aabb(circle: &Circle, aabb: &Aabb) -> Option<Collision> {
+    let (min, max) = (aabb.min(), aabb.max());
+    let closest = circle.center.clamp(min, max);
+    let difference = circle.center - closest;
+
+    if difference == Vec2::ZERO {
+        let exits = [
+            vec2(0.0, min.y - circle.center.y - circle.radius),
+            vec2(max.x - circle.center.x + circle.radius, 0.0),
+            vec2(0.0, max.y - circle.center.y + circle.radius),
+            vec2(min.x - circle.center.x - circle.radius, 0.0),
+        ];
+        let penetration = exits
+            .into_iter()
+            .min_by(|a, b| a.length_squared().total_cmp(&b.length_squared()))
+            .unwrap();
+
+        return Some(Collision {
+            direction: Direction::of(penetration),
+            penetration,
+        });
+    }
+
+    let distance = difference.length();
+    if distance >= circle.radius {
+        return None;
+    }
+
+    let penetration = difference / distance * (circle.radius - distance);
+    Some(Collision {
+        direction: Direction::of(difference),
+        penetration,
+    })
+}
+
+impl Entity {
+    pub fn aabb(&self) -> Aabb {
+        Aabb::new(self.position, self.size)
+    }
+}
+
+impl Ball {
+    pub fn circle(&self) -> Circle {
+        Circle::new(self.entity.position + self.radius, self.radius)
+    }
+}
+
+pub fn check_ball_collision(ball: &Ball, entity: &Entity) -> Option<Collision> {
+    circle_aabb(&ball.circle(), &entity.aabb())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EPSILON: f32 = 1e-4;
+
+    fn unit_box() -> Aabb {
+        Aabb::new(vec2(0.0, 0.0), vec2(10.0, 10.0))
+    }
+
+    #[test]
+    fn separated_boxes_do_not_collide() {
+        let other = Aabb::new(vec2(20.0, 0.0), vec2(5.0, 5.0));
+        assert_eq!(aabb_aabb(&unit_box(), &other), None);
+    }
+
+    #[test]
+    fn boxes_touching_at_an_edge_do_not_collide() {
+        let right = Aabb::new(vec2(10.0, 2.0), vec2(5.0, 5.0));
+        let below = Aabb::new(vec2(2.0, 10.0), vec2(5.0, 5.0));
+        assert_eq!(aabb_aabb(&right, &unit_box()), None);
+        assert_eq!(aabb_aabb(&below, &unit_box()), None);
+    }
+
+    #[test]
+    fn boxes_touching_at_a_corner_do_not_collide() {
+        let corner = Aabb::new(vec2(10.0, 10.0), vec2(5.0, 5.0));
+        assert_eq!(aabb_aabb(&corner, &unit_box()), None);
+    }
+
+    #[test]
+    fn overlapping_box_is_pushed_along_the_shallow_axis() {
+        let from_left = Aabb::new(vec2(-4.0, 2.0), vec2(5.0, 5.0));
+        assert_eq!(
+            aabb_aabb(&from_left, &unit_box()),
+            Some(Collision {
+                direction: Direction::Left,
+                penetration: vec2(-1.0, 0.0),
+            })
+        );
+
+        let from_top = Aabb::new(vec2(2.0, -3.0), vec2(5.0, 5.0));
+        assert_eq!(
+            aabb_aabb(&from_top, &unit_box()),
+            Some(Collision {
+                direction: Direction::Up,
+                penetration: vec2(0.0, -2.0),
+            })
+        );
+    }
+
+    #[test]
+    fn box_overlapping_a_corner_is_pushed_along_the_shallow_axis() {
+        let bottom_right = Aabb::new(vec2(9.0, 7.0), vec2(5.0, 5.0));
+        assert_eq!(
+            aabb_aabb(&bottom_right, &unit_box()),
+            Some(Collision {
+                direction: Direction::Right,
+                penetration: vec2(1.0, 0.0),
+            })
+        );
+    }
+
+    #[test]
+    fn circle_hitting_the_top_edge_is_pushed_up() {
+        let circle = Circle::new(vec2(5.0, -1.5), 2.0);
+        let collision = circle_aabb(&circle, &unit_box()).unwrap();
+        assert_eq!(collision.direction, Direction::Up);
+        assert!(collision.penetration.abs_diff_eq(vec2(0.0, -0.5), EPSILON));
+    }
+
+    #[test]
+    fn circle_hitting_the_side_edges() {
+        let left = circle_aabb(&Circle::new(vec2(-1.0, 5.0), 2.0), &unit_box()).unwrap();
+        assert_eq!(left.direction, Direction::Left);
+        assert!(left.penetration.abs_diff_eq(vec2(-1.0, 0.0), EPSILON));
+
+        let right = circle_aabb(&Circle::new(vec2(11.0, 5.0), 2.0), &unit_box()).unwrap();
+        assert_eq!(right.direction, Direction::Right);
+        assert!(right.penetration.abs_diff_eq(vec2(1.0, 0.0), EPSILON));
+    }
+
+    #[test]
+    fn circle_touching_an_edge_does_not_collide() {
+        let circle = Circle::new(vec2(5.0, 12.0), 2.0);
+        assert_eq!(circle_aabb(&circle, &unit_box()), None);
+    }
+
+    #[test]
+    fn circle_hitting_a_corner_is_pushed_diagonally() {
+        let circle = Circle::new(vec2(11.0, 12.0), 3.0);
+        let collision = circle_aabb(&circle, &unit_box()).unwrap();
+        let distance = vec2(1.0, 2.0).length();
+        let expected = vec2(1.0, 2.0) / distance * (3.0 - distance);
+
+        assert_eq!(collision.direction, Direction::Down);
+        assert!(collision.penetration.abs_diff_eq(expected, EPSILON));
+    }
+
+    #[test]
+    fn circle_inside_the_corner_bounds_but_outside_the_corner_does_not_collide() {
+        // bounding boxes overlap, but the corner is further away than the radius
+        let circle = Circle::new(vec2(12.0, 12.0), 2.5);
+        assert_eq!(circle_aabb(&circle, &unit_box()), None);
+    }
+
+    #[test]
+    fn tunnelled_circle_is_pushed_out_through_the_nearest_side() {
+        let circle = Circle::new(vec2(4.0, 9.0), 2.0);
+        let collision = circle_aabb(&circle, &unit_box()).unwrap();
+        assert_eq!(collision.direction, Direction::Down);
+        assert!(collision.penetration.abs_diff_eq(vec2(0.0, 3.0), EPSILON));
+    }
+
+    #[test]
+    fn circle_tunnelled_to_the_centre_still_resolves() {
+        let circle = Circle::new(unit_box().center(), 2.0);
+        let collision = circle_aabb(&circle, &unit_box()).unwrap();
+        assert_eq!(collision.direction, Direction::Up);
+        assert!(collision.penetration.abs_diff_eq(vec2(0.0, -7.0), EPSILON));
+    }
+}
@@ -1,21 +1,27 @@
 use glam::{vec2, Vec2, Vec3A};
 
-use super::{sprite_renderer::SpriteRenderer, texture2d::Texture2D};
+use super::{
+    animation::Animation,
+    sprite_batch::SpriteInstance,
+    sprite_renderer::{Sprite, SpriteRenderer},
+};
 
 #[derive(Debug)]
 pub struct Entity {
     pub position: Vec2,
-    size: Vec2,
-    velocity: Vec2,
+    pub size: Vec2,
+    pub velocity: Vec2,
     color: Vec3A,
     rotation: f32,
-    sprite: Texture2D,
+    sprite: Sprite,
+    /// Drawn instead of `sprite` when set.
+    animation: Option<Animation>,
     is_solid: bool,
     is_destroyed: bool,
 }
 
 impl Entity {
-    pub fn new(position: Vec2, size: Vec2, sprite: Texture2D, color: Vec3A) -> Entity {
+    pub fn new(position: Vec2, size: Vec2, sprite: Sprite, color: Vec3A) -> Entity {
         Entity {
             position,
             size,
@@ -23,13 +29,65 @@ impl Entity {
             color,
             rotation: 0.0,
             sprite,
+            animation: None,
             is_solid: false,
             is_destroyed: false,
         }
     }
 
-    pub fn draw(&self, renderer: &SpriteRenderer) {
-        renderer.draw(&self.sprite, self.position, self.size, self.rotation, self.color);
+    /// Draws the entity somewhere else than where it is, like between two steps of its movement.
+    pub fn draw_at(&self, renderer: &mut SpriteRenderer, position: Vec2) {
+        renderer.queue(&self.sprite(), position, self.size, self.rotation, self.color);
+    }
+
+    /// What the entity shows right now, the current frame of its animation if it has one.
+    pub fn sprite(&self) -> Sprite {
+        match &self.animation {
+            Some(animation) => animation.sprite(),
+            None => self.sprite.clone(),
+        }
+    }
+
+    /// The entity as an instance of the instanced sprite path, to be drawn with the texture of
+    /// `sprite`.
+    pub fn instance(&self) -> SpriteInstance {
+        SpriteInstance::new(
+            self.position,
+            self.size,
+            self.rotation,
+            self.color.extend(1.0),
+            self.sprite().uv,
+        )
+    }
+
+    pub fn set_animation(&mut self, animation: Animation) {
+        self.animation = Some(animation);
+    }
+
+    /// Plays a clip of the entity's animation, if it has one.
+    pub fn play(&mut self, clip: &str) {
+        if let Some(animation) = &mut self.animation {
+            animation.play(clip);
+        }
+    }
+
+    pub fn is_animating(&self) -> bool {
+        self.animation.as_ref().is_some_and(Animation::is_playing)
+    }
+
+    /// Advances the animation by `dt` seconds.
+    pub fn update(&mut self, dt: f32) {
+        if let Some(animation) = &mut self.animation {
+            animation.update(dt);
+        }
+    }
+
+    pub fn color(&self) -> Vec3A {
+        self.color
+    }
+
+    pub fn set_color(&mut self, color: Vec3A) {
+        self.color = color;
     }
 
     pub fn is_destroyed(&self) -> bool {
@@ -39,4 +97,12 @@ impl Entity {
     pub fn is_solid(&self) -> bool {
         self.is_solid
     }
+
+    pub fn set_solid(&mut self, is_solid: bool) {
+        self.is_solid = is_solid;
+    }
+
+    pub fn destroy(&mut self) {
+        self.is_destroyed = true;
+    }
 }
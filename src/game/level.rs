@@ -1,69 +1,159 @@
-use std::path::Path;
+use std::{collections::HashSet, path::Path};
 
 use glam::{vec2, vec3a, Vec3A};
 
-use crate::util::read_file;
+use crate::{
+    error::{Error, Result},
+    util::{read_file, FileWatch},
+};
 
-use super::{entity::Entity, loader::Loader, sprite_renderer::SpriteRenderer};
+use super::{
+    animation::Animation, ball::Ball, collision::check_ball_collision, entity::Entity, loader::Loader,
+    sprite_renderer::SpriteRenderer,
+};
 
 #[derive(Debug)]
 pub struct Level {
     bricks: Vec<Entity>,
+    tiles: Vec<Vec<u32>>,
+    file: FileWatch,
+    width: u32,
+    height: u32,
 }
 
 impl Level {
     pub fn new() -> Level {
-        Level { bricks: Vec::new() }
+        Level {
+            bricks: Vec::new(),
+            tiles: Vec::new(),
+            file: FileWatch::new(Path::new("")),
+            width: 0,
+            height: 0,
+        }
     }
 
-    pub fn load(&mut self, file_path: &Path, level_width: u32, level_height: u32, loader: &Loader) {
-        self.bricks.clear();
+    pub fn load(&mut self, file_path: &Path, level_width: u32, level_height: u32, loader: &Loader) -> Result<()> {
+        let tiles = read_tiles(file_path)?;
 
-        let mut tile_data: Vec<Vec<u32>> = Vec::with_capacity(5);
-        for line in read_file(file_path).lines() {
-            let row: Vec<u32> = line
-                .chars()
-                .filter(|c| !c.is_whitespace())
-                .map(|c| c.to_digit(10).or_else(|| panic!("Invalid tile data: {}", c)).unwrap())
-                .collect();
-            tile_data.push(row);
-        }
+        self.file = FileWatch::new(file_path);
+        self.width = level_width;
+        self.height = level_height;
+        self.build(tiles, loader);
 
-        if tile_data.is_empty() {
-            panic!("Invalid level file");
+        Ok(())
+    }
+
+    /// Loads the level file again, bringing back every destroyed brick.
+    pub fn reload(&mut self, loader: &Loader) -> Result<()> {
+        let file_path = self.file.path().to_path_buf();
+        self.load(&file_path, self.width, self.height, loader)
+    }
+
+    /// Rebuilds the bricks if the level file was edited since it was loaded or last checked.
+    /// A destroyed brick stays destroyed as long as its cell still holds the same tile.
+    pub fn reload_if_changed(&mut self, loader: &Loader) -> Option<Result<()>> {
+        if !self.file.changed() {
+            return None;
         }
 
+        let result = read_tiles(self.file.path()).map(|tiles| {
+            let destroyed: HashSet<(usize, usize, u32)> = tile_cells(&self.tiles)
+                .zip(&self.bricks)
+                .filter(|(_, brick)| brick.is_destroyed())
+                .map(|(cell, _)| cell)
+                .collect();
+
+            self.build(tiles, loader);
+            for (cell, brick) in tile_cells(&self.tiles).zip(&mut self.bricks) {
+                if destroyed.contains(&cell) {
+                    brick.destroy();
+                }
+            }
+        });
+        Some(result)
+    }
+
+    pub fn file_path(&self) -> &Path {
+        self.file.path()
+    }
+
+    fn build(&mut self, tiles: Vec<Vec<u32>>, loader: &Loader) {
+        self.bricks.clear();
+
         // initialize bricks
-        let row_no = tile_data.len() as u32;
-        let col_no = tile_data[0].len() as u32;
-        let unit_height = level_height / row_no;
-        let unit_width = level_width / col_no;
-
-        for (y, row) in tile_data.iter().enumerate() {
-            for (x, &tile) in row.iter().enumerate() {
-                if tile == 0 {
-                    continue;
-                };
-
-                let color = get_color_from_tile_no(tile);
-                let texture = loader.get_texture(get_texture_from_tile_no(tile));
-                let pos = vec2(x as f32 * unit_width as f32, y as f32 * unit_height as f32);
-                let size = vec2(unit_width as f32, unit_height as f32);
-                let tile_entity = Entity::new(pos, size, texture.clone(), color);
-                self.bricks.push(tile_entity);
+        let row_no = tiles.len() as u32;
+        let col_no = tiles[0].len() as u32;
+        let unit_height = self.height / row_no;
+        let unit_width = self.width / col_no;
+
+        for (x, y, tile) in tile_cells(&tiles) {
+            let color = get_color_from_tile_no(tile);
+            let sprite = loader.get_sprite(get_texture_from_tile_no(tile));
+            let pos = vec2(x as f32 * unit_width as f32, y as f32 * unit_height as f32);
+            let size = vec2(unit_width as f32, unit_height as f32);
+            let mut tile_entity = Entity::new(pos, size, sprite, color);
+            if tile == 1 {
+                tile_entity.set_solid(true);
+            } else {
+                tile_entity.set_animation(Animation::new(loader.get_sprite_sheet("block").clone()));
             }
+            self.bricks.push(tile_entity);
         }
+
+        self.tiles = tiles;
     }
 
-    pub fn draw(&self, renderer: &SpriteRenderer) {
-        for tile in &self.bricks {
-            if tile.is_destroyed() {
+    /// Draws the bricks still standing and the ones still breaking, instanced with one draw call
+    /// per run of bricks sharing a texture.
+    pub fn draw(&self, renderer: &mut SpriteRenderer) {
+        let mut texture = None;
+        let mut instances = Vec::with_capacity(self.bricks.len());
+        for brick in &self.bricks {
+            if brick.is_destroyed() && !brick.is_animating() {
                 continue;
             };
-            tile.draw(renderer);
+            let sprite = brick.sprite();
+            if texture.as_ref() != Some(&sprite.texture) {
+                if let Some(texture) = texture.replace(sprite.texture) {
+                    renderer.draw_instanced(&texture, &instances);
+                    instances.clear();
+                }
+            }
+            instances.push(brick.instance());
+        }
+        if let Some(texture) = texture {
+            renderer.draw_instanced(&texture, &instances);
         }
     }
 
+    /// Advances the animations of the bricks by `dt` seconds.
+    pub fn update(&mut self, dt: f32) {
+        for brick in &mut self.bricks {
+            brick.update(dt);
+        }
+    }
+
+    /// Bounces the ball off every brick it touches, destroying the ones that are not solid.
+    /// A destroyed brick plays its break animation before it disappears. With `pass_through`
+    /// the ball only bounces off solid bricks and carries on through the others.
+    /// Returns the bricks the ball hit.
+    pub fn collide_ball(&mut self, ball: &mut Ball, pass_through: bool) -> Vec<&Entity> {
+        let mut hit = Vec::new();
+        for brick in self.bricks.iter_mut().filter(|brick| !brick.is_destroyed()) {
+            if let Some(collision) = check_ball_collision(ball, brick) {
+                if !brick.is_solid() {
+                    brick.destroy();
+                    brick.play("break");
+                }
+                if brick.is_solid() || !pass_through {
+                    ball.bounce(&collision);
+                }
+                hit.push(&*brick);
+            }
+        }
+        hit
+    }
+
     pub fn is_completed(&self) -> bool {
         self.bricks
             .iter()
@@ -71,6 +161,57 @@ impl Level {
     }
 }
 
+fn read_tiles(file_path: &Path) -> Result<Vec<Vec<u32>>> {
+    parse_tiles(&read_file(file_path)?).map_err(|reason| Error::InvalidLevel {
+        path: file_path.to_path_buf(),
+        reason,
+    })
+}
+
+/// Every cell with a brick as (column, row, tile), in the order `Level::build` creates them.
+fn tile_cells(tiles: &[Vec<u32>]) -> impl Iterator<Item = (usize, usize, u32)> + '_ {
+    tiles.iter().enumerate().flat_map(|(y, row)| {
+        row.iter()
+            .enumerate()
+            .filter(|(_, &tile)| tile != 0)
+            .map(move |(x, &tile)| (x, y, tile))
+    })
+}
+
+/// Reads one row of tile numbers per line, every row as long as the first one.
+fn parse_tiles(source: &str) -> std::result::Result<Vec<Vec<u32>>, String> {
+    let mut tile_data: Vec<Vec<u32>> = Vec::with_capacity(5);
+    for (line_no, line) in source.lines().enumerate() {
+        let row = line
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .map(|c| {
+                c.to_digit(10)
+                    .ok_or_else(|| format!("invalid tile {:?} on line {}", c, line_no + 1))
+            })
+            .collect::<std::result::Result<Vec<u32>, String>>()?;
+        if row.is_empty() {
+            continue;
+        }
+        if let Some(first) = tile_data.first() {
+            if row.len() != first.len() {
+                return Err(format!(
+                    "line {} has {} tiles, expected {}",
+                    line_no + 1,
+                    row.len(),
+                    first.len()
+                ));
+            }
+        }
+        tile_data.push(row);
+    }
+
+    if tile_data.is_empty() {
+        return Err("no tiles".to_string());
+    }
+    Ok(tile_data)
+}
+
 fn get_color_from_tile_no(tile_no: u32) -> Vec3A {
     match tile_no {
         1 => vec3a(0.8, 0.8, 0.8),
@@ -88,3 +229,110 @@ fn get_texture_from_tile_no(title_no: u32) -> &'static str {
         _ => "block",
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs,
+        time::{Duration, SystemTime},
+    };
+
+    use crate::{game::manifest::TextureOptions, opengl::recording::RecordingBackend, util::TempDir};
+
+    use super::*;
+
+    fn loader_with_brick_textures() -> Loader {
+        let mut loader = Loader::new();
+        for (name, alpha) in [("block", false), ("block_solid", false), ("block_sheet", true)] {
+            let path = format!("resources/textures/{}.png", name);
+            loader
+                .load_texture(name, Path::new(&path), TextureOptions::new(alpha))
+                .unwrap();
+        }
+        loader
+            .load_sprite_sheet("block", Path::new("resources/sheets/block.sheet"), "block_sheet")
+            .unwrap();
+        loader
+    }
+
+    fn write_level(path: &Path, tiles: &str, seconds_later: u64) {
+        fs::write(path, tiles).unwrap();
+        let file = fs::File::options().write(true).open(path).unwrap();
+        file.set_modified(SystemTime::now() + Duration::from_secs(seconds_later))
+            .unwrap();
+    }
+
+    fn destroyed(level: &Level) -> Vec<bool> {
+        level.bricks.iter().map(Entity::is_destroyed).collect()
+    }
+
+    #[test]
+    fn parses_rows_of_tiles() {
+        assert_eq!(
+            parse_tiles("1 2 0\n 5 5 3 \t\n\n"),
+            Ok(vec![vec![1, 2, 0], vec![5, 5, 3]])
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_tiles() {
+        assert_eq!(parse_tiles("1 1\n1 x"), Err("invalid tile 'x' on line 2".to_string()));
+    }
+
+    #[test]
+    fn rejects_rows_of_different_lengths() {
+        assert_eq!(
+            parse_tiles("1 1 1\n1 1"),
+            Err("line 2 has 2 tiles, expected 3".to_string())
+        );
+    }
+
+    #[test]
+    fn rejects_empty_levels() {
+        assert_eq!(parse_tiles(" \n"), Err("no tiles".to_string()));
+    }
+
+    #[test]
+    fn edited_levels_keep_destroyed_bricks_whose_tile_did_not_change() {
+        let _recorder = RecordingBackend::install();
+        let loader = loader_with_brick_textures();
+        let directory = TempDir::new("level-hot-reload");
+        let path = directory.path().join("level.lvl");
+        write_level(&path, "2 3 4\n1 0 5\n", 0);
+
+        let mut level = Level::new();
+        level.load(&path, 300, 100, &loader).unwrap();
+        assert!(level.reload_if_changed(&loader).is_none());
+        level.bricks[0].destroy();
+        level.bricks[1].destroy();
+
+        // the second brick changes color, a new one fills the gap
+        write_level(&path, "2 4 4\n1 3 5\n", 60);
+        assert!(matches!(level.reload_if_changed(&loader), Some(Ok(()))));
+        assert_eq!(destroyed(&level), vec![true, false, false, false, false, false]);
+        assert!(level.reload_if_changed(&loader).is_none());
+
+        level.reload(&loader).unwrap();
+        assert!(destroyed(&level).iter().all(|destroyed| !destroyed));
+    }
+
+    #[test]
+    fn invalid_edits_keep_the_current_bricks() {
+        let _recorder = RecordingBackend::install();
+        let loader = loader_with_brick_textures();
+        let directory = TempDir::new("level-invalid-edit");
+        let path = directory.path().join("level.lvl");
+        write_level(&path, "2 3\n", 0);
+
+        let mut level = Level::new();
+        level.load(&path, 200, 100, &loader).unwrap();
+        level.bricks[1].destroy();
+
+        write_level(&path, "2 3\n2\n", 60);
+        assert!(matches!(
+            level.reload_if_changed(&loader),
+            Some(Err(Error::InvalidLevel { .. }))
+        ));
+        assert_eq!(destroyed(&level), vec![false, true]);
+    }
+}
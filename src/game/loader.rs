@@ -1,73 +1,713 @@
 use std::{
     collections::{hash_map::Entry, HashMap},
-    path::Path,
+    path::{Path, PathBuf},
+    rc::Rc,
 };
 
-use stb_image::image::{self, LoadResult};
+use gl::types::GLenum;
+use stb_image::image::{self, Image, LoadResult};
 
-use crate::util::read_file;
+use crate::{
+    audio::{load_sound, Sound},
+    error::{AssetKind, Error, Result, ShaderStage},
+    util::{read_file, FileWatch},
+};
+
+use super::{
+    animation::SpriteSheet,
+    atlas::{Atlas, Bitmap},
+    manifest::{Manifest, TextureOptions},
+    material::Material,
+    preprocessor::ShaderSource,
+    sprite_renderer::Sprite,
+    texture2d::Texture2D,
+};
 
-use super::{material::Material, texture2d::Texture2D};
+/// Largest width and height of an atlas page, which every GPU the game runs on supports.
+const ATLAS_PAGE_SIZE: u32 = 2048;
+/// Texels around each image in an atlas, so filtering never reaches into the next one.
+const ATLAS_PADDING: u32 = 2;
 
 pub struct Loader {
-    materials: HashMap<&'static str, Material>,
-    textures: HashMap<&'static str, Texture2D>,
+    materials: HashMap<String, Rc<Material>>,
+    material_sources: HashMap<String, MaterialSources>,
+    textures: HashMap<String, Rc<Texture2D>>,
+    texture_files: HashMap<String, FileWatch>,
+    atlases: HashMap<String, AtlasTextures>,
+    sprite_sheets: HashMap<String, Rc<SpriteSheet>>,
+    sounds: HashMap<String, Sound>,
+}
+
+/// An atlas with a texture per page and the image files packed into it, watched for hot reload.
+struct AtlasTextures {
+    atlas: Atlas,
+    pages: Vec<Rc<Texture2D>>,
+    files: Vec<(String, FileWatch)>,
+}
+
+/// The shader files a material was loaded from, watched for hot reload.
+struct MaterialSources {
+    vertex: PathBuf,
+    fragment: PathBuf,
+    defines: Vec<(String, String)>,
+    /// Both shader files and every file they include.
+    files: Vec<FileWatch>,
+}
+
+impl MaterialSources {
+    fn new(vertex: &Path, fragment: &Path, defines: &[(String, String)]) -> MaterialSources {
+        MaterialSources {
+            vertex: vertex.to_path_buf(),
+            fragment: fragment.to_path_buf(),
+            defines: defines.to_vec(),
+            files: Vec::new(),
+        }
+    }
+
+    /// Preprocesses both shaders and watches the files they are made of from now on.
+    fn preprocess(&mut self) -> Result<(ShaderSource, ShaderSource)> {
+        let vertex = ShaderSource::load(&self.vertex, &self.defines)?;
+        let fragment = ShaderSource::load(&self.fragment, &self.defines)?;
+
+        self.files = vertex
+            .files()
+            .iter()
+            .chain(fragment.files())
+            .map(|file| FileWatch::new(file))
+            .collect();
+        Ok((vertex, fragment))
+    }
+
+    fn changed(&mut self) -> bool {
+        // check every file so none reports the same edit again on the next call
+        let mut changed = false;
+        for file in &mut self.files {
+            changed |= file.changed();
+        }
+        changed
+    }
 }
 
 impl Loader {
     pub fn new() -> Loader {
         Loader {
             materials: HashMap::new(),
+            material_sources: HashMap::new(),
             textures: HashMap::new(),
+            texture_files: HashMap::new(),
+            atlases: HashMap::new(),
+            sprite_sheets: HashMap::new(),
+            sounds: HashMap::new(),
+        }
+    }
+
+    /// Loads every material, texture, sprite sheet and sound listed in the manifest at `path` and returns the manifest
+    /// for the assets the loader does not own, like levels.
+    pub fn load_manifest(&mut self, path: &Path) -> Result<Manifest> {
+        let manifest = Manifest::load(path)?;
+        for material in &manifest.materials {
+            self.load_material(&material.name, &material.vertex, &material.fragment, &material.defines)?;
+        }
+        let mut atlases: Vec<(&str, Vec<(String, PathBuf)>)> = Vec::new();
+        for texture in &manifest.textures {
+            let Some(atlas) = &texture.atlas else {
+                self.load_texture(&texture.name, &texture.path, texture.options)?;
+                continue;
+            };
+            let image = (texture.name.clone(), texture.path.clone());
+            match atlases.iter_mut().find(|(name, _)| name == atlas) {
+                Some((_, images)) => images.push(image),
+                None => atlases.push((atlas, vec![image])),
+            }
+        }
+        for (atlas, images) in atlases {
+            self.load_atlas(atlas, &images)?;
+        }
+        for sheet in &manifest.sheets {
+            self.load_sprite_sheet(&sheet.name, &sheet.path, &sheet.texture)?;
         }
+        for sound in &manifest.sounds {
+            self.load_sound(&sound.name, &sound.path)?;
+        }
+        Ok(manifest)
     }
 
+    /// Builds a material from two shader files, after resolving their `#include`s and adding
+    /// `defines` to both, see `ShaderSource`.
     pub fn load_material(
         &mut self,
-        name: &'static str,
-        vertex_shader_file_path: &str,
-        fragment_shader_file_path: &str,
-    ) -> &Material {
-        match self.materials.entry(name) {
-            Entry::Occupied(_) => panic!("Material already exists: {}", name),
+        name: &str,
+        vertex_shader_file_path: &Path,
+        fragment_shader_file_path: &Path,
+        defines: &[(String, String)],
+    ) -> Result<&Rc<Material>> {
+        match self.materials.entry(name.to_string()) {
+            Entry::Occupied(_) => Err(Error::DuplicateAsset {
+                kind: AssetKind::Material,
+                name: name.to_string(),
+            }),
             Entry::Vacant(entry) => {
-                let vertex_source = read_file(Path::new(vertex_shader_file_path));
-                let fragment_source = read_file(Path::new(fragment_shader_file_path));
+                let mut sources = MaterialSources::new(vertex_shader_file_path, fragment_shader_file_path, defines);
+                let (vertex_source, fragment_source) = sources.preprocess()?;
+
+                let material = Material::new(name, &vertex_source.text, &fragment_source.text)
+                    .map_err(|error| map_compile_log(error, &vertex_source, &fragment_source))?;
 
-                let material = Material::new(&vertex_source, &fragment_source);
+                self.material_sources.insert(name.to_string(), sources);
+                Ok(entry.insert(Rc::new(material)))
+            }
+        }
+    }
 
-                entry.insert(material)
+    /// Recompiles every material whose shader files changed since they were loaded or last checked,
+    /// in place so everything holding on to the material picks the change up.
+    /// Returns the materials it tried to reload, a material that failed keeps its previous program.
+    pub fn reload_changed_materials(&mut self) -> Vec<(String, Result<()>)> {
+        let mut reloaded = Vec::new();
+        for (name, sources) in self.material_sources.iter_mut() {
+            if !sources.changed() {
+                continue;
             }
+
+            let result = sources.preprocess().and_then(|(vertex_source, fragment_source)| {
+                self.materials[name]
+                    .reload(&vertex_source.text, &fragment_source.text)
+                    .map_err(|error| map_compile_log(error, &vertex_source, &fragment_source))
+            });
+            reloaded.push((name.clone(), result));
         }
+        reloaded
     }
 
-    pub fn get_material(&self, name: &str) -> &Material {
+    pub fn get_material(&self, name: &str) -> &Rc<Material> {
         self.materials
             .get(name)
             .unwrap_or_else(|| panic!("Material not found: {}", name))
     }
 
-    pub fn load_texture(&mut self, name: &'static str, image_file_path: &Path, alpha: bool) -> &Texture2D {
-        let format = if alpha { gl::RGBA } else { gl::RGB };
+    pub fn load_texture(
+        &mut self,
+        name: &str,
+        image_file_path: &Path,
+        options: TextureOptions,
+    ) -> Result<&Rc<Texture2D>> {
+        if self.has_sprite(name) {
+            return Err(Error::DuplicateAsset {
+                kind: AssetKind::Texture,
+                name: name.to_string(),
+            });
+        }
 
-        let texture_2d = Texture2D::new(format, format, gl::REPEAT, gl::REPEAT, gl::LINEAR, gl::LINEAR);
+        let format = if options.alpha { gl::RGBA } else { gl::RGB };
+        let image = load_texture_image(image_file_path, format)?;
 
-        if let LoadResult::ImageU8(image) = image::load(image_file_path) {
-            texture_2d.generate(image.width as i32, image.height as i32, image.data);
+        let texture_2d = Texture2D::new(
+            format,
+            format,
+            options.wrap,
+            options.wrap,
+            options.filter,
+            options.filter,
+        );
+        texture_2d.generate(image.width as i32, image.height as i32, image.data);
 
-            let entry = self.textures.entry(name);
-            match entry {
-                Entry::Occupied(_) => panic!("Texture already exists: {}", name),
-                Entry::Vacant(entry) => entry.insert(texture_2d),
+        self.texture_files
+            .insert(name.to_string(), FileWatch::new(image_file_path));
+        Ok(self.textures.entry(name.to_string()).or_insert(Rc::new(texture_2d)))
+    }
+
+    /// Packs the images, given as (name, path), into the pages of a new atlas. Each image can then
+    /// be drawn by its name with `get_sprite`, like a texture of its own.
+    pub fn load_atlas(&mut self, name: &str, images: &[(String, PathBuf)]) -> Result<()> {
+        if self.atlases.contains_key(name) {
+            return Err(Error::DuplicateAsset {
+                kind: AssetKind::Atlas,
+                name: name.to_string(),
+            });
+        }
+
+        let mut bitmaps = Vec::with_capacity(images.len());
+        for (image_name, path) in images {
+            if self.has_sprite(image_name) || bitmaps.iter().any(|(name, _)| name == image_name) {
+                return Err(Error::DuplicateAsset {
+                    kind: AssetKind::Texture,
+                    name: image_name.clone(),
+                });
+            }
+            bitmaps.push((image_name.clone(), load_bitmap(path)?));
+        }
+
+        let atlas = Atlas::build(&bitmaps, ATLAS_PAGE_SIZE, ATLAS_PADDING).map_err(|reason| Error::Atlas {
+            name: name.to_string(),
+            reason,
+        })?;
+        let pages = atlas
+            .pages
+            .iter()
+            .map(|page| {
+                let texture = Texture2D::new(
+                    gl::RGBA,
+                    gl::RGBA,
+                    gl::CLAMP_TO_EDGE,
+                    gl::CLAMP_TO_EDGE,
+                    gl::LINEAR,
+                    gl::LINEAR,
+                );
+                texture.generate(page.width as i32, page.height as i32, page.rgba.clone());
+                Rc::new(texture)
+            })
+            .collect();
+        let files = images
+            .iter()
+            .map(|(image_name, path)| (image_name.clone(), FileWatch::new(path)))
+            .collect();
+
+        self.atlases
+            .insert(name.to_string(), AtlasTextures { atlas, pages, files });
+        Ok(())
+    }
+
+    /// Uploads every texture whose image file changed since it was loaded or last checked into
+    /// its existing `Texture2D`, so every sprite using it shows the new image.
+    /// Returns the textures it tried to reload, a texture that failed keeps its previous image.
+    pub fn reload_changed_textures(&mut self) -> Vec<(String, Result<()>)> {
+        let mut reloaded = Vec::new();
+        for (name, file) in self.texture_files.iter_mut() {
+            if !file.changed() {
+                continue;
+            }
+
+            let texture = &self.textures[name];
+            let result = load_texture_image(file.path(), texture.image_format()).map(|image| {
+                texture.generate(image.width as i32, image.height as i32, image.data);
+            });
+            reloaded.push((name.clone(), result));
+        }
+
+        // a packed image is copied over its old self, the rest of its page stays as it is
+        for atlas in self.atlases.values_mut() {
+            for (name, file) in atlas.files.iter_mut() {
+                if !file.changed() {
+                    continue;
+                }
+
+                let result = load_bitmap(file.path()).and_then(|bitmap| {
+                    let page = atlas.atlas.replace(name, &bitmap).map_err(|reason| Error::Image {
+                        path: file.path().to_path_buf(),
+                        reason,
+                    })?;
+                    let page_bitmap = &atlas.atlas.pages[page];
+                    atlas.pages[page].generate(
+                        page_bitmap.width as i32,
+                        page_bitmap.height as i32,
+                        page_bitmap.rgba.clone(),
+                    );
+                    Ok(())
+                });
+                reloaded.push((name.clone(), result));
             }
-        } else {
-            panic!("Error reading image file: {}", image_file_path.display())
         }
+        reloaded
     }
 
-    pub fn get_texture(&self, name: &str) -> &Texture2D {
+    pub fn get_texture(&self, name: &str) -> &Rc<Texture2D> {
         self.textures
             .get(name)
             .unwrap_or_else(|| panic!("Texture not found: {}", name))
     }
+
+    /// The part of an atlas page an image was packed into, or all of the texture by that name.
+    pub fn get_sprite(&self, name: &str) -> Sprite {
+        for atlas in self.atlases.values() {
+            if let Some(region) = atlas.atlas.region(name) {
+                return Sprite {
+                    texture: atlas.pages[region.page].clone(),
+                    uv: region.uv,
+                };
+            }
+        }
+        Sprite::whole(self.get_texture(name).clone())
+    }
+
+    /// Reads the frames and clips of a sheet cut out of the texture or atlas image `sprite`,
+    /// which has to be loaded already.
+    pub fn load_sprite_sheet(&mut self, name: &str, path: &Path, sprite: &str) -> Result<&Rc<SpriteSheet>> {
+        if self.sprite_sheets.contains_key(name) {
+            return Err(Error::DuplicateAsset {
+                kind: AssetKind::SpriteSheet,
+                name: name.to_string(),
+            });
+        }
+        let error = |reason| Error::InvalidSpriteSheet {
+            path: path.to_path_buf(),
+            reason,
+        };
+        if !self.has_sprite(sprite) {
+            return Err(error(format!("no texture {}", sprite)));
+        }
+
+        let sheet = SpriteSheet::parse(&read_file(path)?, self.get_sprite(sprite)).map_err(error)?;
+        Ok(self.sprite_sheets.entry(name.to_string()).or_insert(Rc::new(sheet)))
+    }
+
+    pub fn get_sprite_sheet(&self, name: &str) -> &Rc<SpriteSheet> {
+        self.sprite_sheets
+            .get(name)
+            .unwrap_or_else(|| panic!("Sprite sheet not found: {}", name))
+    }
+
+    /// Loads a WAV or OGG file with the current audio backend.
+    pub fn load_sound(&mut self, name: &str, path: &Path) -> Result<Sound> {
+        match self.sounds.entry(name.to_string()) {
+            Entry::Occupied(_) => Err(Error::DuplicateAsset {
+                kind: AssetKind::Sound,
+                name: name.to_string(),
+            }),
+            Entry::Vacant(entry) => Ok(*entry.insert(load_sound(path)?)),
+        }
+    }
+
+    pub fn get_sound(&self, name: &str) -> Sound {
+        *self
+            .sounds
+            .get(name)
+            .unwrap_or_else(|| panic!("Sound not found: {}", name))
+    }
+
+    fn has_sprite(&self, name: &str) -> bool {
+        self.textures.contains_key(name) || self.atlases.values().any(|atlas| atlas.atlas.region(name).is_some())
+    }
+}
+
+fn load_bitmap(image_file_path: &Path) -> Result<Bitmap> {
+    let image = load_image(image_file_path)?;
+    Ok(Bitmap::from_channels(
+        image.width as u32,
+        image.height as u32,
+        image.depth,
+        &image.data,
+    ))
+}
+
+/// Loads an image to upload into a texture of `format`, which its channels have to match.
+fn load_texture_image(image_file_path: &Path, format: GLenum) -> Result<Image<u8>> {
+    let image = load_image(image_file_path)?;
+    let channels = if format == gl::RGBA { 4 } else { 3 };
+    if image.depth != channels {
+        return Err(Error::Image {
+            path: image_file_path.to_path_buf(),
+            reason: format!("{} channels, the texture needs {}", image.depth, channels),
+        });
+    }
+    Ok(image)
+}
+
+/// Points the lines in a compile error back at the files the shader was preprocessed from.
+fn map_compile_log(error: Error, vertex_source: &ShaderSource, fragment_source: &ShaderSource) -> Error {
+    match error {
+        Error::ShaderCompile { stage, log } => {
+            let source = match stage {
+                ShaderStage::Vertex => vertex_source,
+                ShaderStage::Fragment => fragment_source,
+            };
+            Error::ShaderCompile {
+                stage,
+                log: source.map_log(&log),
+            }
+        }
+        error => error,
+    }
+}
+
+fn load_image(image_file_path: &Path) -> Result<Image<u8>> {
+    match image::load(image_file_path) {
+        LoadResult::ImageU8(image) => Ok(image),
+        LoadResult::ImageF32(_) => Err(Error::Image {
+            path: image_file_path.to_path_buf(),
+            reason: "HDR images are not supported".to_string(),
+        }),
+        LoadResult::Error(reason) => Err(Error::Image {
+            path: image_file_path.to_path_buf(),
+            reason,
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs,
+        path::PathBuf,
+        time::{Duration, SystemTime},
+    };
+
+    use glam::Mat4;
+
+    use crate::{
+        opengl::{
+            recording::{GlCall, RecordingBackend},
+            software::SoftwareBackend,
+        },
+        util::TempDir,
+    };
+
+    use super::*;
+
+    /// Copies a file into `directory` so a test can edit it.
+    fn temp_copy(directory: &TempDir, source: &Path) -> PathBuf {
+        let path = directory.path().join(source.file_name().unwrap());
+        fs::copy(source, &path).unwrap();
+        path
+    }
+
+    fn shader_copy(directory: &TempDir, file_name: &str) -> PathBuf {
+        temp_copy(directory, &Path::new("src/shaders").join(file_name))
+    }
+
+    /// Writes `contents` with a modification time clearly after the previous one.
+    fn edit(path: &Path, contents: impl AsRef<[u8]>) {
+        fs::write(path, contents).unwrap();
+        let file = fs::File::options().write(true).open(path).unwrap();
+        file.set_modified(SystemTime::now() + Duration::from_secs(60)).unwrap();
+    }
+
+    #[test]
+    fn loading_a_texture_twice_is_an_error() {
+        let _recorder = RecordingBackend::install();
+        let mut loader = Loader::new();
+        let path = Path::new("resources/textures/block.png");
+
+        assert!(loader.load_texture("block", path, TextureOptions::new(false)).is_ok());
+        assert!(matches!(
+            loader.load_texture("block", path, TextureOptions::new(false)),
+            Err(Error::DuplicateAsset {
+                kind: AssetKind::Texture,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn missing_files_are_reported_with_their_path() {
+        let _recorder = RecordingBackend::install();
+        let mut loader = Loader::new();
+
+        let error = loader
+            .load_material(
+                "missing",
+                Path::new("src/shaders/missing.vert"),
+                Path::new("src/shaders/sprite.frag"),
+                &[],
+            )
+            .unwrap_err();
+        assert!(matches!(&error, Error::Io { path, .. } if path == Path::new("src/shaders/missing.vert")));
+
+        let error = loader
+            .load_texture(
+                "missing",
+                Path::new("resources/textures/missing.png"),
+                TextureOptions::new(false),
+            )
+            .unwrap_err();
+        assert!(matches!(error, Error::Image { .. }));
+        assert!(!loader.has_sprite("missing"));
+    }
+
+    #[test]
+    fn edited_shaders_are_reloaded_in_place_with_their_uniforms() {
+        let recorder = RecordingBackend::install();
+        let directory = TempDir::new("loader-reload");
+        let vertex_path = shader_copy(&directory, "sprite.vert");
+        let fragment_path = shader_copy(&directory, "sprite.frag");
+        let mut loader = Loader::new();
+        let material = loader
+            .load_material("sprite", &vertex_path, &fragment_path, &[])
+            .unwrap()
+            .clone();
+        material.set_matrix4f("projection", Mat4::IDENTITY);
+        assert!(loader.reload_changed_materials().is_empty());
+
+        recorder.clear();
+        edit(
+            &fragment_path,
+            fs::read_to_string(&fragment_path).unwrap().replace("// color", "//"),
+        );
+        let reloaded = loader.reload_changed_materials();
+        assert_eq!(reloaded.len(), 1);
+        assert_eq!(reloaded[0].0, "sprite");
+        assert!(reloaded[0].1.is_ok());
+        assert!(loader.reload_changed_materials().is_empty());
+
+        let calls = recorder.calls();
+        assert!(calls.iter().any(|call| matches!(call, GlCall::LinkProgram(_))));
+        assert!(calls.iter().any(|call| matches!(call, GlCall::DeleteProgram(_))));
+        assert_eq!(recorder.uniform_values("projection").len(), 1);
+    }
+
+    #[test]
+    fn shaders_that_fail_to_build_keep_the_previous_program() {
+        // the software backend refuses to link programs without the inputs its port reads
+        let _framebuffer = SoftwareBackend::install(1, 1);
+        let directory = TempDir::new("loader-broken");
+        let vertex_path = shader_copy(&directory, "sprite.vert");
+        let fragment_path = shader_copy(&directory, "sprite.frag");
+        let mut loader = Loader::new();
+        loader
+            .load_material("sprite", &vertex_path, &fragment_path, &[])
+            .unwrap();
+
+        edit(&vertex_path, "#version 330 core\nvoid main() {}\n");
+        let reloaded = loader.reload_changed_materials();
+        assert_eq!(reloaded.len(), 1);
+        assert_eq!(reloaded[0].0, "sprite");
+        assert!(matches!(reloaded[0].1, Err(Error::Link { .. })));
+    }
+
+    #[test]
+    fn edited_includes_reload_the_material() {
+        let recorder = RecordingBackend::install();
+        let directory = TempDir::new("loader-include");
+        let vertex_path = shader_copy(&directory, "sprite.vert");
+        let fragment_path = shader_copy(&directory, "sprite.frag");
+        let include_path = fragment_path.with_file_name("tint.glsl");
+        fs::write(&include_path, "uniform vec3 spriteColor;\n").unwrap();
+        edit(
+            &fragment_path,
+            fs::read_to_string(&fragment_path)
+                .unwrap()
+                .replace("uniform vec3 spriteColor;", "#include \"tint.glsl\""),
+        );
+
+        let mut loader = Loader::new();
+        let defines = [("TINTED".to_string(), String::new())];
+        let material = loader
+            .load_material("sprite", &vertex_path, &fragment_path, &defines)
+            .unwrap()
+            .clone();
+        assert!(material.reflection().uniform("spriteColor").is_some());
+        let fragment_source = recorder.calls().into_iter().find_map(|call| match call {
+            GlCall::ShaderSource { source, .. } if source.contains("spriteColor") => Some(source),
+            _ => None,
+        });
+        assert!(fragment_source
+            .unwrap()
+            .starts_with("#version 330 core\n#define TINTED\n"));
+
+        edit(&include_path, "uniform vec3 spriteColor;\nuniform float alpha;\n");
+        let reloaded = loader.reload_changed_materials();
+        assert_eq!(reloaded.len(), 1);
+        assert!(reloaded[0].1.is_ok());
+        assert!(material.reflection().uniform("alpha").is_some());
+    }
+
+    #[test]
+    fn edited_images_are_uploaded_into_the_existing_texture() {
+        let recorder = RecordingBackend::install();
+        let directory = TempDir::new("loader-texture");
+        let path = temp_copy(&directory, Path::new("resources/textures/block.png"));
+        let mut loader = Loader::new();
+        let texture = loader
+            .load_texture("block", &path, TextureOptions::new(false))
+            .unwrap()
+            .clone();
+
+        recorder.clear();
+        edit(&path, fs::read("resources/textures/block_solid.png").unwrap());
+        let reloaded = loader.reload_changed_textures();
+        assert_eq!(reloaded.len(), 1);
+        assert_eq!(reloaded[0].0, "block");
+        assert!(reloaded[0].1.is_ok());
+        assert!(loader.reload_changed_textures().is_empty());
+        assert!(Rc::ptr_eq(&texture, loader.get_texture("block")));
+
+        let calls = recorder.calls();
+        assert!(calls.iter().any(|call| matches!(call, GlCall::TexImage2D { .. })));
+        assert!(!calls.iter().any(|call| matches!(call, GlCall::GenTexture(_))));
+    }
+
+    #[test]
+    fn packed_images_share_a_page_and_reload_in_place() {
+        let recorder = RecordingBackend::install();
+        let directory = TempDir::new("loader-atlas");
+        let block_path = temp_copy(&directory, Path::new("resources/textures/block.png"));
+        let paddle_path = temp_copy(&directory, Path::new("resources/textures/paddle.png"));
+        let mut loader = Loader::new();
+        loader
+            .load_atlas(
+                "sprites",
+                &[
+                    ("block".to_string(), block_path.clone()),
+                    ("paddle".to_string(), paddle_path.clone()),
+                ],
+            )
+            .unwrap();
+
+        let (block, paddle) = (loader.get_sprite("block"), loader.get_sprite("paddle"));
+        assert!(Rc::ptr_eq(&block.texture, &paddle.texture));
+        assert_ne!(block.uv, paddle.uv);
+        assert!(matches!(
+            loader.load_texture("block", &block_path, TextureOptions::new(false)),
+            Err(Error::DuplicateAsset { .. })
+        ));
+
+        recorder.clear();
+        edit(&block_path, fs::read("resources/textures/block_solid.png").unwrap());
+        let reloaded = loader.reload_changed_textures();
+        assert_eq!(reloaded.len(), 1);
+        assert_eq!(reloaded[0].0, "block");
+        assert!(reloaded[0].1.is_ok());
+        assert_eq!(loader.get_sprite("block"), block);
+        assert!(recorder
+            .calls()
+            .iter()
+            .any(|call| matches!(call, GlCall::TexImage2D { .. })));
+
+        // a different size would move everything else on the page
+        edit(&block_path, fs::read("resources/textures/paddle.png").unwrap());
+        let reloaded = loader.reload_changed_textures();
+        assert!(matches!(reloaded[0].1, Err(Error::Image { .. })));
+    }
+
+    #[test]
+    fn images_with_other_channels_than_the_texture_are_rejected() {
+        let recorder = RecordingBackend::install();
+        let directory = TempDir::new("loader-texture-channels");
+        let path = temp_copy(&directory, Path::new("resources/textures/block.png"));
+        let mut loader = Loader::new();
+
+        let error = loader
+            .load_texture(
+                "paddle",
+                Path::new("resources/textures/paddle.png"),
+                TextureOptions::new(false),
+            )
+            .unwrap_err();
+        assert!(matches!(error, Error::Image { .. }));
+        assert!(!loader.has_sprite("paddle"));
+
+        loader.load_texture("block", &path, TextureOptions::new(false)).unwrap();
+        recorder.clear();
+        // RGBA instead of RGB
+        edit(&path, fs::read("resources/textures/paddle.png").unwrap());
+        let reloaded = loader.reload_changed_textures();
+        assert_eq!(reloaded.len(), 1);
+        assert!(matches!(reloaded[0].1, Err(Error::Image { .. })));
+        assert!(recorder.calls().is_empty());
+    }
+
+    #[test]
+    fn broken_images_keep_the_previous_texture() {
+        let recorder = RecordingBackend::install();
+        let directory = TempDir::new("loader-broken-texture");
+        let path = temp_copy(&directory, Path::new("resources/textures/block.png"));
+        let mut loader = Loader::new();
+        loader.load_texture("block", &path, TextureOptions::new(false)).unwrap();
+
+        recorder.clear();
+        edit(&path, "not an image");
+        let reloaded = loader.reload_changed_textures();
+        assert_eq!(reloaded.len(), 1);
+        assert!(matches!(reloaded[0].1, Err(Error::Image { .. })));
+        assert!(recorder.calls().is_empty());
+    }
 }
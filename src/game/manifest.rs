@@ -0,0 +1,351 @@
+use std::path::{Path, PathBuf};
+
+use gl::types::GLenum;
+use serde::{Deserialize, Deserializer};
+
+use crate::{
+    error::{Error, Result},
+    util::read_file,
+};
+
+/// How a texture is stored and sampled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TextureOptions {
+    pub alpha: bool,
+    pub wrap: GLenum,
+    pub filter: GLenum,
+}
+
+impl TextureOptions {
+    /// Repeating and linearly filtered, with or without an alpha channel.
+    pub fn new(alpha: bool) -> TextureOptions {
+        TextureOptions {
+            alpha,
+            wrap: gl::REPEAT,
+            filter: gl::LINEAR,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MaterialEntry {
+    pub name: String,
+    pub vertex: PathBuf,
+    pub fragment: PathBuf,
+    /// `#define`s added to both shaders, as (name, value) with an empty value for a bare name.
+    #[serde(default, deserialize_with = "parse_defines")]
+    pub defines: Vec<(String, String)>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "TextureTable")]
+pub struct TextureEntry {
+    pub name: String,
+    pub path: PathBuf,
+    pub options: TextureOptions,
+    /// The atlas the image is packed into instead of getting a texture of its own.
+    pub atlas: Option<String>,
+}
+
+/// A sprite sheet, cut out of a texture or atlas image loaded before it.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SheetEntry {
+    pub name: String,
+    pub path: PathBuf,
+    pub texture: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SoundEntry {
+    pub name: String,
+    pub path: PathBuf,
+}
+
+/// The assets the game loads at startup, read from a TOML file of `[[material]]`, `[[texture]]`,
+/// `[[sheet]]`, `[[sound]]` and `[[level]]` tables.
+///
+/// ```toml
+/// [[material]]
+/// name = "sprite_instanced"
+/// vertex = "src/shaders/sprite_batch.vert"
+/// fragment = "src/shaders/sprite_batch.frag"
+/// defines = "INSTANCED"  # optional, NAME or NAME=VALUE separated by spaces
+///
+/// [[texture]]
+/// name = "face"
+/// path = "resources/textures/awesomeface.png"
+/// alpha = true            # optional, defaults to false
+/// wrap = "clamp_to_edge"  # optional: repeat (default), mirrored_repeat or clamp_to_edge
+/// filter = "nearest"      # optional: linear (default) or nearest
+/// atlas = "sprites"       # optional, packs the image with the others of the same atlas,
+///                         # which are always clamped and linearly filtered
+///
+/// [[sheet]]
+/// name = "block"
+/// path = "resources/sheets/block.sheet"  # frames and clips, see `SpriteSheet::parse`
+/// texture = "block_sheet"                # the texture or atlas image they are cut out of
+///
+/// [[sound]]
+/// name = "brick_hit"
+/// path = "resources/audio/brick_hit.wav"  # a WAV or OGG file
+///
+/// [[level]]
+/// path = "resources/levels/level_1.lvl"
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Manifest {
+    #[serde(default, rename = "material")]
+    pub materials: Vec<MaterialEntry>,
+    #[serde(default, rename = "texture")]
+    pub textures: Vec<TextureEntry>,
+    #[serde(default, rename = "sheet")]
+    pub sheets: Vec<SheetEntry>,
+    #[serde(default, rename = "sound")]
+    pub sounds: Vec<SoundEntry>,
+    #[serde(default, rename = "level", deserialize_with = "level_paths")]
+    pub levels: Vec<PathBuf>,
+}
+
+/// A `[[texture]]` table as written, before its options are checked.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TextureTable {
+    name: String,
+    path: PathBuf,
+    #[serde(default)]
+    alpha: bool,
+    wrap: Option<Wrap>,
+    filter: Option<Filter>,
+    atlas: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum Wrap {
+    Repeat,
+    MirroredRepeat,
+    ClampToEdge,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum Filter {
+    Linear,
+    Nearest,
+}
+
+impl TryFrom<TextureTable> for TextureEntry {
+    type Error = String;
+
+    fn try_from(table: TextureTable) -> std::result::Result<TextureEntry, String> {
+        if table.atlas.is_some() && (table.wrap.is_some() || table.filter.is_some()) {
+            return Err("images in an atlas can't set wrap or filter".to_string());
+        }
+
+        let mut options = TextureOptions::new(table.alpha);
+        if let Some(wrap) = table.wrap {
+            options.wrap = match wrap {
+                Wrap::Repeat => gl::REPEAT,
+                Wrap::MirroredRepeat => gl::MIRRORED_REPEAT,
+                Wrap::ClampToEdge => gl::CLAMP_TO_EDGE,
+            };
+        }
+        if let Some(filter) = table.filter {
+            options.filter = match filter {
+                Filter::Linear => gl::LINEAR,
+                Filter::Nearest => gl::NEAREST,
+            };
+        }
+        Ok(TextureEntry {
+            name: table.name,
+            path: table.path,
+            options,
+            atlas: table.atlas,
+        })
+    }
+}
+
+impl Manifest {
+    pub fn load(path: &Path) -> Result<Manifest> {
+        Manifest::parse(&read_file(path)?).map_err(|reason| Error::InvalidManifest {
+            path: path.to_path_buf(),
+            reason,
+        })
+    }
+
+    pub fn parse(source: &str) -> std::result::Result<Manifest, String> {
+        toml::from_str(source).map_err(|error| error.to_string())
+    }
+}
+
+/// Splits `NAME NAME=VALUE` into (name, value) pairs.
+fn parse_defines<'de, D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Vec<(String, String)>, D::Error> {
+    let defines = String::deserialize(deserializer)?;
+    Ok(defines
+        .split_whitespace()
+        .map(|define| {
+            let (name, value) = define.split_once('=').unwrap_or((define, ""));
+            (name.to_string(), value.to_string())
+        })
+        .collect())
+}
+
+/// Reads the `path` of every `[[level]]` table.
+fn level_paths<'de, D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Vec<PathBuf>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(deny_unknown_fields)]
+    struct Level {
+        path: PathBuf,
+    }
+
+    let levels = Vec::<Level>::deserialize(deserializer)?;
+    Ok(levels.into_iter().map(|level| level.path).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_materials_textures_sheets_sounds_and_levels() {
+        let manifest = Manifest::parse(
+            r#"
+            # the sprite shaders
+            [[material]]
+            name = "sprite"
+            vertex = "src/shaders/sprite.vert"
+            fragment = "src/shaders/sprite.frag"
+            defines = "INSTANCED  SAMPLES=4"
+
+            [[texture]]
+            name = "face"
+            path = "resources/textures/awesome face.png" # spaces are fine
+            alpha = true
+            wrap = "clamp_to_edge"
+            filter = "nearest"
+
+            [[texture]]
+            name = "block"
+            path = "resources/textures/block.png"
+            atlas = "sprites"
+
+            [[sheet]]
+            name = "block"
+            path = "resources/sheets/block.sheet"
+            texture = "block"
+
+            [[sound]]
+            name = "bleep"
+            path = "resources/audio/bleep.ogg"
+
+            [[level]]
+            path = "resources/levels/level_1.lvl"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            manifest.materials,
+            vec![MaterialEntry {
+                name: "sprite".to_string(),
+                vertex: "src/shaders/sprite.vert".into(),
+                fragment: "src/shaders/sprite.frag".into(),
+                defines: vec![
+                    ("INSTANCED".to_string(), String::new()),
+                    ("SAMPLES".to_string(), "4".to_string()),
+                ],
+            }]
+        );
+        assert_eq!(
+            manifest.textures[0],
+            TextureEntry {
+                name: "face".to_string(),
+                path: "resources/textures/awesome face.png".into(),
+                options: TextureOptions {
+                    alpha: true,
+                    wrap: gl::CLAMP_TO_EDGE,
+                    filter: gl::NEAREST,
+                },
+                atlas: None,
+            }
+        );
+        assert_eq!(manifest.textures[1].options, TextureOptions::new(false));
+        assert_eq!(manifest.textures[1].atlas.as_deref(), Some("sprites"));
+        assert_eq!(
+            manifest.sheets,
+            vec![SheetEntry {
+                name: "block".to_string(),
+                path: "resources/sheets/block.sheet".into(),
+                texture: "block".to_string(),
+            }]
+        );
+        assert_eq!(
+            manifest.sounds,
+            vec![SoundEntry {
+                name: "bleep".to_string(),
+                path: "resources/audio/bleep.ogg".into(),
+            }]
+        );
+        assert_eq!(manifest.levels, vec![PathBuf::from("resources/levels/level_1.lvl")]);
+    }
+
+    #[test]
+    fn parses_the_bundled_manifest() {
+        let manifest = Manifest::load(Path::new("resources/assets.toml")).unwrap();
+        assert!(manifest.materials.iter().any(|material| material.name == "sprite"));
+        assert!(manifest.textures.iter().all(|texture| texture.path.exists()));
+        assert!(manifest.sheets.iter().all(|sheet| sheet.path.exists()));
+        assert!(manifest.sounds.iter().all(|sound| sound.path.exists()));
+        assert!(manifest.levels.iter().all(|level| level.exists()));
+    }
+
+    #[test]
+    fn reports_mistakes_with_their_line() {
+        // the first line says where the mistake is and the last line what it is
+        let error = |source: &str| {
+            let error = Manifest::parse(source).unwrap_err();
+            let lines: Vec<&str> = error.lines().collect();
+            (lines[0].to_string(), lines[lines.len() - 1].to_string())
+        };
+        let at = |line: usize, column: usize, reason: &str| {
+            (
+                format!("TOML parse error at line {}, column {}", line, column),
+                reason.to_string(),
+            )
+        };
+
+        assert_eq!(error("[[level]]\n"), at(1, 1, "missing field `path`"));
+        assert_eq!(
+            error("\n[[font]]\n"),
+            at(
+                2,
+                3,
+                "unknown field `font`, expected one of `material`, `texture`, `sheet`, `sound`, `level`"
+            )
+        );
+        assert_eq!(
+            error("[[level]]\npath = \"a\"\npaht = \"b\""),
+            at(3, 1, "unknown field `paht`, expected `path`")
+        );
+        assert_eq!(
+            error("[[texture]]\nname = \"a\"\npath = \"a.png\"\nalpha = \"yes\""),
+            at(4, 9, "invalid type: string \"yes\", expected a boolean")
+        );
+        assert_eq!(
+            error("[[texture]]\nname = \"a\"\npath = \"a.png\"\nwrap = \"clamp\""),
+            at(
+                4,
+                8,
+                "unknown variant `clamp`, expected one of `repeat`, `mirrored_repeat`, `clamp_to_edge`"
+            )
+        );
+        assert_eq!(
+            error("[[texture]]\nname = \"a\"\npath = \"a.png\"\natlas = \"b\"\nwrap = \"repeat\""),
+            at(1, 1, "images in an atlas can't set wrap or filter")
+        );
+    }
+}
@@ -1,79 +1,342 @@
-use glam::{Mat4, Vec2, Vec3A, Vec4};
+use std::{
+    cell::{Ref, RefCell},
+    collections::HashMap,
+};
 
-use crate::opengl::{create_program, create_shader, Program};
+use gl::types::GLenum;
+use glam::{Mat4, Vec3A, Vec4};
 
-// Not deriving Copy for allowing heap allocated
-// data in the future
-#[derive(Debug, Clone)]
-pub struct Material {
-    program: Program,
+use crate::{
+    error::{Error, Result, ShaderStage},
+    opengl::{create_program, create_shader, type_name, ActiveVariable, Program, Shader},
+};
+
+/// A value set on a uniform, kept so it can be set again on a reloaded program.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum UniformValue {
+    Float(f32),
+    Integer(i32),
+    Vector3f(Vec3A),
+    Vector4f(Vec4),
+    Matrix4f(Mat4),
 }
 
-impl Material {
-    pub fn new(vertex_source: &str, fragment_source: &str) -> Self {
-        let vertex_shader = create_shader(gl::VERTEX_SHADER);
-        vertex_shader.source(vertex_source);
-        vertex_shader.compile();
-        if !vertex_shader.get(gl::COMPILE_STATUS) {
-            let info_log = vertex_shader.get_info_log();
-            println!("Error during compilation of vertex shader:\n{}", info_log);
+impl UniformValue {
+    /// Whether a uniform of the given GL type can be set to this value.
+    fn fits(&self, type_: GLenum) -> bool {
+        match self {
+            UniformValue::Float(_) => matches!(type_, gl::FLOAT | gl::BOOL),
+            UniformValue::Integer(_) => matches!(type_, gl::INT | gl::BOOL | gl::SAMPLER_2D | gl::SAMPLER_CUBE),
+            UniformValue::Vector3f(_) => type_ == gl::FLOAT_VEC3,
+            UniformValue::Vector4f(_) => type_ == gl::FLOAT_VEC4,
+            UniformValue::Matrix4f(_) => type_ == gl::FLOAT_MAT4,
         }
+    }
+}
 
-        let fragment_shader = create_shader(gl::FRAGMENT_SHADER);
-        fragment_shader.source(fragment_source);
-        fragment_shader.compile();
-        if !fragment_shader.get(gl::COMPILE_STATUS) {
-            let info_log = fragment_shader.get_info_log();
-            println!("Error during compilation of fragment shader:\n{}", info_log);
-        }
+/// Why a uniform could not be set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum UniformWarning {
+    Missing,
+    Mismatch,
+}
 
-        let program = create_program();
-        program.attach_shader(vertex_shader);
-        program.attach_shader(fragment_shader);
-        program.link();
+/// The active uniforms of a material's program, read once after linking.
+#[derive(Debug, Clone, Default)]
+pub struct Reflection {
+    uniforms: Vec<ActiveVariable>,
+    /// Index into `uniforms` by name, with arrays also found without their `[0]`.
+    by_name: HashMap<String, usize>,
+}
 
-        if !program.get(gl::LINK_STATUS) {
-            let info_log = program.get_info_log();
-            println!("Error during linking:\n{}", info_log);
+impl Reflection {
+    fn of(program: &Program) -> Reflection {
+        let uniforms = program.active_uniforms();
+        let mut by_name = HashMap::new();
+        for (i, uniform) in uniforms.iter().enumerate() {
+            by_name.insert(uniform.name.clone(), i);
+            if let Some(array) = uniform.name.strip_suffix("[0]") {
+                by_name.insert(array.to_string(), i);
+            }
         }
 
-        vertex_shader.delete();
-        fragment_shader.delete();
+        Reflection { uniforms, by_name }
+    }
+
+    pub fn uniform(&self, name: &str) -> Option<&ActiveVariable> {
+        self.by_name.get(name).map(|&i| &self.uniforms[i])
+    }
+}
+
+// Owns its program, so it is not Clone, share it
+// with Rc instead
+#[derive(Debug)]
+pub struct Material {
+    /// Names the program for GL debuggers, the name of the material in the manifest.
+    label: String,
+    program: RefCell<Program>,
+    reflection: RefCell<Reflection>,
+    uniforms: RefCell<HashMap<String, UniformValue>>,
+    /// Uniforms that could not be set and why, so each is only warned about once per program.
+    warned: RefCell<HashMap<String, Vec<UniformWarning>>>,
+}
+
+impl Material {
+    pub fn new(label: &str, vertex_source: &str, fragment_source: &str) -> Result<Self> {
+        let program = link_program(label, vertex_source, fragment_source)?;
+        Ok(Material {
+            label: label.to_string(),
+            reflection: RefCell::new(Reflection::of(&program)),
+            program: RefCell::new(program),
+            uniforms: RefCell::new(HashMap::new()),
+            warned: RefCell::new(HashMap::new()),
+        })
+    }
+
+    /// Swaps in a program built from the new sources, with every uniform set so far carried over.
+    /// If the sources don't compile or link the current program stays in use.
+    pub fn reload(&self, vertex_source: &str, fragment_source: &str) -> Result<()> {
+        let program = link_program(&self.label, vertex_source, fragment_source)?;
+        self.reflection.replace(Reflection::of(&program));
+        self.program.replace(program);
+        self.warned.borrow_mut().clear();
 
-        Material { program }
+        self.use_();
+        for (name, value) in self.uniforms.borrow().iter() {
+            self.apply(name, *value);
+        }
+        Ok(())
     }
 
     pub fn use_(&self) {
-        self.program.use_();
+        self.program().use_();
     }
 
     pub fn set_float(&self, name: &str, value: f32) {
-        self.program.get_uniform_location(name).set_1f(value);
+        self.set(name, UniformValue::Float(value));
     }
 
     pub fn set_integer(&self, name: &str, value: i32) {
-        self.program.get_uniform_location(name).set_1i(value);
-    }
-
-    pub fn set_vector2f(&self, name: &str, value: Vec2) {
-        self.program.get_uniform_location(name).set_2f(value.x, value.y);
+        self.set(name, UniformValue::Integer(value));
     }
 
     pub fn set_vector3f(&self, name: &str, value: Vec3A) {
-        self.program
-            .get_uniform_location(name)
-            .set_3f(value.x, value.y, value.z);
+        self.set(name, UniformValue::Vector3f(value));
     }
 
     pub fn set_vector4f(&self, name: &str, value: Vec4) {
-        self.program
-            .get_uniform_location(name)
-            .set_4f(value.x, value.y, value.z, value.w);
+        self.set(name, UniformValue::Vector4f(value));
     }
 
     pub fn set_matrix4f(&self, name: &str, value: Mat4) {
-        self.program
-            .get_uniform_location(name)
-            .set_matrix4fv(1, false, &value.to_cols_array());
+        self.set(name, UniformValue::Matrix4f(value));
+    }
+
+    /// What the driver reported about the uniforms of the current program.
+    pub fn reflection(&self) -> Ref<'_, Reflection> {
+        self.reflection.borrow()
+    }
+
+    fn program(&self) -> Ref<'_, Program> {
+        self.program.borrow()
+    }
+
+    fn set(&self, name: &str, value: UniformValue) {
+        self.apply(name, value);
+        let mut uniforms = self.uniforms.borrow_mut();
+        match uniforms.get_mut(name) {
+            Some(set) => *set = value,
+            None => {
+                uniforms.insert(name.to_string(), value);
+            }
+        }
+    }
+
+    /// Sets the uniform if the program has one by that name and type, and warns once otherwise.
+    fn apply(&self, name: &str, value: UniformValue) {
+        let reflection = self.reflection();
+        let uniform = match reflection.uniform(name) {
+            Some(uniform) if value.fits(uniform.type_) => uniform.uniform(),
+            Some(uniform) => {
+                let type_ = type_name(uniform.type_).unwrap_or("an unsupported type");
+                return self.warn_once(name, UniformWarning::Mismatch, || {
+                    format!("Uniform {} is {}, it can't be set to {:?}", name, type_, value)
+                });
+            }
+            None => {
+                return self.warn_once(name, UniformWarning::Missing, || {
+                    format!("The program has no active uniform {}", name)
+                })
+            }
+        };
+
+        match value {
+            UniformValue::Float(value) => uniform.set_1f(value),
+            UniformValue::Integer(value) => uniform.set_1i(value),
+            UniformValue::Vector3f(value) => uniform.set_3f(value.x, value.y, value.z),
+            UniformValue::Vector4f(value) => uniform.set_4f(value.x, value.y, value.z, value.w),
+            UniformValue::Matrix4f(value) => uniform.set_matrix4fv(1, false, &value.to_cols_array()),
+        }
+    }
+
+    fn warn_once(&self, name: &str, warning: UniformWarning, message: impl FnOnce() -> String) {
+        let mut warned = self.warned.borrow_mut();
+        match warned.get_mut(name) {
+            Some(warnings) if warnings.contains(&warning) => return,
+            Some(warnings) => warnings.push(warning),
+            None => {
+                warned.insert(name.to_string(), vec![warning]);
+            }
+        }
+        eprintln!("{}", message());
+    }
+}
+
+fn link_program(label: &str, vertex_source: &str, fragment_source: &str) -> Result<Program> {
+    let vertex_shader = compile_shader(ShaderStage::Vertex, vertex_source)?;
+    let fragment_shader = compile_shader(ShaderStage::Fragment, fragment_source)?;
+
+    let program = create_program();
+    program.attach_shader(&vertex_shader);
+    program.attach_shader(&fragment_shader);
+    program.set_label(label);
+    program.link();
+
+    if !program.get(gl::LINK_STATUS) {
+        return Err(Error::Link {
+            log: program.get_info_log(),
+        });
+    }
+
+    Ok(program)
+}
+
+fn compile_shader(stage: ShaderStage, source: &str) -> Result<Shader> {
+    let shader_type = match stage {
+        ShaderStage::Vertex => gl::VERTEX_SHADER,
+        ShaderStage::Fragment => gl::FRAGMENT_SHADER,
+    };
+
+    // the driver would read the source up to the NUL only
+    if let Some(offset) = source.find('\0') {
+        return Err(Error::ShaderCompile {
+            stage,
+            log: format!("The source contains a NUL byte at offset {}", offset),
+        });
+    }
+
+    let shader = create_shader(shader_type);
+    shader.source(source);
+    shader.compile();
+    if !shader.get(gl::COMPILE_STATUS) {
+        return Err(Error::ShaderCompile {
+            stage,
+            log: shader.get_info_log(),
+        });
+    }
+
+    Ok(shader)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use crate::{
+        opengl::recording::{GlCall, RecordingBackend, UniformValue as RecordedValue},
+        util::read_file,
+    };
+
+    use super::*;
+
+    fn sprite_material() -> Material {
+        Material::new(
+            "sprite",
+            &read_file(Path::new("src/shaders/sprite.vert")).unwrap(),
+            &read_file(Path::new("src/shaders/sprite.frag")).unwrap(),
+        )
+        .unwrap()
+    }
+
+    fn uniform_calls(calls: &[GlCall]) -> usize {
+        calls
+            .iter()
+            .filter(|call| matches!(call, GlCall::Uniform { .. }))
+            .count()
+    }
+
+    #[test]
+    fn reflects_uniforms_after_linking() {
+        let _recorder = RecordingBackend::install();
+        let material = sprite_material();
+        let reflection = material.reflection();
+
+        for (name, type_) in [
+            ("model", gl::FLOAT_MAT4),
+            ("projection", gl::FLOAT_MAT4),
+            ("uvRect", gl::FLOAT_VEC4),
+            ("image", gl::SAMPLER_2D),
+            ("spriteColor", gl::FLOAT_VEC3),
+        ] {
+            assert_eq!(
+                reflection.uniform(name).map(|uniform| uniform.type_),
+                Some(type_),
+                "{}",
+                name
+            );
+        }
+        assert!(reflection.uniform("vertex").is_none());
+    }
+
+    #[test]
+    fn setters_do_not_query_locations() {
+        let recorder = RecordingBackend::install();
+        let material = sprite_material();
+        let reflected = material.reflection().uniform("spriteColor").unwrap().location;
+        recorder.clear();
+
+        material.set_vector3f("spriteColor", Vec3A::ONE);
+        material.set_vector3f("spriteColor", Vec3A::ZERO);
+
+        assert_eq!(recorder.uniform_name(reflected).as_deref(), Some("spriteColor"));
+        assert_eq!(
+            recorder.uniform_values("spriteColor"),
+            vec![RecordedValue::Vec3(1.0, 1.0, 1.0), RecordedValue::Vec3(0.0, 0.0, 0.0)]
+        );
+    }
+
+    #[test]
+    fn unknown_names_and_mismatched_types_are_not_set() {
+        let recorder = RecordingBackend::install();
+        let material = sprite_material();
+        recorder.clear();
+
+        material.set_float("spriteColour", 1.0);
+        material.set_float("spriteColour", 1.0);
+        material.set_float("spriteColor", 1.0);
+        material.set_integer("image", 0);
+
+        assert_eq!(uniform_calls(&recorder.calls()), 1);
+        assert_eq!(recorder.uniform_values("image"), vec![RecordedValue::Int(0)]);
+        assert_eq!(
+            material.warned.borrow().clone(),
+            HashMap::from([
+                ("spriteColour".to_string(), vec![UniformWarning::Missing]),
+                ("spriteColor".to_string(), vec![UniformWarning::Mismatch]),
+            ])
+        );
+    }
+
+    #[test]
+    fn sources_with_a_nul_byte_do_not_compile() {
+        let recorder = RecordingBackend::install();
+        let fragment = read_file(Path::new("src/shaders/sprite.frag")).unwrap();
+        let result = Material::new("sprite", "#version 330 core\0\nvoid main() {}\n", &fragment);
+
+        assert!(matches!(
+            result,
+            Err(Error::ShaderCompile { stage: ShaderStage::Vertex, log }) if log.ends_with("offset 17")
+        ));
+        assert!(recorder.calls().is_empty());
     }
 }
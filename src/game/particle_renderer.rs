@@ -0,0 +1,115 @@
+use std::{
+    mem::{offset_of, size_of},
+    rc::Rc,
+};
+
+use glam::{Vec2, Vec4};
+
+use crate::opengl::{
+    active_texture, blend_func, buffer_data, buffer_storage, buffer_sub_data, draw_arrays_instanced,
+    enable_vertex_attrib_array, gen_buffers, gen_vertex_arrays, vertex_attrib_divisor, vertex_attrib_pointer, Buffer,
+    VertexAttributeArray,
+};
+
+use super::{material::Material, particles::ParticleGenerator, sprite_renderer::Sprite};
+
+/// Per-instance data of a particle, read by `particle.vert`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ParticleInstance {
+    pub position: Vec2,
+    /// Read together with `position` as a vec3.
+    pub size: f32,
+    pub color: Vec4,
+}
+
+/// Draws the live particles of a generator as glowing quads, added on top of what is already drawn.
+pub struct ParticleRenderer {
+    material: Rc<Material>,
+    sprite: Sprite,
+    vao: VertexAttributeArray,
+    /// Only read through `vao`, kept to be deleted with it.
+    _quad_vbo: Buffer,
+    instance_vbo: Buffer,
+    instance_capacity: usize,
+    instances: Vec<ParticleInstance>,
+}
+
+impl ParticleRenderer {
+    pub fn new(material: Rc<Material>, sprite: Sprite) -> ParticleRenderer {
+        let [vao] = gen_vertex_arrays::<1>();
+        let [quad_vbo, instance_vbo] = gen_buffers::<2>();
+
+        #[rustfmt::skip]
+        let vertices: [f32;12] = [
+            0.0, 1.0,
+            1.0, 0.0,
+            0.0, 0.0,
+
+            0.0, 1.0,
+            1.0, 1.0,
+            1.0, 0.0,
+        ];
+
+        vao.bind();
+        quad_vbo.bind(gl::ARRAY_BUFFER);
+        buffer_data(gl::ARRAY_BUFFER, &vertices, gl::STATIC_DRAW);
+        vertex_attrib_pointer(0, 2, gl::FLOAT, false, 2 * size_of::<f32>(), 0);
+        enable_vertex_attrib_array(0);
+
+        let stride = size_of::<ParticleInstance>();
+        instance_vbo.bind(gl::ARRAY_BUFFER);
+        vertex_attrib_pointer(1, 3, gl::FLOAT, false, stride, offset_of!(ParticleInstance, position));
+        enable_vertex_attrib_array(1);
+        vertex_attrib_divisor(1, 1);
+        vertex_attrib_pointer(2, 4, gl::FLOAT, false, stride, offset_of!(ParticleInstance, color));
+        enable_vertex_attrib_array(2);
+        vertex_attrib_divisor(2, 1);
+
+        ParticleRenderer {
+            material,
+            sprite,
+            vao,
+            _quad_vbo: quad_vbo,
+            instance_vbo,
+            instance_capacity: 0,
+            instances: Vec::new(),
+        }
+    }
+
+    /// Draws every live particle of `generator` in a single instanced draw call, with additive
+    /// blending so overlapping particles glow. Leaves the usual alpha blending on afterwards.
+    pub fn draw(&mut self, generator: &ParticleGenerator) {
+        let size = generator.settings().size;
+        self.instances.clear();
+        self.instances.extend(generator.live().map(|particle| ParticleInstance {
+            position: particle.position,
+            size,
+            color: particle.color,
+        }));
+        if self.instances.is_empty() {
+            return;
+        }
+
+        self.instance_vbo.bind(gl::ARRAY_BUFFER);
+        if self.instances.len() > self.instance_capacity {
+            self.instance_capacity = self.instances.len().next_power_of_two();
+            buffer_storage(
+                gl::ARRAY_BUFFER,
+                self.instance_capacity * size_of::<ParticleInstance>(),
+                gl::DYNAMIC_DRAW,
+            );
+        }
+        buffer_sub_data(gl::ARRAY_BUFFER, 0, &self.instances);
+
+        self.material.use_();
+        self.material.set_vector4f("uvRect", self.sprite.uv.to_vec4());
+        active_texture(gl::TEXTURE0);
+        self.sprite.texture.bind();
+
+        blend_func(gl::SRC_ALPHA, gl::ONE);
+        self.vao.bind();
+        draw_arrays_instanced(gl::TRIANGLES, 0, 6, self.instances.len());
+        blend_func(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
+    }
+}
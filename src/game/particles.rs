@@ -0,0 +1,261 @@
+use std::f32::consts::TAU;
+
+use glam::{vec2, Vec2, Vec3A, Vec4};
+
+use crate::util::Rng;
+
+use super::entity::Entity;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Particle {
+    /// The centre of the particle.
+    pub position: Vec2,
+    pub velocity: Vec2,
+    /// Tint and, in `w`, opacity.
+    pub color: Vec4,
+    /// Seconds left before the particle dies.
+    pub life: f32,
+}
+
+impl Particle {
+    const DEAD: Particle = Particle {
+        position: Vec2::ZERO,
+        velocity: Vec2::ZERO,
+        color: Vec4::ZERO,
+        life: 0.0,
+    };
+
+    pub fn is_alive(&self) -> bool {
+        self.life > 0.0
+    }
+}
+
+/// Where particles spawn and how fast they drift, taken from an entity so the simulation can run
+/// without anything to draw.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Emitter {
+    pub center: Vec2,
+    pub velocity: Vec2,
+}
+
+impl Emitter {
+    pub fn of(entity: &Entity) -> Emitter {
+        Emitter {
+            center: entity.position + entity.size / 2.0,
+            velocity: entity.velocity,
+        }
+    }
+}
+
+/// How the particles of a generator look and move.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ParticleSettings {
+    /// Seconds a particle lives, fading out as it goes.
+    pub life: f32,
+    /// Width and height of a particle in pixels.
+    pub size: f32,
+    /// Largest distance in pixels from the emitter's centre a particle spawns at, along each axis.
+    pub spread: f32,
+    /// Acceleration of every particle, in pixels per second squared.
+    pub gravity: Vec2,
+}
+
+/// A fixed pool of particles, reused as they die so a generator never allocates after it is made.
+/// Knows nothing about drawing, see `ParticleRenderer`.
+#[derive(Debug, Clone)]
+pub struct ParticleGenerator {
+    particles: Vec<Particle>,
+    settings: ParticleSettings,
+    /// Where the search for a dead particle starts, right after the last one spawned.
+    last_used: usize,
+    rng: Rng,
+}
+
+impl ParticleGenerator {
+    pub fn new(amount: usize, settings: ParticleSettings, rng: Rng) -> ParticleGenerator {
+        ParticleGenerator {
+            particles: vec![Particle::DEAD; amount],
+            settings,
+            last_used: 0,
+            rng,
+        }
+    }
+
+    pub fn settings(&self) -> &ParticleSettings {
+        &self.settings
+    }
+
+    /// Moves and fades every live particle by `dt` seconds, then spawns `new_particles` around the
+    /// emitter, drifting along with it, like a trail.
+    pub fn update(&mut self, dt: f32, emitter: Emitter, new_particles: usize) {
+        self.simulate(dt);
+
+        for _ in 0..new_particles {
+            let spread = self.settings.spread;
+            let jitter = vec2(self.rng.range(-spread, spread), self.rng.range(-spread, spread));
+            let brightness = self.rng.range(0.5, 1.0);
+            self.spawn(
+                emitter.center + jitter,
+                emitter.velocity * 0.1,
+                Vec4::new(brightness, brightness, brightness, 1.0),
+            );
+        }
+    }
+
+    /// Throws `count` particles of `color` out of the rectangle at `position` of `size` in every
+    /// direction, like the debris of a broken brick.
+    pub fn burst(&mut self, position: Vec2, size: Vec2, color: Vec3A, count: usize) {
+        for _ in 0..count {
+            let at = position + size * vec2(self.rng.next_f32(), self.rng.next_f32());
+            let velocity = Vec2::from_angle(self.rng.range(0.0, TAU)) * self.rng.range(40.0, 160.0);
+            let shade = self.rng.range(0.6, 1.0);
+            self.spawn(at, velocity, (color * shade).extend(1.0));
+        }
+    }
+
+    /// The particles still alive, in no particular order.
+    pub fn live(&self) -> impl Iterator<Item = &Particle> {
+        self.particles.iter().filter(|particle| particle.is_alive())
+    }
+
+    /// Kills every particle.
+    pub fn clear(&mut self) {
+        self.particles.fill(Particle::DEAD);
+        self.last_used = 0;
+    }
+
+    /// Moves and fades every live particle by `dt` seconds without spawning any.
+    pub fn simulate(&mut self, dt: f32) {
+        let ParticleSettings { life, gravity, .. } = self.settings;
+        for particle in self.particles.iter_mut().filter(|particle| particle.is_alive()) {
+            particle.life -= dt;
+            particle.velocity += gravity * dt;
+            particle.position += particle.velocity * dt;
+            particle.color.w = (particle.life / life).max(0.0);
+        }
+    }
+
+    fn spawn(&mut self, position: Vec2, velocity: Vec2, color: Vec4) {
+        let index = self.first_unused();
+        self.particles[index] = Particle {
+            position,
+            velocity,
+            color,
+            life: self.settings.life,
+        };
+        self.last_used = index;
+    }
+
+    /// The first dead particle after the last one spawned, wrapping around. Dead particles tend to
+    /// be found right away that way. When every particle is alive, the first one is replaced.
+    fn first_unused(&self) -> usize {
+        let amount = self.particles.len();
+        (0..amount)
+            .map(|i| (self.last_used + i) % amount)
+            .find(|&i| !self.particles[i].is_alive())
+            .unwrap_or(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::vec3a;
+
+    use super::*;
+
+    const SETTINGS: ParticleSettings = ParticleSettings {
+        life: 1.0,
+        size: 10.0,
+        spread: 5.0,
+        gravity: Vec2::ZERO,
+    };
+
+    fn emitter(center: Vec2, velocity: Vec2) -> Emitter {
+        Emitter { center, velocity }
+    }
+
+    fn alive(generator: &ParticleGenerator) -> Vec<bool> {
+        generator.particles.iter().map(Particle::is_alive).collect()
+    }
+
+    #[test]
+    fn spawns_around_the_emitter_and_moves_along_with_it() {
+        let ball = emitter(vec2(110.0, 110.0), vec2(50.0, -100.0));
+        let mut generator = ParticleGenerator::new(10, SETTINGS, Rng::new(1));
+        generator.update(0.0, ball, 3);
+
+        assert_eq!(generator.live().count(), 3);
+        for particle in generator.live() {
+            assert!((particle.position - vec2(110.0, 110.0)).abs().max_element() <= 5.0);
+            assert_eq!(particle.velocity, vec2(5.0, -10.0));
+            assert_eq!(particle.color.w, 1.0);
+        }
+
+        let before: Vec<Vec2> = generator.live().map(|particle| particle.position).collect();
+        generator.update(0.5, ball, 0);
+        let after: Vec<Vec2> = generator.live().map(|particle| particle.position).collect();
+        for (before, after) in before.iter().zip(&after) {
+            assert!((*after - *before).abs_diff_eq(vec2(2.5, -5.0), 1e-4));
+        }
+        assert!(generator.live().all(|particle| particle.color.w == 0.5));
+    }
+
+    #[test]
+    fn particles_die_once_their_life_is_over() {
+        let ball = emitter(Vec2::ZERO, Vec2::ZERO);
+        let mut generator = ParticleGenerator::new(4, SETTINGS, Rng::new(1));
+        generator.update(0.0, ball, 2);
+        generator.update(0.75, ball, 1);
+        assert_eq!(alive(&generator), vec![true, true, true, false]);
+
+        generator.update(0.25, ball, 0);
+        assert_eq!(alive(&generator), vec![false, false, true, false]);
+        generator.update(1.0, ball, 0);
+        assert_eq!(generator.live().count(), 0);
+    }
+
+    #[test]
+    fn reuses_dead_particles_and_replaces_the_first_when_all_are_alive() {
+        let ball = emitter(Vec2::ZERO, Vec2::ZERO);
+        let mut generator = ParticleGenerator::new(3, SETTINGS, Rng::new(1));
+        generator.update(0.0, ball, 3);
+        assert_eq!(alive(&generator), vec![true; 3]);
+
+        // a full pool gives up its first particle
+        generator.particles[0].life = 0.5;
+        generator.update(0.0, ball, 1);
+        assert_eq!(generator.particles[0].life, 1.0);
+        assert_eq!(generator.live().count(), 3);
+
+        // after the last particle spawned comes the next dead one
+        generator.particles[1].life = 0.0;
+        generator.particles[2].life = 0.0;
+        generator.update(0.0, ball, 1);
+        assert_eq!(alive(&generator), vec![true, true, false]);
+    }
+
+    #[test]
+    fn bursts_out_of_the_rectangle_in_its_color() {
+        let mut generator = ParticleGenerator::new(20, SETTINGS, Rng::new(7));
+        generator.burst(vec2(40.0, 20.0), vec2(80.0, 30.0), vec3a(0.2, 0.6, 1.0), 12);
+
+        assert_eq!(generator.live().count(), 12);
+        for particle in generator.live() {
+            assert!(particle.position.cmpge(vec2(40.0, 20.0)).all());
+            assert!(particle.position.cmplt(vec2(120.0, 50.0)).all());
+            assert!((particle.color.x / particle.color.z - 0.2).abs() < 1e-5);
+            assert!(particle.velocity.length() >= 40.0);
+        }
+    }
+
+    #[test]
+    fn the_same_seed_gives_the_same_particles() {
+        let burst = |seed| {
+            let mut generator = ParticleGenerator::new(8, SETTINGS, Rng::new(seed));
+            generator.burst(Vec2::ZERO, vec2(10.0, 10.0), vec3a(1.0, 1.0, 1.0), 8);
+            generator.particles
+        };
+        assert_eq!(burst(3), burst(3));
+        assert_ne!(burst(3), burst(4));
+    }
+}
@@ -0,0 +1,273 @@
+use std::{mem::size_of, rc::Rc};
+
+use crate::{
+    error::{Error, Result},
+    opengl::{
+        active_texture, blit_framebuffer, buffer_data, check_framebuffer_status, clear, clear_color, draw_arrays,
+        enable_vertex_attrib_array, framebuffer_renderbuffer, framebuffer_texture_2d, gen_buffers, gen_framebuffers,
+        gen_renderbuffers, gen_vertex_arrays, renderbuffer_storage_multisample, vertex_attrib_pointer, viewport,
+        Buffer, Framebuffer, Renderbuffer, VertexAttributeArray,
+    },
+};
+
+use super::{material::Material, texture2d::Texture2D};
+
+/// A full screen effect of `post_process.frag`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Effect {
+    /// Wobbles and blurs the screen.
+    Shake,
+    /// Turns the screen upside down and inverts its colors.
+    Confuse,
+    /// Swirls the screen around and shows only its edges.
+    Chaos,
+}
+
+impl Effect {
+    const ALL: [Effect; 3] = [Effect::Shake, Effect::Confuse, Effect::Chaos];
+
+    fn uniform(self) -> &'static str {
+        match self {
+            Effect::Shake => "shake",
+            Effect::Confuse => "confuse",
+            Effect::Chaos => "chaos",
+        }
+    }
+}
+
+/// Which effects are on and for how long. Each effect has its own timer, so effects started at
+/// different times overlap and run out on their own.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Effects {
+    /// Seconds each effect stays on for, in the order of `Effect::ALL`.
+    remaining: [f32; 3],
+}
+
+impl Effects {
+    /// Turns `effect` on for `seconds`, or keeps it on for longer if it already is. An infinite
+    /// duration keeps it on for good.
+    pub fn enable(&mut self, effect: Effect, seconds: f32) {
+        let remaining = &mut self.remaining[effect as usize];
+        *remaining = remaining.max(seconds);
+    }
+
+    pub fn is_active(&self, effect: Effect) -> bool {
+        self.remaining[effect as usize] > 0.0
+    }
+
+    /// Counts the timers of the active effects down by `dt` seconds.
+    pub fn update(&mut self, dt: f32) {
+        for remaining in &mut self.remaining {
+            *remaining = (*remaining - dt).max(0.0);
+        }
+    }
+}
+
+/// Renders the scene into a multisampled framebuffer, resolves it to a texture and draws that
+/// on the screen through the active `Effects`. Everything drawn between `begin_render` and
+/// `end_render` ends up in the texture.
+pub struct PostProcessor {
+    material: Rc<Material>,
+    width: i32,
+    height: i32,
+    /// The size of the window's framebuffer, which can differ from the scene's.
+    window_size: (i32, i32),
+    multisampled: Framebuffer,
+    resolved: Framebuffer,
+    /// The color buffer of `multisampled`, kept to be deleted with it.
+    _renderbuffer: Renderbuffer,
+    texture: Texture2D,
+    vao: VertexAttributeArray,
+    /// Only read through `vao`, kept to be deleted with it.
+    _vbo: Buffer,
+}
+
+impl PostProcessor {
+    pub fn new(material: Rc<Material>, width: u32, height: u32, samples: i32) -> Result<PostProcessor> {
+        let (width, height) = (width as i32, height as i32);
+        let [multisampled, resolved] = gen_framebuffers::<2>();
+        let [renderbuffer] = gen_renderbuffers::<1>();
+
+        multisampled.bind(gl::FRAMEBUFFER);
+        renderbuffer.bind();
+        renderbuffer_storage_multisample(samples, gl::RGB8, width, height);
+        framebuffer_renderbuffer(gl::FRAMEBUFFER, gl::COLOR_ATTACHMENT0, &renderbuffer);
+        check_complete()?;
+
+        // repeats, so the chaos effect can move the scene around without running out of it
+        let texture = Texture2D::new(gl::RGB, gl::RGB, gl::REPEAT, gl::REPEAT, gl::LINEAR, gl::LINEAR);
+        texture.generate(width, height, Vec::new());
+        resolved.bind(gl::FRAMEBUFFER);
+        framebuffer_texture_2d(gl::FRAMEBUFFER, gl::COLOR_ATTACHMENT0, texture.texture());
+        check_complete()?;
+        Framebuffer::unbind(gl::FRAMEBUFFER);
+
+        let [vao] = gen_vertex_arrays::<1>();
+        let [vbo] = gen_buffers::<1>();
+
+        #[rustfmt::skip]
+        let vertices: [f32;12] = [
+            -1.0, -1.0,
+             1.0,  1.0,
+            -1.0,  1.0,
+
+            -1.0, -1.0,
+             1.0, -1.0,
+             1.0,  1.0,
+        ];
+
+        vao.bind();
+        vbo.bind(gl::ARRAY_BUFFER);
+        buffer_data(gl::ARRAY_BUFFER, &vertices, gl::STATIC_DRAW);
+        vertex_attrib_pointer(0, 2, gl::FLOAT, false, 2 * size_of::<f32>(), 0);
+        enable_vertex_attrib_array(0);
+
+        material.use_();
+        material.set_integer("image", 0);
+
+        Ok(PostProcessor {
+            material,
+            width,
+            height,
+            window_size: (width, height),
+            multisampled,
+            resolved,
+            _renderbuffer: renderbuffer,
+            texture,
+            vao,
+            _vbo: vbo,
+        })
+    }
+
+    /// Sets the size of the window's framebuffer the scene is drawn on.
+    pub fn resize_window(&mut self, width: i32, height: i32) {
+        self.window_size = (width, height);
+    }
+
+    /// Sends what is drawn from now on to the multisampled framebuffer, cleared to black.
+    pub fn begin_render(&self) {
+        self.multisampled.bind(gl::FRAMEBUFFER);
+        viewport(0, 0, self.width, self.height);
+        clear_color(0.0, 0.0, 0.0, 1.0);
+        clear(gl::COLOR_BUFFER_BIT);
+    }
+
+    /// Resolves the multisampled framebuffer into the texture and draws to the screen again.
+    pub fn end_render(&self) {
+        self.multisampled.bind(gl::READ_FRAMEBUFFER);
+        self.resolved.bind(gl::DRAW_FRAMEBUFFER);
+        let rect = [0, 0, self.width, self.height];
+        blit_framebuffer(rect, rect, gl::COLOR_BUFFER_BIT, gl::NEAREST);
+        Framebuffer::unbind(gl::FRAMEBUFFER);
+        let (width, height) = self.window_size;
+        viewport(0, 0, width, height);
+    }
+
+    /// Draws the scene on the screen through `effects`. `time` drives the shaking and swirling.
+    pub fn render(&self, effects: &Effects, time: f32) {
+        self.material.use_();
+        self.material.set_float("time", time);
+        for effect in Effect::ALL {
+            self.material
+                .set_integer(effect.uniform(), effects.is_active(effect) as i32);
+        }
+
+        active_texture(gl::TEXTURE0);
+        self.texture.bind();
+        self.vao.bind();
+        draw_arrays(gl::TRIANGLES, 0, 6);
+    }
+}
+
+fn check_complete() -> Result<()> {
+    match check_framebuffer_status(gl::FRAMEBUFFER) {
+        gl::FRAMEBUFFER_COMPLETE => Ok(()),
+        status => Err(Error::Framebuffer { status }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use crate::{
+        opengl::recording::{GlCall, RecordingBackend},
+        util::read_file,
+    };
+
+    use super::*;
+
+    #[test]
+    fn effects_run_out_on_their_own_timers() {
+        let mut effects = Effects::default();
+        effects.enable(Effect::Shake, 0.05);
+        effects.enable(Effect::Confuse, 1.0);
+        assert!(effects.is_active(Effect::Shake) && effects.is_active(Effect::Confuse));
+        assert!(!effects.is_active(Effect::Chaos));
+
+        effects.update(0.1);
+        assert!(!effects.is_active(Effect::Shake));
+        assert!(effects.is_active(Effect::Confuse));
+
+        // enabling an active effect again never cuts it short
+        effects.enable(Effect::Confuse, 0.2);
+        effects.update(0.5);
+        assert!(effects.is_active(Effect::Confuse));
+        effects.update(0.5);
+        assert!(!effects.is_active(Effect::Confuse));
+    }
+
+    #[test]
+    fn effects_enabled_forever_stay_on() {
+        let mut effects = Effects::default();
+        effects.enable(Effect::Chaos, f32::INFINITY);
+        effects.update(1000.0);
+        assert!(effects.is_active(Effect::Chaos));
+    }
+
+    #[test]
+    fn renders_the_scene_at_its_size_and_the_quad_at_the_window_size() {
+        let recorder = RecordingBackend::install();
+        let material = Material::new(
+            "post_process",
+            &read_file(Path::new("src/shaders/post_process.vert")).unwrap(),
+            &read_file(Path::new("src/shaders/post_process.frag")).unwrap(),
+        )
+        .unwrap();
+        let mut post_processor = PostProcessor::new(Rc::new(material), 800, 600, 4).unwrap();
+        post_processor.resize_window(1600, 1200);
+        recorder.clear();
+
+        post_processor.begin_render();
+        post_processor.end_render();
+        post_processor.render(&Effects::default(), 0.0);
+
+        let calls: Vec<_> = recorder
+            .calls()
+            .into_iter()
+            .filter(|call| matches!(call, GlCall::Viewport { .. } | GlCall::DrawArrays { .. }))
+            .collect();
+        assert_eq!(
+            calls,
+            [
+                GlCall::Viewport {
+                    x: 0,
+                    y: 0,
+                    width: 800,
+                    height: 600
+                },
+                GlCall::Viewport {
+                    x: 0,
+                    y: 0,
+                    width: 1600,
+                    height: 1200
+                },
+                GlCall::DrawArrays {
+                    mode: gl::TRIANGLES,
+                    first: 0,
+                    count: 6
+                },
+            ]
+        );
+    }
+}
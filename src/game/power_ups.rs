@@ -0,0 +1,269 @@
+use glam::{vec2, vec3a, Vec2, Vec3A};
+
+use crate::util::Rng;
+
+use super::{
+    collision::{aabb_aabb, Aabb},
+    loader::Loader,
+    sprite_renderer::SpriteRenderer,
+};
+
+/// Width and height of a falling power-up in pixels.
+pub const POWER_UP_SIZE: Vec2 = vec2(60.0, 20.0);
+/// How fast power-ups fall, in pixels per second.
+const FALL_VELOCITY: Vec2 = vec2(0.0, 150.0);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PowerUpKind {
+    /// The ball moves faster.
+    Speed,
+    /// The ball sticks to the paddle when it lands on it, until it is released again.
+    Sticky,
+    /// The ball breaks through bricks without bouncing off them, solid bricks excepted.
+    PassThrough,
+    /// The paddle gets wider.
+    PadSizeIncrease,
+    /// The screen is turned upside down and its colors inverted.
+    Confuse,
+    /// The screen swirls around showing only its edges.
+    Chaos,
+}
+
+impl PowerUpKind {
+    pub const ALL: [PowerUpKind; 6] = [
+        PowerUpKind::Speed,
+        PowerUpKind::Sticky,
+        PowerUpKind::PassThrough,
+        PowerUpKind::PadSizeIncrease,
+        PowerUpKind::Confuse,
+        PowerUpKind::Chaos,
+    ];
+
+    /// Seconds the power-up lasts once caught.
+    pub fn duration(self) -> f32 {
+        match self {
+            PowerUpKind::Speed => 10.0,
+            PowerUpKind::Sticky => 20.0,
+            PowerUpKind::PassThrough => 10.0,
+            PowerUpKind::PadSizeIncrease => 10.0,
+            PowerUpKind::Confuse => 15.0,
+            PowerUpKind::Chaos => 15.0,
+        }
+    }
+
+    /// Chance a destroyed brick drops this power-up. The ones that get in the way of the player
+    /// drop more often.
+    pub fn drop_chance(self) -> f32 {
+        match self {
+            PowerUpKind::Confuse | PowerUpKind::Chaos => 1.0 / 15.0,
+            _ => 1.0 / 75.0,
+        }
+    }
+
+    fn sprite(self) -> &'static str {
+        match self {
+            PowerUpKind::Speed => "power_up_speed",
+            PowerUpKind::Sticky => "power_up_sticky",
+            PowerUpKind::PassThrough => "power_up_pass_through",
+            PowerUpKind::PadSizeIncrease => "power_up_pad_size_increase",
+            PowerUpKind::Confuse => "power_up_confuse",
+            PowerUpKind::Chaos => "power_up_chaos",
+        }
+    }
+
+    fn color(self) -> Vec3A {
+        match self {
+            PowerUpKind::Speed => vec3a(0.5, 0.5, 1.0),
+            PowerUpKind::Sticky => vec3a(1.0, 0.5, 1.0),
+            PowerUpKind::PassThrough => vec3a(0.5, 1.0, 0.5),
+            PowerUpKind::PadSizeIncrease => vec3a(1.0, 0.6, 0.4),
+            PowerUpKind::Confuse => vec3a(1.0, 0.3, 0.3),
+            PowerUpKind::Chaos => vec3a(0.9, 0.25, 0.25),
+        }
+    }
+}
+
+/// A power-up falling towards the paddle.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PowerUp {
+    pub kind: PowerUpKind,
+    /// The top left corner.
+    pub position: Vec2,
+}
+
+impl PowerUp {
+    pub fn aabb(&self) -> Aabb {
+        Aabb::new(self.position, POWER_UP_SIZE)
+    }
+}
+
+/// The power-ups falling and the ones caught, each active for its own duration. Catching one
+/// that is already active starts its duration over.
+#[derive(Debug, Clone)]
+pub struct PowerUps {
+    falling: Vec<PowerUp>,
+    /// Seconds each power-up stays active for, in the order of `PowerUpKind::ALL`.
+    remaining: [f32; 6],
+    rng: Rng,
+}
+
+impl PowerUps {
+    pub fn new(rng: Rng) -> PowerUps {
+        PowerUps {
+            falling: Vec::new(),
+            remaining: [0.0; 6],
+            rng,
+        }
+    }
+
+    /// Rolls whether a brick destroyed at `center` drops a power-up, and which one. Returns the
+    /// power-up dropped, if any.
+    pub fn roll_drop(&mut self, center: Vec2) -> Option<PowerUpKind> {
+        let mut roll = self.rng.next_f32();
+        for kind in PowerUpKind::ALL {
+            if roll < kind.drop_chance() {
+                self.falling.push(PowerUp {
+                    kind,
+                    position: center - POWER_UP_SIZE / 2.0,
+                });
+                return Some(kind);
+            }
+            roll -= kind.drop_chance();
+        }
+        None
+    }
+
+    /// Counts the active power-ups down by `dt` seconds and moves the falling ones. The ones
+    /// touching `paddle` are caught and activated, the ones below `bottom` are lost.
+    /// Returns the power-ups caught.
+    pub fn update(&mut self, dt: f32, paddle: &Aabb, bottom: f32) -> Vec<PowerUpKind> {
+        for remaining in &mut self.remaining {
+            *remaining = (*remaining - dt).max(0.0);
+        }
+
+        let mut caught = Vec::new();
+        self.falling.retain_mut(|power_up| {
+            power_up.position += FALL_VELOCITY * dt;
+            if aabb_aabb(&power_up.aabb(), paddle).is_some() {
+                caught.push(power_up.kind);
+                return false;
+            }
+            power_up.position.y < bottom
+        });
+        for &kind in &caught {
+            self.activate(kind);
+        }
+        caught
+    }
+
+    /// Turns `kind` on for its full duration.
+    pub fn activate(&mut self, kind: PowerUpKind) {
+        self.remaining[kind as usize] = kind.duration();
+    }
+
+    pub fn is_active(&self, kind: PowerUpKind) -> bool {
+        self.remaining[kind as usize] > 0.0
+    }
+
+    #[cfg(test)]
+    pub fn falling(&self) -> &[PowerUp] {
+        &self.falling
+    }
+
+    /// Removes the falling power-ups and turns the active ones off.
+    pub fn clear(&mut self) {
+        self.falling.clear();
+        self.remaining = [0.0; 6];
+    }
+
+    pub fn draw(&self, renderer: &mut SpriteRenderer, loader: &Loader) {
+        for power_up in &self.falling {
+            let kind = power_up.kind;
+            renderer.queue(
+                &loader.get_sprite(kind.sprite()),
+                power_up.position,
+                POWER_UP_SIZE,
+                0.0,
+                kind.color(),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn paddle() -> Aabb {
+        Aabb::new(vec2(100.0, 580.0), vec2(100.0, 20.0))
+    }
+
+    fn drops(seed: u64, bricks: usize) -> Vec<Option<PowerUpKind>> {
+        let mut power_ups = PowerUps::new(Rng::new(seed));
+        (0..bricks).map(|_| power_ups.roll_drop(Vec2::ZERO)).collect()
+    }
+
+    #[test]
+    fn the_same_seed_drops_the_same_power_ups() {
+        assert_eq!(drops(5, 100), drops(5, 100));
+        assert_ne!(drops(5, 100), drops(6, 100));
+    }
+
+    #[test]
+    fn drops_each_kind_about_as_often_as_its_chance() {
+        let drops = drops(1, 30_000);
+        for kind in PowerUpKind::ALL {
+            let rate = drops.iter().filter(|&&drop| drop == Some(kind)).count() as f32 / drops.len() as f32;
+            assert!(
+                (rate - kind.drop_chance()).abs() < 0.01,
+                "{:?} dropped at {}",
+                kind,
+                rate
+            );
+        }
+    }
+
+    #[test]
+    fn falling_power_ups_are_caught_by_the_paddle_or_lost() {
+        let mut power_ups = PowerUps::new(Rng::new(1));
+        power_ups.falling = vec![
+            PowerUp {
+                kind: PowerUpKind::Sticky,
+                position: vec2(120.0, 500.0),
+            },
+            PowerUp {
+                kind: PowerUpKind::Chaos,
+                position: vec2(400.0, 500.0),
+            },
+        ];
+
+        assert_eq!(power_ups.update(0.1, &paddle(), 600.0), vec![]);
+        assert_eq!(power_ups.update(0.5, &paddle(), 600.0), vec![PowerUpKind::Sticky]);
+        assert!(power_ups.is_active(PowerUpKind::Sticky));
+        assert_eq!(power_ups.falling().len(), 1);
+
+        power_ups.update(0.5, &paddle(), 600.0);
+        assert!(power_ups.falling().is_empty());
+        assert!(!power_ups.is_active(PowerUpKind::Chaos));
+    }
+
+    #[test]
+    fn overlapping_power_ups_run_out_on_their_own() {
+        let mut power_ups = PowerUps::new(Rng::new(1));
+        let catch = |power_ups: &mut PowerUps, dt| power_ups.update(dt, &paddle(), 600.0);
+
+        power_ups.activate(PowerUpKind::PassThrough);
+        catch(&mut power_ups, 5.0);
+        power_ups.activate(PowerUpKind::Speed);
+        catch(&mut power_ups, 5.0);
+        assert!(!power_ups.is_active(PowerUpKind::PassThrough));
+        assert!(power_ups.is_active(PowerUpKind::Speed));
+
+        // catching it again starts it over
+        power_ups.activate(PowerUpKind::Speed);
+        catch(&mut power_ups, 9.0);
+        assert!(power_ups.is_active(PowerUpKind::Speed));
+        catch(&mut power_ups, 1.0);
+        assert_eq!(PowerUpKind::ALL.map(|kind| power_ups.is_active(kind)), [false; 6]);
+    }
+}
@@ -0,0 +1,317 @@
+use std::path::{Component, Path, PathBuf};
+
+use crate::{
+    error::{Error, Result},
+    util::read_file,
+};
+
+/// Where a line of preprocessed source came from, 1 based like compiler logs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Origin {
+    pub file: PathBuf,
+    pub line: usize,
+}
+
+/// A shader with its includes resolved and defines injected, ready to compile.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ShaderSource {
+    pub text: String,
+    /// The origin of every line of `text`, `None` for the injected defines.
+    origins: Vec<Option<Origin>>,
+    /// The shader file followed by every file it includes, once each.
+    files: Vec<PathBuf>,
+}
+
+impl ShaderSource {
+    /// Preprocesses the shader at `path`, see `preprocess_with`.
+    pub fn load(path: &Path, defines: &[(String, String)]) -> Result<ShaderSource> {
+        ShaderSource::preprocess_with(path, defines, read_file)
+    }
+
+    /// Resolves `#include "file"` relative to the including file and adds a `#define` for every
+    /// `(name, value)` pair right after the `#version` line. Files are read with `read`, so this
+    /// can run without touching the disk.
+    pub fn preprocess_with(
+        path: &Path,
+        defines: &[(String, String)],
+        mut read: impl FnMut(&Path) -> Result<String>,
+    ) -> Result<ShaderSource> {
+        let path = normalize(path);
+        let source = read(&path)?;
+
+        // #version has to come first, so the defines go right after it
+        let defines_at = match source.lines().enumerate().find(|(_, line)| !line.trim().is_empty()) {
+            Some((i, line)) if line.trim_start().starts_with("#version") => i + 1,
+            _ => 0,
+        };
+
+        let mut shader = ShaderSource {
+            files: vec![path.clone()],
+            ..ShaderSource::default()
+        };
+        let mut lines = source.lines().enumerate();
+        for (i, line) in lines.by_ref().take(defines_at) {
+            shader.push(line, Some(&path), i + 1);
+        }
+        for (name, value) in defines {
+            shader.push(format!("#define {} {}", name, value).trim_end(), None, 0);
+        }
+        shader.expand(&path, lines, &mut vec![path.clone()], &mut read)?;
+
+        Ok(shader)
+    }
+
+    pub fn files(&self) -> &[PathBuf] {
+        &self.files
+    }
+
+    /// The file and line a line of `text`, counted from 1, came from.
+    pub fn origin(&self, line: usize) -> Option<&Origin> {
+        self.origins.get(line.checked_sub(1)?)?.as_ref()
+    }
+
+    /// Rewrites the line references in a compiler log, `0(12)` or `0:12` depending on the driver,
+    /// to the file and line they came from.
+    pub fn map_log(&self, log: &str) -> String {
+        log.lines()
+            .map(|line| self.map_log_line(line))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    fn map_log_line(&self, line: &str) -> String {
+        for (start, _) in line.match_indices('0') {
+            if line[..start].ends_with(|c: char| c.is_ascii_alphanumeric()) {
+                continue;
+            }
+
+            let rest = &line[start + 1..];
+            let close = match rest.chars().next() {
+                Some('(') => Some(')'),
+                Some(':') => None,
+                _ => continue,
+            };
+            let digits = rest[1..].len() - rest[1..].trim_start_matches(|c: char| c.is_ascii_digit()).len();
+            let mut end = start + 2 + digits;
+            if let Some(close) = close {
+                if !line[end..].starts_with(close) {
+                    continue;
+                }
+                end += 1;
+            }
+
+            let Some(origin) = rest[1..1 + digits].parse().ok().and_then(|n| self.origin(n)) else {
+                continue;
+            };
+            return format!(
+                "{}{}:{}{}",
+                &line[..start],
+                origin.file.display(),
+                origin.line,
+                &line[end..]
+            );
+        }
+        line.to_string()
+    }
+
+    fn push(&mut self, line: &str, file: Option<&Path>, line_no: usize) {
+        self.text.push_str(line);
+        self.text.push('\n');
+        self.origins.push(file.map(|file| Origin {
+            file: file.to_path_buf(),
+            line: line_no,
+        }));
+    }
+
+    /// Copies `lines` of `path`, replacing includes with the lines of the included file.
+    /// `stack` holds the files being expanded, to catch a file that ends up including itself.
+    fn expand<'a>(
+        &mut self,
+        path: &Path,
+        lines: impl Iterator<Item = (usize, &'a str)>,
+        stack: &mut Vec<PathBuf>,
+        read: &mut impl FnMut(&Path) -> Result<String>,
+    ) -> Result<()> {
+        for (i, line) in lines {
+            let error = |reason: String| Error::Preprocess {
+                path: path.to_path_buf(),
+                line: i + 1,
+                reason,
+            };
+
+            let Some(include) = line
+                .trim_start()
+                .strip_prefix('#')
+                .and_then(|directive| directive.trim_start().strip_prefix("include"))
+            else {
+                self.push(line, Some(path), i + 1);
+                continue;
+            };
+
+            let Some(file) = include
+                .trim()
+                .strip_prefix('"')
+                .and_then(|include| include.strip_suffix('"'))
+            else {
+                return Err(error("expected #include \"file\"".to_string()));
+            };
+            let included = normalize(&path.parent().unwrap_or(Path::new("")).join(file));
+            if stack.contains(&included) {
+                let cycle: Vec<_> = stack
+                    .iter()
+                    .chain([&included])
+                    .map(|file| file.display().to_string())
+                    .collect();
+                return Err(error(format!("include cycle {}", cycle.join(" -> "))));
+            }
+
+            let source = read(&included)?;
+            if !self.files.contains(&included) {
+                self.files.push(included.clone());
+            }
+            stack.push(included.clone());
+            self.expand(&included, source.lines().enumerate(), stack, read)?;
+            stack.pop();
+        }
+        Ok(())
+    }
+}
+
+/// Removes `.` and resolves `..` without touching the file system, so the same file is always
+/// spelled the same.
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir if matches!(normalized.components().next_back(), Some(Component::Normal(_))) => {
+                normalized.pop();
+            }
+            component => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, io};
+
+    use super::*;
+
+    /// Preprocesses `path` from in memory files.
+    fn preprocess(files: &[(&str, &str)], path: &str, defines: &[(&str, &str)]) -> Result<ShaderSource> {
+        let files: HashMap<PathBuf, String> = files
+            .iter()
+            .map(|(path, source)| (PathBuf::from(path), source.to_string()))
+            .collect();
+        let defines: Vec<_> = defines
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect();
+
+        ShaderSource::preprocess_with(Path::new(path), &defines, |path| {
+            files.get(path).cloned().ok_or_else(|| Error::Io {
+                path: path.to_path_buf(),
+                source: io::ErrorKind::NotFound.into(),
+            })
+        })
+    }
+
+    #[test]
+    fn resolves_includes_relative_to_the_including_file() {
+        let shader = preprocess(
+            &[
+                (
+                    "shaders/sprite.frag",
+                    "#version 330 core\n#include \"lib/color.glsl\"\nvoid main() {}",
+                ),
+                ("shaders/lib/color.glsl", "#include \"../common.glsl\"\nvec4 tint;"),
+                ("shaders/common.glsl", "const float PI = 3.14;"),
+            ],
+            "shaders/sprite.frag",
+            &[],
+        )
+        .unwrap();
+
+        assert_eq!(
+            shader.text,
+            "#version 330 core\nconst float PI = 3.14;\nvec4 tint;\nvoid main() {}\n"
+        );
+        assert_eq!(
+            shader.files(),
+            [
+                PathBuf::from("shaders/sprite.frag"),
+                PathBuf::from("shaders/lib/color.glsl"),
+                PathBuf::from("shaders/common.glsl"),
+            ]
+        );
+    }
+
+    #[test]
+    fn injects_defines_after_the_version() {
+        let shader = preprocess(
+            &[("a.vert", "\n#version 330 core\nvoid main() {}")],
+            "a.vert",
+            &[("INSTANCED", ""), ("SAMPLES", "4")],
+        )
+        .unwrap();
+
+        assert_eq!(
+            shader.text,
+            "\n#version 330 core\n#define INSTANCED\n#define SAMPLES 4\nvoid main() {}\n"
+        );
+        assert_eq!(shader.origin(3), None);
+        assert_eq!(
+            shader.origin(5),
+            Some(&Origin {
+                file: "a.vert".into(),
+                line: 3
+            })
+        );
+    }
+
+    #[test]
+    fn include_cycles_are_an_error() {
+        let error = preprocess(
+            &[("a.glsl", "#include \"b.glsl\""), ("b.glsl", "\n#include \"./a.glsl\"")],
+            "a.glsl",
+            &[],
+        )
+        .unwrap_err();
+
+        assert_eq!(
+            error.to_string(),
+            "Error preprocessing b.glsl line 2: include cycle a.glsl -> b.glsl -> a.glsl"
+        );
+    }
+
+    #[test]
+    fn missing_includes_are_reported_with_their_path() {
+        let error = preprocess(&[("a.glsl", "#include \"missing.glsl\"")], "a.glsl", &[]).unwrap_err();
+        assert!(matches!(error, Error::Io { path, .. } if path == Path::new("missing.glsl")));
+
+        let error = preprocess(&[("a.glsl", "#include <b.glsl>")], "a.glsl", &[]).unwrap_err();
+        assert!(matches!(error, Error::Preprocess { line: 1, .. }));
+    }
+
+    #[test]
+    fn compiler_logs_point_at_the_original_lines() {
+        let shader = preprocess(
+            &[
+                ("main.frag", "#version 330 core\n#include \"lib.glsl\"\nvoid main() {}"),
+                ("lib.glsl", "float a;\nfloat b"),
+            ],
+            "main.frag",
+            &[("DEBUG", "1")],
+        )
+        .unwrap();
+
+        // nvidia, then mesa
+        assert_eq!(
+            shader.map_log("0(4) : error C0000: syntax error\nERROR: 0:5: 'void' : syntax error"),
+            "lib.glsl:2 : error C0000: syntax error\nERROR: main.frag:3: 'void' : syntax error"
+        );
+        assert_eq!(shader.map_log("10(4): unrelated"), "10(4): unrelated");
+    }
+}
@@ -0,0 +1,56 @@
+use crate::audio::Sound;
+
+use super::loader::Loader;
+
+/// Something happening in the game that makes a sound.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SoundEvent {
+    PaddleHit,
+    BrickHit,
+    SolidHit,
+    PowerUpPickup,
+}
+
+impl SoundEvent {
+    const ALL: [SoundEvent; 4] = [
+        SoundEvent::PaddleHit,
+        SoundEvent::BrickHit,
+        SoundEvent::SolidHit,
+        SoundEvent::PowerUpPickup,
+    ];
+
+    /// The name of the sound in the asset manifest.
+    fn sound(self) -> &'static str {
+        match self {
+            SoundEvent::PaddleHit => "paddle_hit",
+            SoundEvent::BrickHit => "brick_hit",
+            SoundEvent::SolidHit => "solid_hit",
+            SoundEvent::PowerUpPickup => "power_up",
+        }
+    }
+}
+
+/// The sounds of the game, looked up once so playing one is just a backend call.
+pub struct Sounds {
+    /// In the order of `SoundEvent::ALL`.
+    events: [Sound; 4],
+    music: Sound,
+}
+
+impl Sounds {
+    pub fn new(loader: &Loader) -> Sounds {
+        Sounds {
+            events: SoundEvent::ALL.map(|event| loader.get_sound(event.sound())),
+            music: loader.get_sound("music"),
+        }
+    }
+
+    pub fn play(&self, event: SoundEvent) {
+        self.events[event as usize].play();
+    }
+
+    /// Loops the background music for as long as the game runs.
+    pub fn start_music(&self) {
+        self.music.play_looped();
+    }
+}
@@ -0,0 +1,268 @@
+use glam::{vec2, Mat4, Vec2, Vec3, Vec3A, Vec4};
+
+use super::{atlas::UvRect, sprite_renderer::sprite_model};
+
+/// Corners of the unit quad, two triangles, in the same order `SpriteRenderer` draws them.
+#[rustfmt::skip]
+const QUAD: [Vec2; 6] = [
+    vec2(0.0, 1.0),
+    vec2(1.0, 0.0),
+    vec2(0.0, 0.0),
+
+    vec2(0.0, 1.0),
+    vec2(1.0, 1.0),
+    vec2(1.0, 0.0),
+];
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SpriteVertex {
+    pub position: Vec2,
+    pub uv: Vec2,
+    pub color: Vec3,
+}
+
+/// Per-instance data of the instanced sprite path, read by `sprite_batch.vert` built with `INSTANCED`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SpriteInstance {
+    pub model: Mat4,
+    /// The tint, with an alpha to fade the sprite out.
+    pub color: Vec4,
+    pub uv_rect: Vec4,
+}
+
+impl SpriteInstance {
+    pub fn new(position: Vec2, size: Vec2, rotate: f32, color: Vec4, uv: UvRect) -> SpriteInstance {
+        SpriteInstance {
+            model: sprite_model(position, size, rotate),
+            color,
+            uv_rect: uv.to_vec4(),
+        }
+    }
+}
+
+/// A run of vertices that share a texture and are drawn with one call.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Batch<T> {
+    pub texture: T,
+    pub first: usize,
+    pub count: usize,
+}
+
+/// Collects the sprites of a frame and turns them into one vertex buffer, drawn in queue order.
+///
+/// Only sprites queued one after another with the same texture share a batch, so whatever is
+/// queued later is always drawn on top.
+#[derive(Debug)]
+pub struct SpriteBatch<T> {
+    sprites: Vec<(T, [SpriteVertex; 6])>,
+}
+
+impl<T: Clone + PartialEq> SpriteBatch<T> {
+    pub fn new() -> SpriteBatch<T> {
+        SpriteBatch { sprites: Vec::new() }
+    }
+
+    /// Queues the `uv` part of a texture with the transform of `sprite_model`:
+    /// scaled to `size`, rotated by `rotate` degrees around its centre and moved to `position`.
+    pub fn push(&mut self, texture: T, uv: UvRect, position: Vec2, size: Vec2, rotate: f32, color: Vec3A) {
+        let model = sprite_model(position, size, rotate);
+
+        let vertices = QUAD.map(|corner| SpriteVertex {
+            position: model.transform_point3(Vec3::from((corner, 0.0))).truncate(),
+            uv: uv.lerp(corner),
+            color: color.into(),
+        });
+        self.sprites.push((texture, vertices));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sprites.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.sprites.clear();
+    }
+
+    pub fn build(&self) -> (Vec<SpriteVertex>, Vec<Batch<T>>) {
+        let mut vertices = Vec::with_capacity(self.sprites.len() * QUAD.len());
+        let mut batches: Vec<Batch<T>> = Vec::new();
+        for (texture, sprite) in &self.sprites {
+            match batches.last_mut() {
+                Some(batch) if batch.texture == *texture => batch.count += sprite.len(),
+                _ => batches.push(Batch {
+                    texture: texture.clone(),
+                    first: vertices.len(),
+                    count: sprite.len(),
+                }),
+            }
+            vertices.extend_from_slice(sprite);
+        }
+
+        (vertices, batches)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::{vec3, vec3a, vec4};
+
+    use super::*;
+
+    const EPSILON: f32 = 1e-4;
+
+    fn positions(vertices: &[SpriteVertex]) -> Vec<Vec2> {
+        vertices.iter().map(|vertex| vertex.position).collect()
+    }
+
+    #[test]
+    fn empty_batch_builds_nothing() {
+        let batch: SpriteBatch<u32> = SpriteBatch::new();
+        assert!(batch.is_empty());
+        assert_eq!(batch.build(), (vec![], vec![]));
+    }
+
+    #[test]
+    fn sprite_becomes_two_triangles_with_uvs_and_color() {
+        let mut batch = SpriteBatch::new();
+        batch.push(
+            1,
+            UvRect::FULL,
+            vec2(10.0, 20.0),
+            vec2(100.0, 50.0),
+            0.0,
+            vec3a(0.2, 0.4, 0.6),
+        );
+
+        let (vertices, batches) = batch.build();
+        assert_eq!(
+            positions(&vertices),
+            vec![
+                vec2(10.0, 70.0),
+                vec2(110.0, 20.0),
+                vec2(10.0, 20.0),
+                vec2(10.0, 70.0),
+                vec2(110.0, 70.0),
+                vec2(110.0, 20.0),
+            ]
+        );
+        assert_eq!(vertices.iter().map(|vertex| vertex.uv).collect::<Vec<_>>(), QUAD);
+        assert!(vertices.iter().all(|vertex| vertex.color == vec3(0.2, 0.4, 0.6)));
+        assert_eq!(
+            batches,
+            vec![Batch {
+                texture: 1,
+                first: 0,
+                count: 6,
+            }]
+        );
+    }
+
+    #[test]
+    fn rotation_is_around_the_sprite_centre() {
+        let mut batch = SpriteBatch::new();
+        batch.push(
+            1,
+            UvRect::FULL,
+            vec2(0.0, 0.0),
+            vec2(20.0, 10.0),
+            90.0,
+            vec3a(1.0, 1.0, 1.0),
+        );
+
+        let (vertices, _) = batch.build();
+        // top left corner of a 20x10 sprite turned a quarter around (10, 5)
+        assert!(vertices[2].position.abs_diff_eq(vec2(15.0, -5.0), EPSILON));
+        // bottom right corner
+        assert!(vertices[4].position.abs_diff_eq(vec2(5.0, 15.0), EPSILON));
+    }
+
+    #[test]
+    fn only_consecutive_sprites_with_the_same_texture_share_a_batch() {
+        let mut batch = SpriteBatch::new();
+        let size = vec2(1.0, 1.0);
+        let white = vec3a(1.0, 1.0, 1.0);
+        batch.push(7, UvRect::FULL, vec2(0.0, 0.0), size, 0.0, white);
+        batch.push(7, UvRect::FULL, vec2(1.0, 0.0), size, 0.0, white);
+        batch.push(3, UvRect::FULL, vec2(2.0, 0.0), size, 0.0, white);
+        batch.push(7, UvRect::FULL, vec2(3.0, 0.0), size, 0.0, white);
+
+        let (vertices, batches) = batch.build();
+        assert_eq!(vertices.len(), 24);
+        assert_eq!(
+            batches,
+            vec![
+                Batch {
+                    texture: 7,
+                    first: 0,
+                    count: 12,
+                },
+                Batch {
+                    texture: 3,
+                    first: 12,
+                    count: 6,
+                },
+                Batch {
+                    texture: 7,
+                    first: 18,
+                    count: 6,
+                },
+            ]
+        );
+        // the sprite queued last is drawn last, on top of the others
+        let top_left = |sprite: usize| vertices[sprite * 6 + 2].position;
+        assert_eq!(
+            (0..4).map(top_left).collect::<Vec<_>>(),
+            [0.0, 1.0, 2.0, 3.0].map(|x| vec2(x, 0.0))
+        );
+    }
+
+    #[test]
+    fn instance_places_the_quad_like_the_batch() {
+        let (position, size, color) = (vec2(30.0, 40.0), vec2(16.0, 8.0), vec3a(0.5, 0.5, 1.0));
+        let uv = UvRect {
+            min: vec2(0.25, 0.5),
+            max: vec2(0.5, 1.0),
+        };
+        let instance = SpriteInstance::new(position, size, 45.0, color.extend(1.0), uv);
+
+        let mut batch = SpriteBatch::new();
+        batch.push(1, uv, position, size, 45.0, color);
+        let (vertices, _) = batch.build();
+
+        for (corner, vertex) in QUAD.iter().zip(&vertices) {
+            let placed = instance.model.transform_point3(Vec3::from((*corner, 0.0))).truncate();
+            assert!(placed.abs_diff_eq(vertex.position, EPSILON));
+            assert_eq!(uv.lerp(*corner), vertex.uv);
+        }
+        assert_eq!(instance.color, vec4(0.5, 0.5, 1.0, 1.0));
+        assert_eq!(instance.uv_rect, vec4(0.25, 0.5, 0.5, 1.0));
+    }
+
+    #[test]
+    fn instance_layout_matches_the_shader_attributes() {
+        // four vec4 columns at locations 1-4 followed by the vec4 color at location 5 and the
+        // vec4 uv rect at location 6
+        assert_eq!(std::mem::offset_of!(SpriteInstance, model), 0);
+        assert_eq!(std::mem::offset_of!(SpriteInstance, color), 16 * 4);
+        assert_eq!(std::mem::offset_of!(SpriteInstance, uv_rect), 20 * 4);
+        // the stride is padded up to the alignment of Mat4
+        assert_eq!(std::mem::size_of::<SpriteInstance>() % std::mem::align_of::<Mat4>(), 0);
+    }
+
+    #[test]
+    fn clear_empties_the_queue() {
+        let mut batch = SpriteBatch::new();
+        batch.push(
+            1,
+            UvRect::FULL,
+            vec2(0.0, 0.0),
+            vec2(1.0, 1.0),
+            0.0,
+            vec3a(1.0, 1.0, 1.0),
+        );
+        batch.clear();
+        assert!(batch.is_empty());
+    }
+}
@@ -1,23 +1,70 @@
-use std::mem::size_of;
+use std::{
+    mem::{offset_of, size_of},
+    rc::Rc,
+};
 
-use glam::{Mat4, Vec2, Vec3, Vec3A};
+use glam::{vec2, Mat4, Vec2, Vec3, Vec3A};
 
 use crate::opengl::{
-    active_texture, buffer_data, draw_arrays, enable_vertex_attrib_array, gen_buffers, gen_vertex_arrays,
-    vertex_attrib_pointer, VertexAttributeArray,
+    active_texture, buffer_data, buffer_storage, buffer_sub_data, draw_arrays, draw_arrays_instanced,
+    enable_vertex_attrib_array, gen_buffers, gen_vertex_arrays, vertex_attrib_divisor, vertex_attrib_pointer, Buffer,
+    VertexAttributeArray,
+};
+
+use super::{
+    atlas::UvRect,
+    material::Material,
+    sprite_batch::{SpriteBatch, SpriteInstance, SpriteVertex},
+    texture2d::Texture2D,
 };
 
-use super::{material::Material, texture2d::Texture2D};
+/// What a sprite is drawn with: a texture, or the part of an atlas page an image was packed into.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sprite {
+    pub texture: Rc<Texture2D>,
+    pub uv: UvRect,
+}
+
+impl Sprite {
+    /// A sprite showing all of `texture`.
+    pub fn whole(texture: Rc<Texture2D>) -> Sprite {
+        Sprite {
+            texture,
+            uv: UvRect::FULL,
+        }
+    }
+
+    /// Width and height in texels of the part of the texture shown.
+    pub fn size(&self) -> Vec2 {
+        let (width, height) = self.texture.size();
+        (self.uv.max - self.uv.min) * vec2(width as f32, height as f32)
+    }
+}
 
+/// Draws sprites right away one by one, queued into batches of vertices or instanced.
 pub struct SpriteRenderer {
-    material: Material,
+    material: Rc<Material>,
     quad_vao: VertexAttributeArray,
+    /// Only read through `quad_vao` and `instanced_vao`, kept to be deleted with them.
+    _quad_vbo: Buffer,
+    batch_material: Rc<Material>,
+    batch_vao: VertexAttributeArray,
+    batch_vbo: Buffer,
+    batch: SpriteBatch<Rc<Texture2D>>,
+    instanced_material: Rc<Material>,
+    instanced_vao: VertexAttributeArray,
+    instance_vbo: Buffer,
+    instance_capacity: usize,
 }
 
 impl SpriteRenderer {
-    pub fn new(material: Material) -> SpriteRenderer {
+    pub fn new(
+        material: Rc<Material>,
+        batch_material: Rc<Material>,
+        instanced_material: Rc<Material>,
+    ) -> SpriteRenderer {
         let [quad_vao] = gen_vertex_arrays::<1>();
-        let [vbo] = gen_buffers::<1>();
+        let [quad_vbo] = gen_buffers::<1>();
 
         #[rustfmt::skip]
         let vertices: [f32;12] = [
@@ -31,32 +78,299 @@ impl SpriteRenderer {
         ];
 
         quad_vao.bind();
-        vbo.bind(gl::ARRAY_BUFFER);
+        quad_vbo.bind(gl::ARRAY_BUFFER);
         buffer_data(gl::ARRAY_BUFFER, &vertices, gl::STATIC_DRAW);
         vertex_attrib_pointer(0, 2, gl::FLOAT, false, 2 * size_of::<f32>(), 0);
         enable_vertex_attrib_array(0);
 
-        SpriteRenderer { material, quad_vao }
+        let [batch_vao] = gen_vertex_arrays::<1>();
+        let [batch_vbo] = gen_buffers::<1>();
+        let stride = size_of::<SpriteVertex>();
+
+        batch_vao.bind();
+        batch_vbo.bind(gl::ARRAY_BUFFER);
+        vertex_attrib_pointer(0, 2, gl::FLOAT, false, stride, offset_of!(SpriteVertex, position));
+        enable_vertex_attrib_array(0);
+        vertex_attrib_pointer(1, 2, gl::FLOAT, false, stride, offset_of!(SpriteVertex, uv));
+        enable_vertex_attrib_array(1);
+        vertex_attrib_pointer(2, 3, gl::FLOAT, false, stride, offset_of!(SpriteVertex, color));
+        enable_vertex_attrib_array(2);
+
+        // the instanced path shares the quad and reads model and color once per instance
+        let [instanced_vao] = gen_vertex_arrays::<1>();
+        let [instance_vbo] = gen_buffers::<1>();
+        let stride = size_of::<SpriteInstance>();
+
+        instanced_vao.bind();
+        quad_vbo.bind(gl::ARRAY_BUFFER);
+        vertex_attrib_pointer(0, 2, gl::FLOAT, false, 2 * size_of::<f32>(), 0);
+        enable_vertex_attrib_array(0);
+
+        instance_vbo.bind(gl::ARRAY_BUFFER);
+        for column in 0..4 {
+            let index = 1 + column as u32;
+            let offset = offset_of!(SpriteInstance, model) + column * 4 * size_of::<f32>();
+            vertex_attrib_pointer(index, 4, gl::FLOAT, false, stride, offset);
+            enable_vertex_attrib_array(index);
+            vertex_attrib_divisor(index, 1);
+        }
+        vertex_attrib_pointer(5, 4, gl::FLOAT, false, stride, offset_of!(SpriteInstance, color));
+        enable_vertex_attrib_array(5);
+        vertex_attrib_divisor(5, 1);
+        vertex_attrib_pointer(6, 4, gl::FLOAT, false, stride, offset_of!(SpriteInstance, uv_rect));
+        enable_vertex_attrib_array(6);
+        vertex_attrib_divisor(6, 1);
+
+        SpriteRenderer {
+            material,
+            quad_vao,
+            _quad_vbo: quad_vbo,
+            batch_material,
+            batch_vao,
+            batch_vbo,
+            batch: SpriteBatch::new(),
+            instanced_material,
+            instanced_vao,
+            instance_vbo,
+            instance_capacity: 0,
+        }
     }
 
-    pub fn draw(&self, texture: &Texture2D, position: Vec2, size: Vec2, rotate: f32, color: Vec3A) {
+    /// Draws a sprite right away with a draw call of its own, under whatever is queued.
+    pub fn draw(&self, sprite: &Sprite, position: Vec2, size: Vec2, rotate: f32, color: Vec3A) {
         self.material.use_();
 
-        let translation = Mat4::from_translation(Vec3::from((position, 0.0)));
-        let rotation = Mat4::from_translation(Vec3::from((0.5 * size.x, 0.5 * size.y, 0.0)))
-            * Mat4::from_rotation_z(f32::to_radians(rotate))
-            * Mat4::from_translation(Vec3::from((-0.5 * size.x, -0.5 * size.y, 0.0)));
-        let scale = Mat4::from_scale(Vec3::from((size, 1.0)));
-
-        let model = translation * rotation * scale;
+        let model = sprite_model(position, size, rotate);
 
         self.material.set_matrix4f("model", model);
         self.material.set_vector3f("spriteColor", color);
+        self.material.set_vector4f("uvRect", sprite.uv.to_vec4());
 
         active_texture(gl::TEXTURE0);
-        texture.bind();
+        sprite.texture.bind();
 
         self.quad_vao.bind();
         draw_arrays(gl::TRIANGLES, 0, 6);
     }
+
+    /// Draws every instance with `texture` in a single instanced draw call, on top of the sprites
+    /// queued so far.
+    pub fn draw_instanced(&mut self, texture: &Texture2D, instances: &[SpriteInstance]) {
+        if instances.is_empty() {
+            return;
+        }
+        self.flush();
+
+        self.instance_vbo.bind(gl::ARRAY_BUFFER);
+        if instances.len() > self.instance_capacity {
+            self.instance_capacity = instances.len().next_power_of_two();
+            buffer_storage(
+                gl::ARRAY_BUFFER,
+                self.instance_capacity * size_of::<SpriteInstance>(),
+                gl::DYNAMIC_DRAW,
+            );
+        }
+        buffer_sub_data(gl::ARRAY_BUFFER, 0, instances);
+
+        self.instanced_material.use_();
+        active_texture(gl::TEXTURE0);
+        texture.bind();
+
+        self.instanced_vao.bind();
+        draw_arrays_instanced(gl::TRIANGLES, 0, 6, instances.len());
+    }
+
+    /// Queues a sprite to be drawn by the next `flush`.
+    pub fn queue(&mut self, sprite: &Sprite, position: Vec2, size: Vec2, rotate: f32, color: Vec3A) {
+        self.batch
+            .push(sprite.texture.clone(), sprite.uv, position, size, rotate, color);
+    }
+
+    /// Draws every queued sprite in queue order, one draw call per run of sprites sharing a texture,
+    /// and empties the queue.
+    pub fn flush(&mut self) {
+        if self.batch.is_empty() {
+            return;
+        }
+
+        let (vertices, batches) = self.batch.build();
+        self.batch.clear();
+
+        self.batch_material.use_();
+        active_texture(gl::TEXTURE0);
+
+        self.batch_vao.bind();
+        self.batch_vbo.bind(gl::ARRAY_BUFFER);
+        buffer_data(gl::ARRAY_BUFFER, &vertices, gl::DYNAMIC_DRAW);
+
+        for batch in batches {
+            batch.texture.bind();
+            draw_arrays(gl::TRIANGLES, batch.first as i32, batch.count);
+        }
+    }
+}
+
+/// Scales the unit quad to `size`, rotates it by `rotate` degrees around its centre and moves it to `position`.
+pub fn sprite_model(position: Vec2, size: Vec2, rotate: f32) -> Mat4 {
+    let translation = Mat4::from_translation(Vec3::from((position, 0.0)));
+    let rotation = Mat4::from_translation(Vec3::from((0.5 * size.x, 0.5 * size.y, 0.0)))
+        * Mat4::from_rotation_z(f32::to_radians(rotate))
+        * Mat4::from_translation(Vec3::from((-0.5 * size.x, -0.5 * size.y, 0.0)));
+    let scale = Mat4::from_scale(Vec3::from((size, 1.0)));
+
+    translation * rotation * scale
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use glam::{vec3a, Vec4};
+
+    use crate::{
+        game::loader::Loader,
+        opengl::recording::{GlCall, RecordingBackend},
+    };
+
+    use super::*;
+
+    fn renderer() -> SpriteRenderer {
+        let mut loader = Loader::new();
+        let mut material = |name: &str, shader: &str, defines: &[(String, String)]| {
+            let path = |extension: &str| Path::new("src/shaders").join(shader).with_extension(extension);
+            loader
+                .load_material(name, &path("vert"), &path("frag"), defines)
+                .unwrap()
+                .clone()
+        };
+        SpriteRenderer::new(
+            material("sprite", "sprite", &[]),
+            material("sprite_batch", "sprite_batch", &[]),
+            material(
+                "sprite_instanced",
+                "sprite_batch",
+                &[("INSTANCED".to_string(), String::new())],
+            ),
+        )
+    }
+
+    fn texture() -> Rc<Texture2D> {
+        Rc::new(Texture2D::new(
+            gl::RGBA,
+            gl::RGBA,
+            gl::REPEAT,
+            gl::REPEAT,
+            gl::LINEAR,
+            gl::LINEAR,
+        ))
+    }
+
+    fn instances(count: usize) -> Vec<SpriteInstance> {
+        let instance = SpriteInstance::new(Vec2::ZERO, vec2(10.0, 10.0), 0.0, Vec4::ONE, UvRect::FULL);
+        vec![instance; count]
+    }
+
+    /// The instance buffer uploads and the draws, leaving out the binds in between.
+    fn uploads_and_draws(calls: Vec<GlCall>) -> Vec<GlCall> {
+        calls
+            .into_iter()
+            .filter(|call| call.is_draw() || matches!(call, GlCall::BufferData { .. } | GlCall::BufferSubData { .. }))
+            .collect()
+    }
+
+    fn instanced(instance_count: usize) -> GlCall {
+        GlCall::DrawArraysInstanced {
+            mode: gl::TRIANGLES,
+            first: 0,
+            count: 6,
+            instance_count,
+        }
+    }
+
+    #[test]
+    fn draws_all_instances_with_one_call_and_grows_the_buffer_as_needed() {
+        let recorder = RecordingBackend::install();
+        let mut renderer = renderer();
+        let texture = texture();
+        let stride = size_of::<SpriteInstance>();
+        recorder.clear();
+
+        for count in [3, 2, 5] {
+            renderer.draw_instanced(&texture, &instances(count));
+        }
+        renderer.draw_instanced(&texture, &[]);
+
+        assert_eq!(
+            uploads_and_draws(recorder.calls()),
+            vec![
+                GlCall::BufferData {
+                    target: gl::ARRAY_BUFFER,
+                    size: 4 * stride,
+                    usage: gl::DYNAMIC_DRAW,
+                },
+                GlCall::BufferSubData {
+                    target: gl::ARRAY_BUFFER,
+                    offset: 0,
+                    size: 3 * stride,
+                },
+                instanced(3),
+                GlCall::BufferSubData {
+                    target: gl::ARRAY_BUFFER,
+                    offset: 0,
+                    size: 2 * stride,
+                },
+                instanced(2),
+                GlCall::BufferData {
+                    target: gl::ARRAY_BUFFER,
+                    size: 8 * stride,
+                    usage: gl::DYNAMIC_DRAW,
+                },
+                GlCall::BufferSubData {
+                    target: gl::ARRAY_BUFFER,
+                    offset: 0,
+                    size: 5 * stride,
+                },
+                instanced(5),
+            ]
+        );
+    }
+
+    #[test]
+    fn draws_a_sprite_right_away_under_the_queued_ones() {
+        let recorder = RecordingBackend::install();
+        let mut renderer = renderer();
+        let sprite = Sprite::whole(texture());
+        recorder.clear();
+
+        renderer.queue(&sprite, Vec2::ZERO, vec2(10.0, 10.0), 0.0, vec3a(1.0, 1.0, 1.0));
+        renderer.draw(&sprite, Vec2::ZERO, vec2(20.0, 20.0), 0.0, vec3a(1.0, 1.0, 1.0));
+        assert_eq!(recorder.draw_calls().len(), 1);
+        renderer.flush();
+
+        assert_eq!(recorder.draw_calls().len(), 2);
+    }
+
+    #[test]
+    fn queued_sprites_are_drawn_before_the_instances() {
+        let recorder = RecordingBackend::install();
+        let mut renderer = renderer();
+        let sprite = Sprite::whole(texture());
+        recorder.clear();
+
+        renderer.queue(&sprite, Vec2::ZERO, vec2(10.0, 10.0), 0.0, vec3a(1.0, 1.0, 1.0));
+        renderer.draw_instanced(&sprite.texture, &instances(2));
+        renderer.flush();
+
+        assert_eq!(
+            recorder.draw_calls(),
+            vec![
+                GlCall::DrawArrays {
+                    mode: gl::TRIANGLES,
+                    first: 0,
+                    count: 6,
+                },
+                instanced(2),
+            ]
+        );
+    }
 }
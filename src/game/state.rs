@@ -0,0 +1,179 @@
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GameState {
+    Active,
+    Menu,
+    Win,
+    GameOver,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Event {
+    NextLevel,
+    PreviousLevel,
+    Start,
+    LevelCompleted,
+    OutOfLives,
+    Restart,
+    OpenMenu,
+}
+
+/// Tracks which screen the game is on and which level is selected.
+/// Events that don't apply to the current state are ignored.
+#[derive(Debug)]
+pub struct StateMachine {
+    state: GameState,
+    level: usize,
+    level_count: usize,
+}
+
+impl StateMachine {
+    pub fn new(level_count: usize) -> StateMachine {
+        assert!(level_count > 0, "At least one level is needed");
+
+        StateMachine {
+            state: GameState::Menu,
+            level: 0,
+            level_count,
+        }
+    }
+
+    pub fn state(&self) -> GameState {
+        self.state
+    }
+
+    pub fn level(&self) -> usize {
+        self.level
+    }
+
+    /// Applies the event and returns true when the selected level has to be (re)started.
+    pub fn handle(&mut self, event: Event) -> bool {
+        match (self.state, event) {
+            (GameState::Menu, Event::NextLevel) => {
+                self.level = (self.level + 1) % self.level_count;
+                false
+            }
+            (GameState::Menu, Event::PreviousLevel) => {
+                self.level = (self.level + self.level_count - 1) % self.level_count;
+                false
+            }
+            (GameState::Menu, Event::Start) | (GameState::Win | GameState::GameOver, Event::Restart) => {
+                self.state = GameState::Active;
+                true
+            }
+            (GameState::Active, Event::LevelCompleted) => {
+                self.state = GameState::Win;
+                false
+            }
+            (GameState::Active, Event::OutOfLives) => {
+                self.state = GameState::GameOver;
+                false
+            }
+            (GameState::Win | GameState::GameOver, Event::OpenMenu) => {
+                self.state = GameState::Menu;
+                false
+            }
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn starts_in_the_menu_on_the_first_level() {
+        let machine = StateMachine::new(3);
+        assert_eq!(machine.state(), GameState::Menu);
+        assert_eq!(machine.level(), 0);
+    }
+
+    #[test]
+    fn menu_cycles_through_the_levels() {
+        let mut machine = StateMachine::new(3);
+
+        assert!(!machine.handle(Event::PreviousLevel));
+        assert_eq!(machine.level(), 2);
+        machine.handle(Event::NextLevel);
+        machine.handle(Event::NextLevel);
+        assert_eq!(machine.level(), 1);
+        assert_eq!(machine.state(), GameState::Menu);
+    }
+
+    #[test]
+    fn starting_from_the_menu_starts_the_selected_level() {
+        let mut machine = StateMachine::new(3);
+        machine.handle(Event::NextLevel);
+
+        assert!(machine.handle(Event::Start));
+        assert_eq!(machine.state(), GameState::Active);
+        assert_eq!(machine.level(), 1);
+    }
+
+    #[test]
+    fn completing_a_level_shows_the_win_screen() {
+        let mut machine = StateMachine::new(1);
+        machine.handle(Event::Start);
+
+        assert!(!machine.handle(Event::LevelCompleted));
+        assert_eq!(machine.state(), GameState::Win);
+    }
+
+    #[test]
+    fn win_screen_restarts_or_returns_to_the_menu() {
+        let mut machine = StateMachine::new(2);
+        machine.handle(Event::NextLevel);
+        machine.handle(Event::Start);
+        machine.handle(Event::LevelCompleted);
+
+        assert!(machine.handle(Event::Restart));
+        assert_eq!(machine.state(), GameState::Active);
+        assert_eq!(machine.level(), 1);
+
+        machine.handle(Event::LevelCompleted);
+        assert!(!machine.handle(Event::OpenMenu));
+        assert_eq!(machine.state(), GameState::Menu);
+        assert_eq!(machine.level(), 1);
+    }
+
+    #[test]
+    fn running_out_of_lives_ends_the_game() {
+        let mut machine = StateMachine::new(1);
+        machine.handle(Event::Start);
+
+        assert!(!machine.handle(Event::OutOfLives));
+        assert_eq!(machine.state(), GameState::GameOver);
+        assert!(!machine.handle(Event::LevelCompleted));
+        assert_eq!(machine.state(), GameState::GameOver);
+    }
+
+    #[test]
+    fn game_over_screen_restarts_or_returns_to_the_menu() {
+        let mut machine = StateMachine::new(1);
+        machine.handle(Event::Start);
+        machine.handle(Event::OutOfLives);
+
+        assert!(machine.handle(Event::Restart));
+        assert_eq!(machine.state(), GameState::Active);
+
+        machine.handle(Event::OutOfLives);
+        machine.handle(Event::OpenMenu);
+        assert_eq!(machine.state(), GameState::Menu);
+    }
+
+    #[test]
+    fn events_for_other_states_are_ignored() {
+        let mut machine = StateMachine::new(2);
+        assert!(!machine.handle(Event::Restart));
+        assert!(!machine.handle(Event::LevelCompleted));
+        assert!(!machine.handle(Event::OutOfLives));
+        assert_eq!(machine.state(), GameState::Menu);
+
+        machine.handle(Event::Start);
+        assert!(!machine.handle(Event::Start));
+        assert!(!machine.handle(Event::NextLevel));
+        assert!(!machine.handle(Event::OpenMenu));
+        assert_eq!(machine.state(), GameState::Active);
+        assert_eq!(machine.level(), 0);
+    }
+}
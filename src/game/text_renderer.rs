@@ -0,0 +1,78 @@
+use std::{mem::size_of, rc::Rc};
+
+use glam::{Vec2, Vec3A};
+
+use crate::opengl::{
+    active_texture, buffer_data, draw_arrays, enable_vertex_attrib_array, gen_buffers, gen_vertex_arrays,
+    vertex_attrib_pointer, Buffer, VertexAttributeArray,
+};
+
+use super::{bitmap_font::BitmapFont, material::Material, texture2d::Texture2D};
+
+pub struct TextRenderer {
+    material: Rc<Material>,
+    font: BitmapFont,
+    texture: Rc<Texture2D>,
+    vao: VertexAttributeArray,
+    vbo: Buffer,
+}
+
+impl TextRenderer {
+    pub fn new(material: Rc<Material>, font: BitmapFont, texture: Rc<Texture2D>) -> TextRenderer {
+        let [vao] = gen_vertex_arrays::<1>();
+        let [vbo] = gen_buffers::<1>();
+
+        vao.bind();
+        vbo.bind(gl::ARRAY_BUFFER);
+        vertex_attrib_pointer(0, 4, gl::FLOAT, false, 4 * size_of::<f32>(), 0);
+        enable_vertex_attrib_array(0);
+
+        TextRenderer {
+            material,
+            font,
+            texture,
+            vao,
+            vbo,
+        }
+    }
+
+    /// Draws `text` with its top left corner at `position`, all glyphs in a single draw call.
+    pub fn draw(&self, text: &str, position: Vec2, scale: f32, color: Vec3A) {
+        let quads = self.font.layout(text, position, scale);
+        if quads.is_empty() {
+            return;
+        }
+
+        let mut vertices: Vec<f32> = Vec::with_capacity(quads.len() * 6 * 4);
+        for quad in &quads {
+            let (min, max) = (quad.position, quad.position + quad.size);
+            let (uv_min, uv_max) = (quad.uv_min, quad.uv_max);
+
+            #[rustfmt::skip]
+            vertices.extend_from_slice(&[
+                min.x, max.y, uv_min.x, uv_max.y,
+                max.x, min.y, uv_max.x, uv_min.y,
+                min.x, min.y, uv_min.x, uv_min.y,
+
+                min.x, max.y, uv_min.x, uv_max.y,
+                max.x, max.y, uv_max.x, uv_max.y,
+                max.x, min.y, uv_max.x, uv_min.y,
+            ]);
+        }
+
+        self.material.use_();
+        self.material.set_vector3f("textColor", color);
+
+        active_texture(gl::TEXTURE0);
+        self.texture.bind();
+
+        self.vao.bind();
+        self.vbo.bind(gl::ARRAY_BUFFER);
+        buffer_data(gl::ARRAY_BUFFER, &vertices, gl::DYNAMIC_DRAW);
+        draw_arrays(gl::TRIANGLES, 0, quads.len() * 6);
+    }
+
+    pub fn measure(&self, text: &str, scale: f32) -> Vec2 {
+        self.font.measure(text, scale)
+    }
+}
@@ -1,7 +1,11 @@
+use std::cell::Cell;
+
 use crate::opengl::{gen_textures, tex_image_2d, tex_parameter_i, Texture};
 use gl::types::GLenum;
 
-#[derive(Clone, Debug)]
+/// Owns its GPU texture, so it is freed once the last handle to it is dropped.
+/// Share it with `Rc` instead of copying the handle.
+#[derive(Debug, PartialEq, Eq)]
 pub struct Texture2D {
     texture: Texture,
     internal_format: GLenum,
@@ -10,6 +14,8 @@ pub struct Texture2D {
     wrap_t: GLenum,
     filter_min: GLenum,
     filter_mag: GLenum,
+    /// Width and height of the last image uploaded by `generate`.
+    size: Cell<(i32, i32)>,
 }
 
 impl Texture2D {
@@ -30,6 +36,7 @@ impl Texture2D {
             wrap_t,
             filter_min,
             filter_mag,
+            size: Cell::new((0, 0)),
         }
     }
 
@@ -48,6 +55,8 @@ impl Texture2D {
             &data,
         );
 
+        self.size.set((width, height));
+
         // set texture wrap and filter modes
         tex_parameter_i(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, self.wrap_s);
         tex_parameter_i(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, self.wrap_t);
@@ -58,7 +67,22 @@ impl Texture2D {
         Texture::unbind(gl::TEXTURE_2D);
     }
 
+    /// The layout of the images `generate` expects, `gl::RGB` or `gl::RGBA`.
+    pub fn image_format(&self) -> GLenum {
+        self.image_format
+    }
+
+    /// Width and height in texels, zero until an image is uploaded.
+    pub fn size(&self) -> (i32, i32) {
+        self.size.get()
+    }
+
     pub fn bind(&self) {
         self.texture.bind(gl::TEXTURE_2D);
     }
+
+    /// The underlying texture object, e.g. to attach it to a framebuffer.
+    pub fn texture(&self) -> &Texture {
+        &self.texture
+    }
 }
@@ -3,15 +3,25 @@
 extern crate gl;
 extern crate glfw;
 
+mod audio;
+mod error;
 mod game;
 mod opengl;
 mod util;
 
-use std::{cell::RefCell, rc::Rc};
+use std::{cell::RefCell, fmt::Display, process, rc::Rc};
 
-use game::{loader::Loader, Game};
+use audio::{null::NullBackend, system::SystemBackend};
+use game::{loader::Loader, Game, STEP};
 use glfw::{Action, Context, Key, Window};
 use opengl::{clear, clear_color};
+use util::FixedTimestep;
+
+/// Seconds between checks for edited asset files.
+const HOT_RELOAD_INTERVAL: f32 = 0.5;
+/// Longest frame simulated in full. The game slows down through longer stalls rather than
+/// jumping ahead.
+const MAX_FRAME_DELTA: f32 = 0.25;
 
 fn main() {
     let mut glfw = glfw::init(glfw::fail_on_errors).unwrap();
@@ -34,12 +44,29 @@ fn main() {
     // Load OpenGL function pointers
     gl::load_with(|symbol| window.get_proc_address(symbol) as *const _);
 
-    window.set_framebuffer_size_callback(|_window, width, height| {
-        unsafe { gl::Viewport(0, 0, width, height) };
-    });
+    // sounds are loaded by the backend, so it has to be in place first
+    match SystemBackend::open() {
+        Some(backend) => {
+            audio::set_backend(Box::new(backend));
+        }
+        None => eprintln!("No audio device found, playing without sound"),
+    }
 
     let mut loader = Loader::new();
-    let game: Rc<RefCell<Game>> = Rc::new(RefCell::new(Game::new(800, 600, &mut loader)));
+    let game = match Game::new(800, 600, &mut loader) {
+        Ok(game) => Rc::new(RefCell::new(game)),
+        Err(error) => {
+            eprintln!("{}", error);
+            process::exit(1);
+        }
+    };
+
+    {
+        let (width, height) = window.get_framebuffer_size();
+        game.borrow_mut().resize(width, height);
+        let game = Rc::clone(&game);
+        window.set_framebuffer_size_callback(move |_window, width, height| game.borrow_mut().resize(width, height));
+    }
 
     {
         let game = Rc::clone(&game);
@@ -48,6 +75,8 @@ fn main() {
 
     let mut delta_time: f32;
     let mut last_frame: f32 = 0.0;
+    let mut last_reload_check: f32 = 0.0;
+    let mut timestep = FixedTimestep::new(STEP, MAX_FRAME_DELTA);
 
     while !window.should_close() {
         let current_frame = glfw.get_time() as f32;
@@ -55,16 +84,41 @@ fn main() {
         last_frame = current_frame;
         glfw.poll_events();
 
-        game.borrow_mut().process_input(delta_time);
+        if current_frame - last_reload_check > HOT_RELOAD_INTERVAL {
+            last_reload_check = current_frame;
+            report_reloads("material", loader.reload_changed_materials());
+            report_reloads("texture", loader.reload_changed_textures());
+            let levels = game.borrow_mut().reload_changed_levels(&loader);
+            report_reloads(
+                "level",
+                levels
+                    .into_iter()
+                    .map(|(path, result)| (path.display().to_string(), result)),
+            );
+        }
 
-        game.borrow_mut().update(delta_time);
+        for _ in 0..timestep.advance(delta_time) {
+            game.borrow_mut().step(&loader);
+        }
 
         clear_color(0.0, 0.0, 0.0, 1.0);
         clear(gl::COLOR_BUFFER_BIT);
-        game.borrow().render(&mut loader);
+        game.borrow_mut().render(&mut loader, timestep.alpha());
 
         window.swap_buffers();
     }
+
+    // thread locals of the main thread are not always dropped, so stop the sounds still playing here
+    audio::set_backend(Box::new(NullBackend::default()));
+}
+
+fn report_reloads<N: Display>(kind: &str, reloads: impl IntoIterator<Item = (N, error::Result<()>)>) {
+    for (name, result) in reloads {
+        match result {
+            Ok(()) => println!("Reloaded {} {}", kind, name),
+            Err(error) => eprintln!("Failed to reload {} {}: {}", kind, name, error),
+        }
+    }
 }
 
 fn handle_key(game: &RefCell<Game>, window: &mut Window, key: Key, action: Action) {
@@ -1,292 +1,297 @@
-use std::{
-    ffi::{c_void, CString},
-    mem::size_of,
-    ptr::null,
-    str,
-};
-
-use gl::types::{GLboolean, GLenum, GLint};
+use std::{mem::size_of_val, ptr::null};
+
+use gl::types::GLenum;
+
+use self::backend::with_backend;
+
+mod backend;
+#[cfg(test)]
+mod glsl;
+mod native;
+#[cfg(test)]
+pub mod recording;
+#[cfg(test)]
+pub mod software;
+
+pub use self::backend::GlBackend;
+
+/// The GLSL spelling of every uniform and attribute type the game uses.
+const GLSL_TYPES: [(&str, GLenum); 14] = [
+    ("float", gl::FLOAT),
+    ("vec2", gl::FLOAT_VEC2),
+    ("vec3", gl::FLOAT_VEC3),
+    ("vec4", gl::FLOAT_VEC4),
+    ("int", gl::INT),
+    ("ivec2", gl::INT_VEC2),
+    ("ivec3", gl::INT_VEC3),
+    ("ivec4", gl::INT_VEC4),
+    ("bool", gl::BOOL),
+    ("mat2", gl::FLOAT_MAT2),
+    ("mat3", gl::FLOAT_MAT3),
+    ("mat4", gl::FLOAT_MAT4),
+    ("sampler2D", gl::SAMPLER_2D),
+    ("samplerCube", gl::SAMPLER_CUBE),
+];
+
+/// The GLSL spelling of a uniform or attribute type, e.g. `vec3` for `FLOAT_VEC3`.
+pub fn type_name(type_: GLenum) -> Option<&'static str> {
+    GLSL_TYPES
+        .iter()
+        .find(|(_, gl_type)| *gl_type == type_)
+        .map(|(name, _)| *name)
+}
 
-#[derive(Clone, Copy)]
+/// Owns a shader object, deleted on drop.
+#[derive(Debug)]
 pub struct Shader(u32);
 
 pub fn create_shader(shader_type: GLenum) -> Shader {
-    unsafe {
-        let shader = gl::CreateShader(shader_type);
-        Shader(shader)
-    }
+    Shader(with_backend(|backend| backend.create_shader(shader_type)))
 }
 
 impl Shader {
-    pub fn source(self, source: &str) {
-        unsafe {
-            let c_source = CString::new(source).unwrap();
-
-            gl::ShaderSource(self.0, 1, &c_source.as_ptr(), null());
-        }
+    pub fn source(&self, source: &str) {
+        with_backend(|backend| backend.shader_source(self.0, source));
     }
 
-    pub fn compile(self) {
-        unsafe { gl::CompileShader(self.0) }
+    pub fn compile(&self) {
+        with_backend(|backend| backend.compile_shader(self.0));
     }
 
     pub fn get(&self, pname: GLenum) -> bool {
-        unsafe {
-            let mut success = gl::FALSE as GLint;
-            gl::GetShaderiv(self.0, pname, &mut success);
-            success == 1
-        }
+        with_backend(|backend| backend.get_shader_iv(self.0, pname)) == 1
     }
 
-    pub fn get_info_log(self) -> String {
-        const CAPACITY: usize = 512;
-        let mut info_log: Vec<u8> = Vec::with_capacity(CAPACITY);
-        let mut info_log_size: i32 = 0;
-
-        unsafe {
-            gl::GetShaderInfoLog(
-                self.0,
-                CAPACITY as i32,
-                &mut info_log_size,
-                info_log.as_mut_ptr().cast(),
-            );
-
-            info_log.set_len(info_log_size.try_into().unwrap());
-        }
-
-        String::from_utf8(info_log).unwrap()
+    pub fn get_info_log(&self) -> String {
+        with_backend(|backend| backend.get_shader_info_log(self.0))
     }
+}
 
-    pub fn delete(self) {
-        unsafe {
-            gl::DeleteShader(self.0);
-        }
+impl Drop for Shader {
+    fn drop(&mut self) {
+        with_backend(|backend| backend.delete_shader(self.0));
     }
 }
 
-#[derive(Debug, Copy, Clone)]
+/// Owns a program object, deleted on drop.
+#[derive(Debug)]
 pub struct Program(u32);
 
 pub fn create_program() -> Program {
-    unsafe { Program(gl::CreateProgram()) }
+    Program(with_backend(|backend| backend.create_program()))
 }
 
 impl Program {
-    pub fn attach_shader(self, shader: Shader) {
-        unsafe {
-            gl::AttachShader(self.0, shader.0);
-        }
+    pub fn attach_shader(&self, shader: &Shader) {
+        with_backend(|backend| backend.attach_shader(self.0, shader.0));
+    }
+
+    pub fn link(&self) {
+        with_backend(|backend| backend.link_program(self.0));
+    }
+
+    /// Names the program after what it draws, shown by GL debuggers. Backends without a driver
+    /// pick the port of the program to run by this name.
+    pub fn set_label(&self, label: &str) {
+        with_backend(|backend| backend.object_label(gl::PROGRAM, self.0, label));
     }
 
-    pub fn link(self) {
-        unsafe {
-            gl::LinkProgram(self.0);
-        }
+    pub fn get(&self, pname: GLenum) -> bool {
+        with_backend(|backend| backend.get_program_iv(self.0, pname)) == 1
     }
 
-    pub fn get(self, pname: GLenum) -> bool {
-        unsafe {
-            let mut success = gl::FALSE as GLint;
-            gl::GetProgramiv(self.0, pname, &mut success);
-            success == 1
-        }
+    pub fn get_info_log(&self) -> String {
+        with_backend(|backend| backend.get_program_info_log(self.0))
     }
 
-    pub fn get_info_log(self) -> String {
-        const CAPACITY: usize = 512;
-        let mut info_log: Vec<u8> = Vec::with_capacity(CAPACITY);
-        let mut info_log_size: i32 = 0;
+    pub fn use_(&self) {
+        with_backend(|backend| backend.use_program(self.0));
+    }
 
-        unsafe {
-            gl::GetProgramInfoLog(
-                self.0,
-                CAPACITY as i32,
-                &mut info_log_size,
-                info_log.as_mut_ptr().cast(),
-            );
-            info_log.set_len(info_log_size.try_into().unwrap());
-        }
+    #[cfg(test)]
+    pub fn get_uniform_location(&self, name: &str) -> Uniform {
+        Uniform(with_backend(|backend| backend.get_uniform_location(self.0, name)))
+    }
 
-        String::from_utf8(info_log).unwrap()
+    /// The uniforms the linked program uses, with their locations.
+    pub fn active_uniforms(&self) -> Vec<ActiveVariable> {
+        with_backend(|backend| {
+            let count = backend.get_program_iv(self.0, gl::ACTIVE_UNIFORMS);
+            (0..count.max(0) as u32)
+                .map(|index| {
+                    let (name, size, type_) = backend.get_active_uniform(self.0, index);
+                    let location = backend.get_uniform_location(self.0, &name);
+                    ActiveVariable {
+                        name,
+                        size,
+                        type_,
+                        location,
+                    }
+                })
+                .collect()
+        })
     }
+}
 
-    pub fn use_(self) {
-        unsafe {
-            gl::UseProgram(self.0);
-        }
+/// An active uniform of a linked program. Arrays are named after their first
+/// element, `lights[0]`, and `size` is their length.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ActiveVariable {
+    pub name: String,
+    pub size: i32,
+    pub type_: GLenum,
+    pub location: i32,
+}
+
+impl ActiveVariable {
+    /// The uniform to set, for a variable from `Program::active_uniforms`.
+    pub fn uniform(&self) -> Uniform {
+        Uniform(self.location)
     }
+}
 
-    pub fn get_uniform_location(self, name: &str) -> Uniform {
-        let name = CString::new(name).unwrap();
-        unsafe { Uniform(gl::GetUniformLocation(self.0, name.as_ptr())) }
+impl Drop for Program {
+    fn drop(&mut self) {
+        with_backend(|backend| backend.delete_program(self.0));
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Uniform(i32);
 
 impl Uniform {
     pub fn set_4f(self, f1: f32, f2: f32, f3: f32, f4: f32) {
-        unsafe {
-            gl::Uniform4f(self.0, f1, f2, f3, f4);
-        }
+        with_backend(|backend| backend.uniform_4f(self.0, f1, f2, f3, f4));
     }
 
     pub fn set_1f(self, f: f32) {
-        unsafe {
-            gl::Uniform1f(self.0, f);
-        }
-    }
-
-    pub fn set_2f(self, f1: f32, f2: f32) {
-        unsafe {
-            gl::Uniform2f(self.0, f1, f2);
-        }
+        with_backend(|backend| backend.uniform_1f(self.0, f));
     }
 
     pub fn set_3f(self, f1: f32, f2: f32, f3: f32) {
-        unsafe {
-            gl::Uniform3f(self.0, f1, f2, f3);
-        }
+        with_backend(|backend| backend.uniform_3f(self.0, f1, f2, f3));
     }
 
     pub fn set_1i(self, i: i32) {
-        unsafe {
-            gl::Uniform1i(self.0, i);
-        }
+        with_backend(|backend| backend.uniform_1i(self.0, i));
     }
 
     pub fn set_matrix4fv(self, count: i32, transpose: bool, matrix: &[f32]) {
-        unsafe {
-            gl::UniformMatrix4fv(self.0, count, bool_to_glbool(transpose), matrix.as_ptr());
-        }
+        with_backend(|backend| backend.uniform_matrix4fv(self.0, count, transpose, matrix));
     }
 }
 
-#[derive(Clone, Copy)]
+/// Owns a buffer object, deleted on drop.
+#[derive(Debug)]
 pub struct Buffer(u32);
 
 pub fn gen_buffers<const N: usize>() -> [Buffer; N] {
-    let mut buffer_ids: [u32; N] = [0; N];
-    unsafe {
-        gl::GenBuffers(N as i32, buffer_ids.as_mut_ptr());
-    }
-
-    buffer_ids.map(|id| Buffer(id))
+    [(); N].map(|_| Buffer(with_backend(|backend| backend.gen_buffer())))
 }
 
 impl Buffer {
-    pub fn bind(self, target: GLenum) {
-        unsafe { gl::BindBuffer(target, self.0) }
+    pub fn bind(&self, target: GLenum) {
+        with_backend(|backend| backend.bind_buffer(target, self.0));
     }
+}
 
-    pub fn unbind(target: GLenum) {
-        unsafe { gl::BindBuffer(target, 0) }
+impl Drop for Buffer {
+    fn drop(&mut self) {
+        with_backend(|backend| backend.delete_buffer(self.0));
     }
 }
 
-#[derive(Clone, Copy)]
+/// Owns a vertex array object, deleted on drop.
+#[derive(Debug)]
 pub struct VertexAttributeArray(u32);
 
 impl VertexAttributeArray {
-    pub fn bind(self) {
-        unsafe { gl::BindVertexArray(self.0) }
+    pub fn bind(&self) {
+        with_backend(|backend| backend.bind_vertex_array(self.0));
     }
 }
 
-pub fn gen_vertex_arrays<const N: usize>() -> [VertexAttributeArray; N] {
-    let mut vao_ids: [u32; N] = [0; N];
-    unsafe {
-        gl::GenVertexArrays(N as i32, vao_ids.as_mut_ptr());
+impl Drop for VertexAttributeArray {
+    fn drop(&mut self) {
+        with_backend(|backend| backend.delete_vertex_array(self.0));
     }
-    vao_ids.map(|id| VertexAttributeArray(id))
+}
+
+pub fn gen_vertex_arrays<const N: usize>() -> [VertexAttributeArray; N] {
+    [(); N].map(|_| VertexAttributeArray(with_backend(|backend| backend.gen_vertex_array())))
 }
 
 pub fn buffer_data<T>(target: GLenum, data: &[T], usage: GLenum) {
-    unsafe {
-        gl::BufferData(
-            target,
-            (data.len() * size_of::<T>()) as isize,
-            data.as_ptr().cast(),
-            usage,
-        );
-    }
+    with_backend(|backend| unsafe { backend.buffer_data(target, size_of_val(data), data.as_ptr().cast(), usage) });
+}
+
+/// Allocates `size` bytes of uninitialized storage, to be filled with `buffer_sub_data`.
+pub fn buffer_storage(target: GLenum, size: usize, usage: GLenum) {
+    with_backend(|backend| unsafe { backend.buffer_data(target, size, null(), usage) });
+}
+
+pub fn buffer_sub_data<T>(target: GLenum, offset: usize, data: &[T]) {
+    with_backend(|backend| unsafe { backend.buffer_sub_data(target, offset, size_of_val(data), data.as_ptr().cast()) });
 }
 
 pub fn vertex_attrib_pointer(index: u32, size: i32, type_: GLenum, normalized: bool, stride: usize, pointer: usize) {
-    let gl_normalized = if normalized { gl::TRUE } else { gl::FALSE };
-    unsafe {
-        gl::VertexAttribPointer(
-            index,
-            size,
-            type_,
-            gl_normalized,
-            stride as i32,
-            pointer as *const c_void,
-        );
-    }
+    with_backend(|backend| backend.vertex_attrib_pointer(index, size, type_, normalized, stride, pointer));
 }
 
 pub fn enable_vertex_attrib_array(index: u32) {
-    unsafe {
-        gl::EnableVertexAttribArray(index);
-    }
+    with_backend(|backend| backend.enable_vertex_attrib_array(index));
 }
 
-pub fn clear_color(r: f32, g: f32, b: f32, a: f32) {
-    unsafe {
-        gl::ClearColor(r, g, b, a);
-    }
+pub fn vertex_attrib_divisor(index: u32, divisor: u32) {
+    with_backend(|backend| backend.vertex_attrib_divisor(index, divisor));
 }
 
-pub fn clear(mask: GLenum) {
-    unsafe {
-        gl::Clear(mask);
-    }
+pub fn clear_color(r: f32, g: f32, b: f32, a: f32) {
+    with_backend(|backend| backend.clear_color(r, g, b, a));
 }
 
-pub fn draw_arrays(mode: GLenum, first: i32, count: usize) {
-    unsafe { gl::DrawArrays(mode, first, count as i32) }
+pub fn clear(mask: GLenum) {
+    with_backend(|backend| backend.clear(mask));
 }
 
-pub fn draw_elements(mode: GLenum, count: usize, type_: GLenum, indices: usize) {
-    unsafe { gl::DrawElements(mode, count as i32, type_, indices as *const c_void) }
+pub fn viewport(x: i32, y: i32, width: i32, height: i32) {
+    with_backend(|backend| backend.viewport(x, y, width, height));
 }
 
-pub fn polygon_mode(face: GLenum, mode: GLenum) {
-    unsafe { gl::PolygonMode(face, mode) }
+pub fn draw_arrays(mode: GLenum, first: i32, count: usize) {
+    with_backend(|backend| backend.draw_arrays(mode, first, count));
 }
 
-fn bool_to_glbool(b: bool) -> GLboolean {
-    match b {
-        true => gl::TRUE,
-        false => gl::FALSE,
-    }
+pub fn draw_arrays_instanced(mode: GLenum, first: i32, count: usize, instance_count: usize) {
+    with_backend(|backend| backend.draw_arrays_instanced(mode, first, count, instance_count));
 }
 
 // Texture
-#[derive(Debug, Clone, Copy)]
+/// Owns a texture object, deleted on drop.
+#[derive(Debug, PartialEq, Eq)]
 pub struct Texture(u32);
 
 pub fn gen_textures<const N: usize>() -> [Texture; N] {
-    let mut texture_ids: [u32; N] = [0; N];
-    unsafe {
-        gl::GenTextures(N as i32, texture_ids.as_mut_ptr());
-    }
-    texture_ids.map(|id| Texture(id))
+    [(); N].map(|_| Texture(with_backend(|backend| backend.gen_texture())))
 }
 
 impl Texture {
-    pub fn bind(self, target: GLenum) {
-        unsafe {
-            gl::BindTexture(target, self.0);
-        }
+    pub fn bind(&self, target: GLenum) {
+        with_backend(|backend| backend.bind_texture(target, self.0));
     }
 
     pub fn unbind(target: GLenum) {
-        unsafe { gl::BindTexture(target, 0) }
+        with_backend(|backend| backend.bind_texture(target, 0));
     }
 }
 
+impl Drop for Texture {
+    fn drop(&mut self) {
+        with_backend(|backend| backend.delete_texture(self.0));
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 pub fn tex_image_2d(
     target: GLenum,
     level: GLenum,
@@ -298,41 +303,106 @@ pub fn tex_image_2d(
     type_: GLenum,
     data: &[u8],
 ) {
-    unsafe {
-        gl::TexImage2D(
+    debug_assert_eq!(border, 0, "The border of a texture must be 0");
+    with_backend(|backend| {
+        backend.tex_image_2d(
             target,
             level as i32,
-            internal_format as i32,
+            internal_format,
             width,
             height,
-            border as i32,
             format,
             type_,
-            data.as_ptr().cast(),
-        );
-    }
+            data,
+        )
+    });
 }
 
 pub fn tex_parameter_i(target: GLenum, pname: GLenum, param: GLenum) {
-    unsafe {
-        gl::TexParameteri(target, pname, param as i32);
-    }
+    with_backend(|backend| backend.tex_parameter_i(target, pname, param));
 }
 
 pub fn active_texture(texture: GLenum) {
-    unsafe {
-        gl::ActiveTexture(texture);
-    }
+    with_backend(|backend| backend.active_texture(texture));
 }
 
 pub fn blend_func(s_factor: GLenum, d_factor: GLenum) {
-    unsafe {
-        gl::BlendFunc(s_factor, d_factor);
-    }
+    with_backend(|backend| backend.blend_func(s_factor, d_factor));
 }
 
 pub fn enable(cap: GLenum) {
-    unsafe {
-        gl::Enable(cap);
+    with_backend(|backend| backend.enable(cap));
+}
+
+// Framebuffer
+/// Owns a framebuffer object, deleted on drop.
+#[derive(Debug)]
+pub struct Framebuffer(u32);
+
+pub fn gen_framebuffers<const N: usize>() -> [Framebuffer; N] {
+    [(); N].map(|_| Framebuffer(with_backend(|backend| backend.gen_framebuffer())))
+}
+
+impl Framebuffer {
+    pub fn bind(&self, target: GLenum) {
+        with_backend(|backend| backend.bind_framebuffer(target, self.0));
+    }
+
+    /// Binds the window's framebuffer back.
+    pub fn unbind(target: GLenum) {
+        with_backend(|backend| backend.bind_framebuffer(target, 0));
+    }
+}
+
+impl Drop for Framebuffer {
+    fn drop(&mut self) {
+        with_backend(|backend| backend.delete_framebuffer(self.0));
     }
 }
+
+/// Attaches level 0 of `texture` to the framebuffer bound to `target`.
+pub fn framebuffer_texture_2d(target: GLenum, attachment: GLenum, texture: &Texture) {
+    with_backend(|backend| backend.framebuffer_texture_2d(target, attachment, gl::TEXTURE_2D, texture.0, 0));
+}
+
+pub fn framebuffer_renderbuffer(target: GLenum, attachment: GLenum, renderbuffer: &Renderbuffer) {
+    with_backend(|backend| backend.framebuffer_renderbuffer(target, attachment, gl::RENDERBUFFER, renderbuffer.0));
+}
+
+pub fn check_framebuffer_status(target: GLenum) -> GLenum {
+    with_backend(|backend| backend.check_framebuffer_status(target))
+}
+
+/// Copies `source` of the read framebuffer to `destination` of the draw framebuffer, both given
+/// as (x0, y0, x1, y1).
+pub fn blit_framebuffer(source: [i32; 4], destination: [i32; 4], mask: GLenum, filter: GLenum) {
+    with_backend(|backend| backend.blit_framebuffer(source, destination, mask, filter));
+}
+
+// Renderbuffer
+/// Owns a renderbuffer object, deleted on drop.
+#[derive(Debug)]
+pub struct Renderbuffer(u32);
+
+pub fn gen_renderbuffers<const N: usize>() -> [Renderbuffer; N] {
+    [(); N].map(|_| Renderbuffer(with_backend(|backend| backend.gen_renderbuffer())))
+}
+
+impl Renderbuffer {
+    pub fn bind(&self) {
+        with_backend(|backend| backend.bind_renderbuffer(gl::RENDERBUFFER, self.0));
+    }
+}
+
+impl Drop for Renderbuffer {
+    fn drop(&mut self) {
+        with_backend(|backend| backend.delete_renderbuffer(self.0));
+    }
+}
+
+/// Allocates storage of `samples` samples per pixel for the bound renderbuffer.
+pub fn renderbuffer_storage_multisample(samples: i32, internal_format: GLenum, width: i32, height: i32) {
+    with_backend(|backend| {
+        backend.renderbuffer_storage_multisample(gl::RENDERBUFFER, samples, internal_format, width, height)
+    });
+}
@@ -0,0 +1,137 @@
+use std::{cell::RefCell, ffi::c_void};
+
+use gl::types::GLenum;
+
+use super::native::NativeBackend;
+
+/// The raw GL entry points the `opengl` wrappers are built on. Objects are plain GL names,
+/// so a backend that isn't backed by a driver only has to hand out unique ids.
+pub trait GlBackend {
+    fn create_shader(&mut self, shader_type: GLenum) -> u32;
+    fn shader_source(&mut self, shader: u32, source: &str);
+    fn compile_shader(&mut self, shader: u32);
+    fn get_shader_iv(&mut self, shader: u32, pname: GLenum) -> i32;
+    fn get_shader_info_log(&mut self, shader: u32) -> String;
+    fn delete_shader(&mut self, shader: u32);
+
+    fn create_program(&mut self) -> u32;
+    fn attach_shader(&mut self, program: u32, shader: u32);
+    fn link_program(&mut self, program: u32);
+    /// Names an object for debuggers and logs, `identifier` is its kind, e.g. `gl::PROGRAM`.
+    fn object_label(&mut self, identifier: GLenum, name: u32, label: &str);
+    fn get_program_iv(&mut self, program: u32, pname: GLenum) -> i32;
+    fn get_program_info_log(&mut self, program: u32) -> String;
+    fn use_program(&mut self, program: u32);
+    fn get_uniform_location(&mut self, program: u32, name: &str) -> i32;
+    /// Name, array size and type of the active uniform at `index`, below `ACTIVE_UNIFORMS`.
+    fn get_active_uniform(&mut self, program: u32, index: u32) -> (String, i32, GLenum);
+    fn delete_program(&mut self, program: u32);
+
+    fn uniform_1f(&mut self, location: i32, f: f32);
+    fn uniform_3f(&mut self, location: i32, f1: f32, f2: f32, f3: f32);
+    fn uniform_4f(&mut self, location: i32, f1: f32, f2: f32, f3: f32, f4: f32);
+    fn uniform_1i(&mut self, location: i32, i: i32);
+    fn uniform_matrix4fv(&mut self, location: i32, count: i32, transpose: bool, matrix: &[f32]);
+
+    fn gen_buffer(&mut self) -> u32;
+    fn bind_buffer(&mut self, target: GLenum, buffer: u32);
+    fn delete_buffer(&mut self, buffer: u32);
+    /// # Safety
+    /// `data` must be null or point to `size` readable bytes.
+    unsafe fn buffer_data(&mut self, target: GLenum, size: usize, data: *const c_void, usage: GLenum);
+    /// # Safety
+    /// `data` must point to `size` readable bytes.
+    unsafe fn buffer_sub_data(&mut self, target: GLenum, offset: usize, size: usize, data: *const c_void);
+
+    fn gen_vertex_array(&mut self) -> u32;
+    fn bind_vertex_array(&mut self, vao: u32);
+    fn delete_vertex_array(&mut self, vao: u32);
+    fn vertex_attrib_pointer(
+        &mut self,
+        index: u32,
+        size: i32,
+        type_: GLenum,
+        normalized: bool,
+        stride: usize,
+        offset: usize,
+    );
+    fn enable_vertex_attrib_array(&mut self, index: u32);
+    fn vertex_attrib_divisor(&mut self, index: u32, divisor: u32);
+
+    fn gen_texture(&mut self) -> u32;
+    fn bind_texture(&mut self, target: GLenum, texture: u32);
+    fn delete_texture(&mut self, texture: u32);
+    /// An empty `data` allocates the texture without filling it.
+    #[allow(clippy::too_many_arguments)]
+    fn tex_image_2d(
+        &mut self,
+        target: GLenum,
+        level: i32,
+        internal_format: GLenum,
+        width: i32,
+        height: i32,
+        format: GLenum,
+        type_: GLenum,
+        data: &[u8],
+    );
+    fn tex_parameter_i(&mut self, target: GLenum, pname: GLenum, param: GLenum);
+    fn active_texture(&mut self, texture: GLenum);
+
+    fn gen_framebuffer(&mut self) -> u32;
+    fn bind_framebuffer(&mut self, target: GLenum, framebuffer: u32);
+    fn delete_framebuffer(&mut self, framebuffer: u32);
+    fn framebuffer_texture_2d(
+        &mut self,
+        target: GLenum,
+        attachment: GLenum,
+        texture_target: GLenum,
+        texture: u32,
+        level: i32,
+    );
+    fn framebuffer_renderbuffer(
+        &mut self,
+        target: GLenum,
+        attachment: GLenum,
+        renderbuffer_target: GLenum,
+        renderbuffer: u32,
+    );
+    fn check_framebuffer_status(&mut self, target: GLenum) -> GLenum;
+    /// Copies the `source` rectangle of the read framebuffer into the `destination` rectangle of
+    /// the draw framebuffer, both given as (x0, y0, x1, y1). Resolves multisampled buffers.
+    fn blit_framebuffer(&mut self, source: [i32; 4], destination: [i32; 4], mask: GLenum, filter: GLenum);
+
+    fn gen_renderbuffer(&mut self) -> u32;
+    fn bind_renderbuffer(&mut self, target: GLenum, renderbuffer: u32);
+    fn delete_renderbuffer(&mut self, renderbuffer: u32);
+    fn renderbuffer_storage_multisample(
+        &mut self,
+        target: GLenum,
+        samples: i32,
+        internal_format: GLenum,
+        width: i32,
+        height: i32,
+    );
+
+    fn clear_color(&mut self, r: f32, g: f32, b: f32, a: f32);
+    fn clear(&mut self, mask: GLenum);
+    fn viewport(&mut self, x: i32, y: i32, width: i32, height: i32);
+    fn draw_arrays(&mut self, mode: GLenum, first: i32, count: usize);
+    fn draw_arrays_instanced(&mut self, mode: GLenum, first: i32, count: usize, instance_count: usize);
+    fn blend_func(&mut self, s_factor: GLenum, d_factor: GLenum);
+    fn enable(&mut self, cap: GLenum);
+}
+
+thread_local! {
+    static BACKEND: RefCell<Box<dyn GlBackend>> = RefCell::new(Box::new(NativeBackend));
+}
+
+/// Replaces the backend the `opengl` wrappers of this thread go through, returning the previous one.
+/// Every thread starts out with the native backend.
+#[cfg(test)]
+pub fn set_backend(backend: Box<dyn GlBackend>) -> Box<dyn GlBackend> {
+    BACKEND.with(|current| current.replace(backend))
+}
+
+pub(super) fn with_backend<R>(f: impl FnOnce(&mut dyn GlBackend) -> R) -> R {
+    BACKEND.with(|backend| f(backend.borrow_mut().as_mut()))
+}
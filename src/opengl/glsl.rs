@@ -0,0 +1,313 @@
+//! Reads the global `uniform` and `in` declarations out of GLSL source, so backends without a
+//! driver can answer the same reflection queries a linked program would.
+
+use std::collections::HashSet;
+
+use gl::types::GLenum;
+
+use super::GLSL_TYPES;
+
+/// Qualifiers that may come before `uniform` or `in` without changing what is declared.
+const IGNORED_QUALIFIERS: [&str; 7] = [
+    "flat",
+    "smooth",
+    "noperspective",
+    "centroid",
+    "lowp",
+    "mediump",
+    "highp",
+];
+
+fn gl_type(name: &str) -> Option<GLenum> {
+    GLSL_TYPES
+        .iter()
+        .find(|(glsl, _)| *glsl == name)
+        .map(|(_, gl_type)| *gl_type)
+}
+
+/// The number of attribute locations a vertex input of this type takes up.
+fn location_slots(type_: GLenum) -> i32 {
+    match type_ {
+        gl::FLOAT_MAT2 => 2,
+        gl::FLOAT_MAT3 => 3,
+        gl::FLOAT_MAT4 => 4,
+        _ => 1,
+    }
+}
+
+/// A variable declared at global scope. Arrays are named after their first element, like
+/// `glGetActiveUniform` does.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct Declaration {
+    pub name: String,
+    pub size: i32,
+    pub type_: GLenum,
+    pub location: Option<i32>,
+}
+
+/// The uniforms and vertex inputs of a program, in declaration order.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(super) struct Interface {
+    pub uniforms: Vec<Declaration>,
+    pub attributes: Vec<Declaration>,
+}
+
+impl Interface {
+    /// Collects the uniforms of every stage, once each, and the inputs of the vertex stage. Inputs
+    /// without a `layout(location = n)` get the next free location.
+    pub fn of<'a>(stages: impl IntoIterator<Item = (GLenum, &'a str)>) -> Interface {
+        let mut interface = Interface::default();
+
+        for (shader_type, source) in stages {
+            for uniform in declarations(source, "uniform") {
+                if !interface.uniforms.iter().any(|known| known.name == uniform.name) {
+                    interface.uniforms.push(uniform);
+                }
+            }
+            if shader_type == gl::VERTEX_SHADER {
+                interface.attributes.extend(declarations(source, "in"));
+            }
+        }
+
+        let mut next_location = interface
+            .attributes
+            .iter()
+            .filter_map(|attribute| Some(attribute.location? + location_slots(attribute.type_) * attribute.size))
+            .max()
+            .unwrap_or(0);
+        for attribute in &mut interface.attributes {
+            if attribute.location.is_none() {
+                attribute.location = Some(next_location);
+                next_location += location_slots(attribute.type_) * attribute.size;
+            }
+        }
+
+        interface
+    }
+
+    pub fn attribute(&self, name: &str) -> Option<&Declaration> {
+        self.attributes.iter().find(|attribute| attribute.name == name)
+    }
+}
+
+/// Every global declaration with the given storage `qualifier` whose type is known.
+fn declarations(source: &str, qualifier: &str) -> Vec<Declaration> {
+    let source: String = active_lines(source)
+        .into_iter()
+        .map(|line| line.split("//").next().unwrap_or(""))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let mut declarations = Vec::new();
+    let mut depth = 0;
+    let mut statement_start = 0;
+    for (i, c) in source.char_indices() {
+        match c {
+            '{' | '}' => {
+                depth += if c == '{' { 1 } else { -1 };
+                statement_start = i + 1;
+            }
+            ';' => {
+                if depth == 0 {
+                    declarations.extend(parse_declaration(&source[statement_start..i], qualifier));
+                }
+                statement_start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    declarations
+}
+
+/// The lines the preprocessor keeps, without its directives. Follows `#define`, `#undef`,
+/// `#ifdef`, `#ifndef`, `#else` and `#endif`, which is all the game's shaders use.
+fn active_lines(source: &str) -> Vec<&str> {
+    let mut defined = HashSet::new();
+    // whether the lines of each enclosing conditional are kept
+    let mut conditionals: Vec<bool> = Vec::new();
+    let mut lines = Vec::new();
+    for line in source.lines() {
+        let active = conditionals.iter().all(|&kept| kept);
+        let Some(directive) = line.trim_start().strip_prefix('#') else {
+            if active {
+                lines.push(line);
+            }
+            continue;
+        };
+
+        let mut words = directive.split_whitespace();
+        match (words.next(), words.next()) {
+            (Some("ifdef"), Some(name)) => conditionals.push(defined.contains(name)),
+            (Some("ifndef"), Some(name)) => conditionals.push(!defined.contains(name)),
+            (Some("else"), _) => {
+                if let Some(kept) = conditionals.last_mut() {
+                    *kept = !*kept;
+                }
+            }
+            (Some("endif"), _) => {
+                conditionals.pop();
+            }
+            (Some("define"), Some(name)) if active => {
+                defined.insert(name);
+            }
+            (Some("undef"), Some(name)) if active => {
+                defined.remove(name);
+            }
+            _ => {}
+        }
+    }
+    lines
+}
+
+fn parse_declaration(statement: &str, qualifier: &str) -> Vec<Declaration> {
+    let mut statement = statement.trim();
+    let mut location = None;
+    if let Some(layout) = statement.strip_prefix("layout") {
+        let Some((arguments, rest)) = layout
+            .trim_start()
+            .strip_prefix('(')
+            .and_then(|layout| layout.split_once(')'))
+        else {
+            return Vec::new();
+        };
+        location = arguments.split(',').find_map(|argument| {
+            let (key, value) = argument.split_once('=')?;
+            (key.trim() == "location").then(|| value.trim().parse().ok())?
+        });
+        statement = rest;
+    }
+
+    let mut tokens = statement
+        .split_whitespace()
+        .filter(|token| !IGNORED_QUALIFIERS.contains(token));
+    if tokens.next() != Some(qualifier) {
+        return Vec::new();
+    }
+    let Some(type_) = tokens.next().and_then(gl_type) else {
+        return Vec::new();
+    };
+
+    let declarators: String = tokens.collect();
+    declarators
+        .split(',')
+        .filter_map(|declarator| {
+            let (name, size) = match declarator.split_once('[') {
+                Some((name, size)) => (format!("{}[0]", name), size.strip_suffix(']')?.parse().ok()?),
+                None => (declarator.to_string(), 1),
+            };
+            (!name.is_empty()).then_some(Declaration {
+                name,
+                size,
+                type_,
+                location,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use crate::{game::preprocessor::ShaderSource, util::read_file};
+
+    use super::*;
+
+    fn declaration(name: &str, size: i32, type_: GLenum, location: Option<i32>) -> Declaration {
+        Declaration {
+            name: name.to_string(),
+            size,
+            type_,
+            location,
+        }
+    }
+
+    #[test]
+    fn reads_global_declarations_only() {
+        let source = "#version 330 core
+            layout (location = 3) in vec2 vertex; // a comment
+            uniform highp float time, weights[4];
+            uniform Light light;
+            out vec2 TexCoords;
+            void main() {
+                float uniform_ish = 1.0;
+            }";
+
+        assert_eq!(
+            declarations(source, "uniform"),
+            vec![
+                declaration("time", 1, gl::FLOAT, None),
+                declaration("weights[0]", 4, gl::FLOAT, None),
+            ]
+        );
+        assert_eq!(
+            declarations(source, "in"),
+            vec![declaration("vertex", 1, gl::FLOAT_VEC2, Some(3))]
+        );
+    }
+
+    #[test]
+    fn keeps_the_lines_of_the_defined_branches() {
+        let source = "#version 330 core
+            #define INSTANCED
+            #ifdef INSTANCED
+            in vec4 color;
+            #ifndef INSTANCED
+            in vec2 nested;
+            #endif
+            #else
+            in vec3 color;
+            #endif
+            #undef INSTANCED
+            #ifdef INSTANCED
+            in mat4 model;
+            #endif";
+
+        assert_eq!(
+            declarations(source, "in"),
+            vec![declaration("color", 1, gl::FLOAT_VEC4, None)]
+        );
+    }
+
+    #[test]
+    fn reflects_the_instanced_sprite_shaders() {
+        let instanced = [("INSTANCED".to_string(), String::new())];
+        let vertex = ShaderSource::load(Path::new("src/shaders/sprite_batch.vert"), &instanced).unwrap();
+        let fragment = read_file(Path::new("src/shaders/sprite_batch.frag")).unwrap();
+        let interface = Interface::of([
+            (gl::VERTEX_SHADER, vertex.text.as_str()),
+            (gl::FRAGMENT_SHADER, &fragment),
+        ]);
+
+        assert_eq!(
+            interface.uniforms,
+            vec![
+                declaration("projection", 1, gl::FLOAT_MAT4, None),
+                declaration("image", 1, gl::SAMPLER_2D, None),
+            ]
+        );
+        assert_eq!(
+            interface.attributes,
+            vec![
+                declaration("vertex", 1, gl::FLOAT_VEC2, Some(0)),
+                declaration("model", 1, gl::FLOAT_MAT4, Some(1)),
+                declaration("color", 1, gl::FLOAT_VEC4, Some(5)),
+                declaration("uvRect", 1, gl::FLOAT_VEC4, Some(6)),
+            ]
+        );
+    }
+
+    #[test]
+    fn assigns_free_locations_to_inputs_without_a_layout() {
+        let interface = Interface::of([(
+            gl::VERTEX_SHADER,
+            "layout(location = 0) in mat3 a; in vec2 b; in float c;",
+        )]);
+        let locations: Vec<_> = interface
+            .attributes
+            .iter()
+            .map(|attribute| attribute.location)
+            .collect();
+        assert_eq!(locations, vec![Some(0), Some(3), Some(4)]);
+    }
+}
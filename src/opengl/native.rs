@@ -0,0 +1,367 @@
+use std::{
+    ffi::{c_void, CString},
+    ptr::null,
+};
+
+use gl::types::{GLboolean, GLenum, GLint, GLsizei};
+
+use super::backend::GlBackend;
+
+/// Calls straight into the driver through the loaded `gl` function pointers.
+pub struct NativeBackend;
+
+impl GlBackend for NativeBackend {
+    fn create_shader(&mut self, shader_type: GLenum) -> u32 {
+        unsafe { gl::CreateShader(shader_type) }
+    }
+
+    fn shader_source(&mut self, shader: u32, source: &str) {
+        // with an explicit length the source needs no terminating NUL
+        let length = source.len() as GLint;
+        unsafe { gl::ShaderSource(shader, 1, &source.as_ptr().cast(), &length) }
+    }
+
+    fn compile_shader(&mut self, shader: u32) {
+        unsafe { gl::CompileShader(shader) }
+    }
+
+    fn get_shader_iv(&mut self, shader: u32, pname: GLenum) -> i32 {
+        unsafe {
+            let mut value = gl::FALSE as GLint;
+            gl::GetShaderiv(shader, pname, &mut value);
+            value
+        }
+    }
+
+    fn get_shader_info_log(&mut self, shader: u32) -> String {
+        let capacity = self.get_shader_iv(shader, gl::INFO_LOG_LENGTH).max(1);
+        let mut info_log: Vec<u8> = vec![0; capacity as usize];
+        let mut info_log_size: GLint = 0;
+
+        unsafe {
+            gl::GetShaderInfoLog(shader, capacity, &mut info_log_size, info_log.as_mut_ptr().cast());
+        }
+        info_log.truncate(info_log_size.max(0) as usize);
+
+        String::from_utf8_lossy(&info_log).into_owned()
+    }
+
+    fn delete_shader(&mut self, shader: u32) {
+        unsafe { gl::DeleteShader(shader) }
+    }
+
+    fn create_program(&mut self) -> u32 {
+        unsafe { gl::CreateProgram() }
+    }
+
+    fn attach_shader(&mut self, program: u32, shader: u32) {
+        unsafe { gl::AttachShader(program, shader) }
+    }
+
+    fn link_program(&mut self, program: u32) {
+        unsafe { gl::LinkProgram(program) }
+    }
+
+    fn object_label(&mut self, identifier: GLenum, name: u32, label: &str) {
+        // core since 4.3 only, the labels are just a debugging aid on older contexts
+        if gl::ObjectLabel::is_loaded() {
+            unsafe { gl::ObjectLabel(identifier, name, label.len() as GLsizei, label.as_ptr().cast()) }
+        }
+    }
+
+    fn get_program_iv(&mut self, program: u32, pname: GLenum) -> i32 {
+        unsafe {
+            let mut value = gl::FALSE as GLint;
+            gl::GetProgramiv(program, pname, &mut value);
+            value
+        }
+    }
+
+    fn get_program_info_log(&mut self, program: u32) -> String {
+        let capacity = self.get_program_iv(program, gl::INFO_LOG_LENGTH).max(1);
+        let mut info_log: Vec<u8> = vec![0; capacity as usize];
+        let mut info_log_size: GLint = 0;
+
+        unsafe {
+            gl::GetProgramInfoLog(program, capacity, &mut info_log_size, info_log.as_mut_ptr().cast());
+        }
+        info_log.truncate(info_log_size.max(0) as usize);
+
+        String::from_utf8_lossy(&info_log).into_owned()
+    }
+
+    fn use_program(&mut self, program: u32) {
+        unsafe { gl::UseProgram(program) }
+    }
+
+    fn get_uniform_location(&mut self, program: u32, name: &str) -> i32 {
+        // GLSL names can't contain NUL, so such a name is never an active uniform
+        let Ok(name) = CString::new(name) else {
+            return -1;
+        };
+        unsafe { gl::GetUniformLocation(program, name.as_ptr()) }
+    }
+
+    fn get_active_uniform(&mut self, program: u32, index: u32) -> (String, i32, GLenum) {
+        let capacity = self.get_program_iv(program, gl::ACTIVE_UNIFORM_MAX_LENGTH).max(1);
+        let mut name: Vec<u8> = vec![0; capacity as usize];
+        let (mut length, mut size, mut type_): (GLint, GLint, GLenum) = (0, 0, 0);
+
+        unsafe {
+            gl::GetActiveUniform(
+                program,
+                index,
+                capacity,
+                &mut length,
+                &mut size,
+                &mut type_,
+                name.as_mut_ptr().cast(),
+            );
+        }
+        name.truncate(length.max(0) as usize);
+
+        (String::from_utf8_lossy(&name).into_owned(), size, type_)
+    }
+
+    fn delete_program(&mut self, program: u32) {
+        unsafe { gl::DeleteProgram(program) }
+    }
+
+    fn uniform_1f(&mut self, location: i32, f: f32) {
+        unsafe { gl::Uniform1f(location, f) }
+    }
+
+    fn uniform_3f(&mut self, location: i32, f1: f32, f2: f32, f3: f32) {
+        unsafe { gl::Uniform3f(location, f1, f2, f3) }
+    }
+
+    fn uniform_4f(&mut self, location: i32, f1: f32, f2: f32, f3: f32, f4: f32) {
+        unsafe { gl::Uniform4f(location, f1, f2, f3, f4) }
+    }
+
+    fn uniform_1i(&mut self, location: i32, i: i32) {
+        unsafe { gl::Uniform1i(location, i) }
+    }
+
+    fn uniform_matrix4fv(&mut self, location: i32, count: i32, transpose: bool, matrix: &[f32]) {
+        unsafe { gl::UniformMatrix4fv(location, count, bool_to_glbool(transpose), matrix.as_ptr()) }
+    }
+
+    fn gen_buffer(&mut self) -> u32 {
+        let mut buffer = 0;
+        unsafe { gl::GenBuffers(1, &mut buffer) };
+        buffer
+    }
+
+    fn bind_buffer(&mut self, target: GLenum, buffer: u32) {
+        unsafe { gl::BindBuffer(target, buffer) }
+    }
+
+    fn delete_buffer(&mut self, buffer: u32) {
+        unsafe { gl::DeleteBuffers(1, &buffer) }
+    }
+
+    unsafe fn buffer_data(&mut self, target: GLenum, size: usize, data: *const c_void, usage: GLenum) {
+        gl::BufferData(target, size as isize, data, usage);
+    }
+
+    unsafe fn buffer_sub_data(&mut self, target: GLenum, offset: usize, size: usize, data: *const c_void) {
+        gl::BufferSubData(target, offset as isize, size as isize, data);
+    }
+
+    fn gen_vertex_array(&mut self) -> u32 {
+        let mut vao = 0;
+        unsafe { gl::GenVertexArrays(1, &mut vao) };
+        vao
+    }
+
+    fn bind_vertex_array(&mut self, vao: u32) {
+        unsafe { gl::BindVertexArray(vao) }
+    }
+
+    fn delete_vertex_array(&mut self, vao: u32) {
+        unsafe { gl::DeleteVertexArrays(1, &vao) }
+    }
+
+    fn vertex_attrib_pointer(
+        &mut self,
+        index: u32,
+        size: i32,
+        type_: GLenum,
+        normalized: bool,
+        stride: usize,
+        offset: usize,
+    ) {
+        unsafe {
+            gl::VertexAttribPointer(
+                index,
+                size,
+                type_,
+                bool_to_glbool(normalized),
+                stride as i32,
+                offset as *const c_void,
+            );
+        }
+    }
+
+    fn enable_vertex_attrib_array(&mut self, index: u32) {
+        unsafe { gl::EnableVertexAttribArray(index) }
+    }
+
+    fn vertex_attrib_divisor(&mut self, index: u32, divisor: u32) {
+        unsafe { gl::VertexAttribDivisor(index, divisor) }
+    }
+
+    fn gen_texture(&mut self) -> u32 {
+        let mut texture = 0;
+        unsafe { gl::GenTextures(1, &mut texture) };
+        texture
+    }
+
+    fn bind_texture(&mut self, target: GLenum, texture: u32) {
+        unsafe { gl::BindTexture(target, texture) }
+    }
+
+    fn delete_texture(&mut self, texture: u32) {
+        unsafe { gl::DeleteTextures(1, &texture) }
+    }
+
+    fn tex_image_2d(
+        &mut self,
+        target: GLenum,
+        level: i32,
+        internal_format: GLenum,
+        width: i32,
+        height: i32,
+        format: GLenum,
+        type_: GLenum,
+        data: &[u8],
+    ) {
+        unsafe {
+            gl::TexImage2D(
+                target,
+                level,
+                internal_format as i32,
+                width,
+                height,
+                0,
+                format,
+                type_,
+                if data.is_empty() { null() } else { data.as_ptr().cast() },
+            );
+        }
+    }
+
+    fn tex_parameter_i(&mut self, target: GLenum, pname: GLenum, param: GLenum) {
+        unsafe { gl::TexParameteri(target, pname, param as i32) }
+    }
+
+    fn active_texture(&mut self, texture: GLenum) {
+        unsafe { gl::ActiveTexture(texture) }
+    }
+
+    fn gen_framebuffer(&mut self) -> u32 {
+        let mut framebuffer = 0;
+        unsafe { gl::GenFramebuffers(1, &mut framebuffer) };
+        framebuffer
+    }
+
+    fn bind_framebuffer(&mut self, target: GLenum, framebuffer: u32) {
+        unsafe { gl::BindFramebuffer(target, framebuffer) }
+    }
+
+    fn delete_framebuffer(&mut self, framebuffer: u32) {
+        unsafe { gl::DeleteFramebuffers(1, &framebuffer) }
+    }
+
+    fn framebuffer_texture_2d(
+        &mut self,
+        target: GLenum,
+        attachment: GLenum,
+        texture_target: GLenum,
+        texture: u32,
+        level: i32,
+    ) {
+        unsafe { gl::FramebufferTexture2D(target, attachment, texture_target, texture, level) }
+    }
+
+    fn framebuffer_renderbuffer(
+        &mut self,
+        target: GLenum,
+        attachment: GLenum,
+        renderbuffer_target: GLenum,
+        renderbuffer: u32,
+    ) {
+        unsafe { gl::FramebufferRenderbuffer(target, attachment, renderbuffer_target, renderbuffer) }
+    }
+
+    fn check_framebuffer_status(&mut self, target: GLenum) -> GLenum {
+        unsafe { gl::CheckFramebufferStatus(target) }
+    }
+
+    fn blit_framebuffer(&mut self, source: [i32; 4], destination: [i32; 4], mask: GLenum, filter: GLenum) {
+        let [sx0, sy0, sx1, sy1] = source;
+        let [dx0, dy0, dx1, dy1] = destination;
+        unsafe { gl::BlitFramebuffer(sx0, sy0, sx1, sy1, dx0, dy0, dx1, dy1, mask, filter) }
+    }
+
+    fn gen_renderbuffer(&mut self) -> u32 {
+        let mut renderbuffer = 0;
+        unsafe { gl::GenRenderbuffers(1, &mut renderbuffer) };
+        renderbuffer
+    }
+
+    fn bind_renderbuffer(&mut self, target: GLenum, renderbuffer: u32) {
+        unsafe { gl::BindRenderbuffer(target, renderbuffer) }
+    }
+
+    fn delete_renderbuffer(&mut self, renderbuffer: u32) {
+        unsafe { gl::DeleteRenderbuffers(1, &renderbuffer) }
+    }
+
+    fn renderbuffer_storage_multisample(
+        &mut self,
+        target: GLenum,
+        samples: i32,
+        internal_format: GLenum,
+        width: i32,
+        height: i32,
+    ) {
+        unsafe { gl::RenderbufferStorageMultisample(target, samples, internal_format, width, height) }
+    }
+
+    fn clear_color(&mut self, r: f32, g: f32, b: f32, a: f32) {
+        unsafe { gl::ClearColor(r, g, b, a) }
+    }
+
+    fn clear(&mut self, mask: GLenum) {
+        unsafe { gl::Clear(mask) }
+    }
+
+    fn viewport(&mut self, x: i32, y: i32, width: i32, height: i32) {
+        unsafe { gl::Viewport(x, y, width, height) }
+    }
+
+    fn draw_arrays(&mut self, mode: GLenum, first: i32, count: usize) {
+        unsafe { gl::DrawArrays(mode, first, count as i32) }
+    }
+
+    fn draw_arrays_instanced(&mut self, mode: GLenum, first: i32, count: usize, instance_count: usize) {
+        unsafe { gl::DrawArraysInstanced(mode, first, count as i32, instance_count as i32) }
+    }
+
+    fn blend_func(&mut self, s_factor: GLenum, d_factor: GLenum) {
+        unsafe { gl::BlendFunc(s_factor, d_factor) }
+    }
+
+    fn enable(&mut self, cap: GLenum) {
+        unsafe { gl::Enable(cap) }
+    }
+}
+
+fn bool_to_glbool(b: bool) -> GLboolean {
+    match b {
+        true => gl::TRUE,
+        false => gl::FALSE,
+    }
+}
@@ -0,0 +1,671 @@
+use std::{cell::RefCell, collections::HashMap, ffi::c_void, rc::Rc};
+
+use gl::types::GLenum;
+
+use super::{
+    backend::{set_backend, GlBackend},
+    glsl::Interface,
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum UniformValue {
+    Float(f32),
+    Vec3(f32, f32, f32),
+    Vec4(f32, f32, f32, f32),
+    Int(i32),
+    Matrix4(Vec<f32>),
+}
+
+/// A GL call as seen by the `RecordingBackend`. Queries are answered but not logged.
+#[derive(Debug, Clone, PartialEq)]
+pub enum GlCall {
+    CreateShader {
+        shader_type: GLenum,
+        shader: u32,
+    },
+    ShaderSource {
+        shader: u32,
+        source: String,
+    },
+    CompileShader(u32),
+    DeleteShader(u32),
+    CreateProgram(u32),
+    AttachShader {
+        program: u32,
+        shader: u32,
+    },
+    LinkProgram(u32),
+    ObjectLabel {
+        identifier: GLenum,
+        name: u32,
+        label: String,
+    },
+    UseProgram(u32),
+    DeleteProgram(u32),
+    Uniform {
+        location: i32,
+        value: UniformValue,
+    },
+    GenBuffer(u32),
+    BindBuffer {
+        target: GLenum,
+        buffer: u32,
+    },
+    DeleteBuffer(u32),
+    BufferData {
+        target: GLenum,
+        size: usize,
+        usage: GLenum,
+    },
+    BufferSubData {
+        target: GLenum,
+        offset: usize,
+        size: usize,
+    },
+    GenVertexArray(u32),
+    BindVertexArray(u32),
+    DeleteVertexArray(u32),
+    VertexAttribPointer {
+        index: u32,
+        size: i32,
+        type_: GLenum,
+        normalized: bool,
+        stride: usize,
+        offset: usize,
+    },
+    EnableVertexAttribArray(u32),
+    VertexAttribDivisor {
+        index: u32,
+        divisor: u32,
+    },
+    GenTexture(u32),
+    BindTexture {
+        target: GLenum,
+        texture: u32,
+    },
+    DeleteTexture(u32),
+    TexImage2D {
+        target: GLenum,
+        internal_format: GLenum,
+        width: i32,
+        height: i32,
+        format: GLenum,
+    },
+    TexParameter {
+        target: GLenum,
+        pname: GLenum,
+        param: GLenum,
+    },
+    ActiveTexture(GLenum),
+    ClearColor(f32, f32, f32, f32),
+    Clear(GLenum),
+    Viewport {
+        x: i32,
+        y: i32,
+        width: i32,
+        height: i32,
+    },
+    DrawArrays {
+        mode: GLenum,
+        first: i32,
+        count: usize,
+    },
+    DrawArraysInstanced {
+        mode: GLenum,
+        first: i32,
+        count: usize,
+        instance_count: usize,
+    },
+    BlendFunc {
+        s_factor: GLenum,
+        d_factor: GLenum,
+    },
+    Enable(GLenum),
+    GenFramebuffer(u32),
+    BindFramebuffer {
+        target: GLenum,
+        framebuffer: u32,
+    },
+    DeleteFramebuffer(u32),
+    FramebufferTexture2D {
+        attachment: GLenum,
+        texture: u32,
+    },
+    FramebufferRenderbuffer {
+        attachment: GLenum,
+        renderbuffer: u32,
+    },
+    BlitFramebuffer {
+        source: [i32; 4],
+        destination: [i32; 4],
+        mask: GLenum,
+        filter: GLenum,
+    },
+    GenRenderbuffer(u32),
+    BindRenderbuffer(u32),
+    DeleteRenderbuffer(u32),
+    RenderbufferStorageMultisample {
+        samples: i32,
+        internal_format: GLenum,
+        width: i32,
+        height: i32,
+    },
+}
+
+impl GlCall {
+    pub fn is_draw(&self) -> bool {
+        matches!(self, GlCall::DrawArrays { .. } | GlCall::DrawArraysInstanced { .. })
+    }
+}
+
+#[derive(Debug, Default)]
+struct Log {
+    calls: Vec<GlCall>,
+    last_id: u32,
+    uniform_locations: HashMap<(u32, String), i32>,
+    uniform_names: HashMap<i32, String>,
+    shaders: HashMap<u32, (GLenum, String)>,
+    attached_shaders: HashMap<u32, Vec<u32>>,
+    interfaces: HashMap<u32, Interface>,
+}
+
+impl Log {
+    fn next_id(&mut self) -> u32 {
+        self.last_id += 1;
+        self.last_id
+    }
+
+    fn push(&mut self, call: GlCall) {
+        self.calls.push(call);
+    }
+}
+
+/// Backend for tests without a GPU. Logs every call, hands out fake object ids and reports
+/// every shader and program as compiled and linked, with the uniforms and inputs their sources declare.
+pub struct RecordingBackend {
+    log: Rc<RefCell<Log>>,
+}
+
+impl RecordingBackend {
+    /// Makes a new recording backend current for this thread and returns a handle to its log.
+    pub fn install() -> Recorder {
+        let log = Rc::new(RefCell::new(Log::default()));
+        set_backend(Box::new(RecordingBackend { log: Rc::clone(&log) }));
+        Recorder { log }
+    }
+
+    fn log(&self, call: GlCall) {
+        self.log.borrow_mut().push(call);
+    }
+
+    fn gen(&self, call: impl FnOnce(u32) -> GlCall) -> u32 {
+        let mut log = self.log.borrow_mut();
+        let id = log.next_id();
+        log.push(call(id));
+        id
+    }
+}
+
+/// Read access to the calls logged by an installed `RecordingBackend`.
+#[derive(Clone)]
+pub struct Recorder {
+    log: Rc<RefCell<Log>>,
+}
+
+impl Recorder {
+    pub fn calls(&self) -> Vec<GlCall> {
+        self.log.borrow().calls.clone()
+    }
+
+    pub fn draw_calls(&self) -> Vec<GlCall> {
+        self.log
+            .borrow()
+            .calls
+            .iter()
+            .filter(|call| call.is_draw())
+            .cloned()
+            .collect()
+    }
+
+    pub fn clear(&self) {
+        self.log.borrow_mut().calls.clear();
+    }
+
+    pub fn uniform_name(&self, location: i32) -> Option<String> {
+        self.log.borrow().uniform_names.get(&location).cloned()
+    }
+
+    /// Every value set on a uniform called `name`, in any program, in call order.
+    pub fn uniform_values(&self, name: &str) -> Vec<UniformValue> {
+        let log = self.log.borrow();
+        log.calls
+            .iter()
+            .filter_map(|call| match call {
+                GlCall::Uniform { location, value }
+                    if log.uniform_names.get(location).map(String::as_str) == Some(name) =>
+                {
+                    Some(value.clone())
+                }
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+impl GlBackend for RecordingBackend {
+    fn create_shader(&mut self, shader_type: GLenum) -> u32 {
+        let shader = self.gen(|shader| GlCall::CreateShader { shader_type, shader });
+        self.log
+            .borrow_mut()
+            .shaders
+            .insert(shader, (shader_type, String::new()));
+        shader
+    }
+
+    fn shader_source(&mut self, shader: u32, source: &str) {
+        if let Some((_, shader_source)) = self.log.borrow_mut().shaders.get_mut(&shader) {
+            *shader_source = source.to_string();
+        }
+        self.log(GlCall::ShaderSource {
+            shader,
+            source: source.to_string(),
+        });
+    }
+
+    fn compile_shader(&mut self, shader: u32) {
+        self.log(GlCall::CompileShader(shader));
+    }
+
+    fn get_shader_iv(&mut self, _shader: u32, _pname: GLenum) -> i32 {
+        gl::TRUE as i32
+    }
+
+    fn get_shader_info_log(&mut self, _shader: u32) -> String {
+        String::new()
+    }
+
+    fn delete_shader(&mut self, shader: u32) {
+        self.log(GlCall::DeleteShader(shader));
+    }
+
+    fn create_program(&mut self) -> u32 {
+        self.gen(GlCall::CreateProgram)
+    }
+
+    fn attach_shader(&mut self, program: u32, shader: u32) {
+        self.log
+            .borrow_mut()
+            .attached_shaders
+            .entry(program)
+            .or_default()
+            .push(shader);
+        self.log(GlCall::AttachShader { program, shader });
+    }
+
+    fn link_program(&mut self, program: u32) {
+        let mut log = self.log.borrow_mut();
+        let log = &mut *log;
+        let stages = log.attached_shaders.get(&program).into_iter().flatten();
+        let interface = Interface::of(
+            stages
+                .filter_map(|shader| log.shaders.get(shader))
+                .map(|(shader_type, source)| (*shader_type, source.as_str())),
+        );
+        log.interfaces.insert(program, interface);
+        log.push(GlCall::LinkProgram(program));
+    }
+
+    fn object_label(&mut self, identifier: GLenum, name: u32, label: &str) {
+        self.log(GlCall::ObjectLabel {
+            identifier,
+            name,
+            label: label.to_string(),
+        });
+    }
+
+    fn get_program_iv(&mut self, program: u32, pname: GLenum) -> i32 {
+        let log = self.log.borrow();
+        let interface = log.interfaces.get(&program);
+        match pname {
+            gl::ACTIVE_UNIFORMS => interface.map_or(0, |interface| interface.uniforms.len() as i32),
+            _ => gl::TRUE as i32,
+        }
+    }
+
+    fn get_program_info_log(&mut self, _program: u32) -> String {
+        String::new()
+    }
+
+    fn use_program(&mut self, program: u32) {
+        self.log(GlCall::UseProgram(program));
+    }
+
+    fn get_uniform_location(&mut self, program: u32, name: &str) -> i32 {
+        let mut log = self.log.borrow_mut();
+        if let Some(&location) = log.uniform_locations.get(&(program, name.to_string())) {
+            return location;
+        }
+
+        let location = log.uniform_locations.len() as i32;
+        log.uniform_locations.insert((program, name.to_string()), location);
+        log.uniform_names.insert(location, name.to_string());
+        location
+    }
+
+    fn get_active_uniform(&mut self, program: u32, index: u32) -> (String, i32, GLenum) {
+        let log = self.log.borrow();
+        let uniform = &log.interfaces[&program].uniforms[index as usize];
+        (uniform.name.clone(), uniform.size, uniform.type_)
+    }
+
+    fn delete_program(&mut self, program: u32) {
+        self.log(GlCall::DeleteProgram(program));
+    }
+
+    fn uniform_1f(&mut self, location: i32, f: f32) {
+        self.log(GlCall::Uniform {
+            location,
+            value: UniformValue::Float(f),
+        });
+    }
+
+    fn uniform_3f(&mut self, location: i32, f1: f32, f2: f32, f3: f32) {
+        self.log(GlCall::Uniform {
+            location,
+            value: UniformValue::Vec3(f1, f2, f3),
+        });
+    }
+
+    fn uniform_4f(&mut self, location: i32, f1: f32, f2: f32, f3: f32, f4: f32) {
+        self.log(GlCall::Uniform {
+            location,
+            value: UniformValue::Vec4(f1, f2, f3, f4),
+        });
+    }
+
+    fn uniform_1i(&mut self, location: i32, i: i32) {
+        self.log(GlCall::Uniform {
+            location,
+            value: UniformValue::Int(i),
+        });
+    }
+
+    fn uniform_matrix4fv(&mut self, location: i32, _count: i32, _transpose: bool, matrix: &[f32]) {
+        self.log(GlCall::Uniform {
+            location,
+            value: UniformValue::Matrix4(matrix.to_vec()),
+        });
+    }
+
+    fn gen_buffer(&mut self) -> u32 {
+        self.gen(GlCall::GenBuffer)
+    }
+
+    fn bind_buffer(&mut self, target: GLenum, buffer: u32) {
+        self.log(GlCall::BindBuffer { target, buffer });
+    }
+
+    fn delete_buffer(&mut self, buffer: u32) {
+        self.log(GlCall::DeleteBuffer(buffer));
+    }
+
+    unsafe fn buffer_data(&mut self, target: GLenum, size: usize, _data: *const c_void, usage: GLenum) {
+        self.log(GlCall::BufferData { target, size, usage });
+    }
+
+    unsafe fn buffer_sub_data(&mut self, target: GLenum, offset: usize, size: usize, _data: *const c_void) {
+        self.log(GlCall::BufferSubData { target, offset, size });
+    }
+
+    fn gen_vertex_array(&mut self) -> u32 {
+        self.gen(GlCall::GenVertexArray)
+    }
+
+    fn bind_vertex_array(&mut self, vao: u32) {
+        self.log(GlCall::BindVertexArray(vao));
+    }
+
+    fn delete_vertex_array(&mut self, vao: u32) {
+        self.log(GlCall::DeleteVertexArray(vao));
+    }
+
+    fn vertex_attrib_pointer(
+        &mut self,
+        index: u32,
+        size: i32,
+        type_: GLenum,
+        normalized: bool,
+        stride: usize,
+        offset: usize,
+    ) {
+        self.log(GlCall::VertexAttribPointer {
+            index,
+            size,
+            type_,
+            normalized,
+            stride,
+            offset,
+        });
+    }
+
+    fn enable_vertex_attrib_array(&mut self, index: u32) {
+        self.log(GlCall::EnableVertexAttribArray(index));
+    }
+
+    fn vertex_attrib_divisor(&mut self, index: u32, divisor: u32) {
+        self.log(GlCall::VertexAttribDivisor { index, divisor });
+    }
+
+    fn gen_texture(&mut self) -> u32 {
+        self.gen(GlCall::GenTexture)
+    }
+
+    fn bind_texture(&mut self, target: GLenum, texture: u32) {
+        self.log(GlCall::BindTexture { target, texture });
+    }
+
+    fn delete_texture(&mut self, texture: u32) {
+        self.log(GlCall::DeleteTexture(texture));
+    }
+
+    fn tex_image_2d(
+        &mut self,
+        target: GLenum,
+        _level: i32,
+        internal_format: GLenum,
+        width: i32,
+        height: i32,
+        format: GLenum,
+        _type: GLenum,
+        _data: &[u8],
+    ) {
+        self.log(GlCall::TexImage2D {
+            target,
+            internal_format,
+            width,
+            height,
+            format,
+        });
+    }
+
+    fn tex_parameter_i(&mut self, target: GLenum, pname: GLenum, param: GLenum) {
+        self.log(GlCall::TexParameter { target, pname, param });
+    }
+
+    fn active_texture(&mut self, texture: GLenum) {
+        self.log(GlCall::ActiveTexture(texture));
+    }
+
+    fn clear_color(&mut self, r: f32, g: f32, b: f32, a: f32) {
+        self.log(GlCall::ClearColor(r, g, b, a));
+    }
+
+    fn clear(&mut self, mask: GLenum) {
+        self.log(GlCall::Clear(mask));
+    }
+
+    fn viewport(&mut self, x: i32, y: i32, width: i32, height: i32) {
+        self.log(GlCall::Viewport { x, y, width, height });
+    }
+
+    fn draw_arrays(&mut self, mode: GLenum, first: i32, count: usize) {
+        self.log(GlCall::DrawArrays { mode, first, count });
+    }
+
+    fn draw_arrays_instanced(&mut self, mode: GLenum, first: i32, count: usize, instance_count: usize) {
+        self.log(GlCall::DrawArraysInstanced {
+            mode,
+            first,
+            count,
+            instance_count,
+        });
+    }
+
+    fn gen_framebuffer(&mut self) -> u32 {
+        self.gen(GlCall::GenFramebuffer)
+    }
+
+    fn bind_framebuffer(&mut self, target: GLenum, framebuffer: u32) {
+        self.log(GlCall::BindFramebuffer { target, framebuffer });
+    }
+
+    fn delete_framebuffer(&mut self, framebuffer: u32) {
+        self.log(GlCall::DeleteFramebuffer(framebuffer));
+    }
+
+    fn framebuffer_texture_2d(
+        &mut self,
+        _target: GLenum,
+        attachment: GLenum,
+        _texture_target: GLenum,
+        texture: u32,
+        _level: i32,
+    ) {
+        self.log(GlCall::FramebufferTexture2D { attachment, texture });
+    }
+
+    fn framebuffer_renderbuffer(
+        &mut self,
+        _target: GLenum,
+        attachment: GLenum,
+        _renderbuffer_target: GLenum,
+        renderbuffer: u32,
+    ) {
+        self.log(GlCall::FramebufferRenderbuffer {
+            attachment,
+            renderbuffer,
+        });
+    }
+
+    fn check_framebuffer_status(&mut self, _target: GLenum) -> GLenum {
+        gl::FRAMEBUFFER_COMPLETE
+    }
+
+    fn blit_framebuffer(&mut self, source: [i32; 4], destination: [i32; 4], mask: GLenum, filter: GLenum) {
+        self.log(GlCall::BlitFramebuffer {
+            source,
+            destination,
+            mask,
+            filter,
+        });
+    }
+
+    fn gen_renderbuffer(&mut self) -> u32 {
+        self.gen(GlCall::GenRenderbuffer)
+    }
+
+    fn bind_renderbuffer(&mut self, _target: GLenum, renderbuffer: u32) {
+        self.log(GlCall::BindRenderbuffer(renderbuffer));
+    }
+
+    fn delete_renderbuffer(&mut self, renderbuffer: u32) {
+        self.log(GlCall::DeleteRenderbuffer(renderbuffer));
+    }
+
+    fn renderbuffer_storage_multisample(
+        &mut self,
+        _target: GLenum,
+        samples: i32,
+        internal_format: GLenum,
+        width: i32,
+        height: i32,
+    ) {
+        self.log(GlCall::RenderbufferStorageMultisample {
+            samples,
+            internal_format,
+            width,
+            height,
+        });
+    }
+
+    fn blend_func(&mut self, s_factor: GLenum, d_factor: GLenum) {
+        self.log(GlCall::BlendFunc { s_factor, d_factor });
+    }
+
+    fn enable(&mut self, cap: GLenum) {
+        self.log(GlCall::Enable(cap));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::opengl::{create_program, draw_arrays, gen_buffers, gen_textures};
+
+    use super::*;
+
+    #[test]
+    fn hands_out_unique_ids_and_logs_deletes_on_drop() {
+        let recorder = RecordingBackend::install();
+
+        let [first, second] = gen_buffers::<2>();
+        let [texture] = gen_textures::<1>();
+        drop((first, second, texture));
+
+        assert_eq!(
+            recorder.calls(),
+            vec![
+                GlCall::GenBuffer(1),
+                GlCall::GenBuffer(2),
+                GlCall::GenTexture(3),
+                GlCall::DeleteBuffer(1),
+                GlCall::DeleteBuffer(2),
+                GlCall::DeleteTexture(3),
+            ]
+        );
+    }
+
+    #[test]
+    fn resolves_uniform_names() {
+        let recorder = RecordingBackend::install();
+
+        let program = create_program();
+        program.get_uniform_location("projection").set_1i(3);
+        program.get_uniform_location("model").set_1f(1.0);
+        program.get_uniform_location("projection").set_1i(4);
+
+        assert_eq!(
+            recorder.uniform_values("projection"),
+            vec![UniformValue::Int(3), UniformValue::Int(4)]
+        );
+        assert_eq!(recorder.uniform_name(1).as_deref(), Some("model"));
+    }
+
+    #[test]
+    fn filters_and_clears_draw_calls() {
+        let recorder = RecordingBackend::install();
+
+        gen_buffers::<1>();
+        draw_arrays(gl::TRIANGLES, 0, 6);
+        assert_eq!(
+            recorder.draw_calls(),
+            vec![GlCall::DrawArrays {
+                mode: gl::TRIANGLES,
+                first: 0,
+                count: 6,
+            }]
+        );
+
+        recorder.clear();
+        assert!(recorder.calls().is_empty());
+    }
+}
@@ -0,0 +1,1386 @@
+use std::{cell::RefCell, collections::HashMap, ffi::c_void, path::Path, rc::Rc, slice};
+
+use gl::types::GLenum;
+use glam::{vec2, vec4, Mat4, Vec2, Vec4};
+
+use super::{
+    backend::{set_backend, GlBackend},
+    glsl::Interface,
+};
+
+const MAX_ATTRIBUTES: usize = 16;
+const MAX_TEXTURE_UNITS: usize = 16;
+
+/// Where `post_process.frag` samples around each pixel, and the kernels it weighs the samples with.
+const POST_PROCESS_OFFSET: f32 = 1.0 / 300.0;
+const EDGE_KERNEL: [f32; 9] = [-1.0, -1.0, -1.0, -1.0, 8.0, -1.0, -1.0, -1.0, -1.0];
+const BLUR_KERNEL: [f32; 9] = [
+    1.0 / 16.0,
+    2.0 / 16.0,
+    1.0 / 16.0,
+    2.0 / 16.0,
+    4.0 / 16.0,
+    2.0 / 16.0,
+    1.0 / 16.0,
+    2.0 / 16.0,
+    1.0 / 16.0,
+];
+
+/// The programs the software backend can run, picked by the label the program was given before
+/// linking. Each one is a port of the shaders of the material with that name in
+/// `resources/assets.toml`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Pipeline {
+    /// `sprite.vert`: unit quad placed by the `model` uniform, textured by `uvRect` and tinted
+    /// by `spriteColor`.
+    Sprite,
+    /// `sprite_batch.vert`: positions, uvs and colors per vertex.
+    Batch,
+    /// `sprite_batch.vert` with `INSTANCED` defined: unit quad placed by a per instance model
+    /// matrix, uv rect and color with alpha.
+    Instanced,
+    /// `text.vert`: position and uv packed in a vec4, tinted by `textColor`.
+    Text,
+    /// `particle.vert`: unit quad centred on a per instance position and size, textured by
+    /// `uvRect` and tinted by a per instance color with alpha.
+    Particle,
+    /// `post_process.vert` and `.frag`: a full screen quad showing the scene texture through the
+    /// shake, confuse and chaos effects.
+    PostProcess,
+}
+
+impl Pipeline {
+    fn named(label: &str) -> Option<Pipeline> {
+        match label {
+            "sprite" => Some(Pipeline::Sprite),
+            "sprite_batch" => Some(Pipeline::Batch),
+            "sprite_instanced" => Some(Pipeline::Instanced),
+            "text" => Some(Pipeline::Text),
+            "particle" => Some(Pipeline::Particle),
+            "post_process" => Some(Pipeline::PostProcess),
+            _ => None,
+        }
+    }
+
+    /// The vertex inputs the port reads, a program that doesn't declare them all can't run on it.
+    fn inputs(self) -> &'static [&'static str] {
+        match self {
+            Pipeline::Sprite | Pipeline::Text | Pipeline::PostProcess => &["vertex"],
+            Pipeline::Batch => &["position", "texCoords", "color"],
+            Pipeline::Instanced => &["vertex", "model", "color", "uvRect"],
+            Pipeline::Particle => &["vertex", "particle", "color"],
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct Shader {
+    shader_type: GLenum,
+    source: String,
+}
+
+#[derive(Debug, Default)]
+struct Program {
+    shaders: Vec<u32>,
+    label: String,
+    pipeline: Option<Pipeline>,
+    info_log: String,
+    interface: Interface,
+    uniforms: HashMap<String, i32>,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Attribute {
+    buffer: u32,
+    size: i32,
+    stride: usize,
+    offset: usize,
+    enabled: bool,
+    divisor: u32,
+}
+
+#[derive(Debug, Default)]
+struct Texture {
+    width: usize,
+    height: usize,
+    texels: Vec<Vec4>,
+    /// Whether the texture stores alpha, without it alpha always reads as 1.
+    alpha: bool,
+    wrap_s: GLenum,
+    wrap_t: GLenum,
+    min_filter: GLenum,
+    mag_filter: GLenum,
+}
+
+impl Texture {
+    fn texel(&self, x: i32, y: i32) -> Vec4 {
+        let x = wrap(x, self.width, self.wrap_s);
+        let y = wrap(y, self.height, self.wrap_t);
+        self.texels[y * self.width + x]
+    }
+
+    /// `texture()` in GLSL, with `filter` picked by the caller as there are no mipmaps.
+    fn sample(&self, uv: Vec2, filter: GLenum) -> Vec4 {
+        if self.texels.is_empty() {
+            return vec4(0.0, 0.0, 0.0, 1.0);
+        }
+
+        let texel = uv * vec2(self.width as f32, self.height as f32);
+        if filter == gl::NEAREST {
+            return self.texel(texel.x.floor() as i32, texel.y.floor() as i32);
+        }
+
+        let texel = texel - 0.5;
+        let (x, y) = (texel.x.floor() as i32, texel.y.floor() as i32);
+        let (fx, fy) = (texel.x - x as f32, texel.y - y as f32);
+        let top = self.texel(x, y).lerp(self.texel(x + 1, y), fx);
+        let bottom = self.texel(x, y + 1).lerp(self.texel(x + 1, y + 1), fx);
+        top.lerp(bottom, fy)
+    }
+}
+
+#[derive(Debug, Default)]
+struct Renderbuffer {
+    width: usize,
+    height: usize,
+    /// Samples are not kept apart, a multisampled renderbuffer holds one color per pixel.
+    pixels: Vec<[u8; 4]>,
+}
+
+/// The color attachment of a framebuffer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Attachment {
+    Texture(u32),
+    Renderbuffer(u32),
+}
+
+fn wrap(coordinate: i32, size: usize, mode: GLenum) -> usize {
+    let size = size as i32;
+    match mode {
+        gl::REPEAT => coordinate.rem_euclid(size) as usize,
+        _ => coordinate.clamp(0, size - 1) as usize,
+    }
+}
+
+/// What the vertex stage hands to the rasterizer.
+#[derive(Debug, Clone, Copy)]
+struct Vertex {
+    position: Vec4,
+    uv: Vec2,
+    color: Vec4,
+}
+
+/// A pixel covered by a triangle, with the interpolated vertex outputs to shade it with.
+#[derive(Debug, Clone, Copy)]
+struct Fragment {
+    index: usize,
+    uv: Vec2,
+    color: Vec4,
+    filter: GLenum,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    width: usize,
+    height: usize,
+    pixels: Vec<[u8; 4]>,
+
+    last_id: u32,
+    shaders: HashMap<u32, Shader>,
+    programs: HashMap<u32, Program>,
+    current_program: u32,
+    uniforms: HashMap<i32, Vec<f32>>,
+    last_uniform: i32,
+
+    buffers: HashMap<u32, Vec<u8>>,
+    array_buffer: u32,
+    vertex_arrays: HashMap<u32, [Attribute; MAX_ATTRIBUTES]>,
+    current_vertex_array: u32,
+
+    textures: HashMap<u32, Texture>,
+    texture_units: [u32; MAX_TEXTURE_UNITS],
+    active_texture: usize,
+
+    /// The color attachment of every framebuffer, framebuffer 0 is the window.
+    framebuffers: HashMap<u32, Option<Attachment>>,
+    renderbuffers: HashMap<u32, Renderbuffer>,
+    bound_renderbuffer: u32,
+    draw_framebuffer: u32,
+    read_framebuffer: u32,
+
+    clear_color: Vec4,
+    viewport: (i32, i32, i32, i32),
+    blend: bool,
+    blend_func: (GLenum, GLenum),
+}
+
+impl State {
+    fn next_id(&mut self) -> u32 {
+        self.last_id += 1;
+        self.last_id
+    }
+
+    fn uniform(&self, program: &Program, name: &str) -> &[f32] {
+        program
+            .uniforms
+            .get(name)
+            .and_then(|location| self.uniforms.get(location))
+            .map_or(&[], Vec::as_slice)
+    }
+
+    fn uniform_matrix(&self, program: &Program, name: &str) -> Mat4 {
+        let matrix = self.uniform(program, name);
+        if matrix.len() == 16 {
+            Mat4::from_cols_slice(matrix)
+        } else {
+            Mat4::ZERO
+        }
+    }
+
+    fn uniform_color(&self, program: &Program, name: &str) -> Vec4 {
+        match *self.uniform(program, name) {
+            [r, g, b] => vec4(r, g, b, 1.0),
+            _ => vec4(0.0, 0.0, 0.0, 1.0),
+        }
+    }
+
+    /// Reads an attribute the way GL does: missing components default to (0, 0, 0, 1).
+    fn attribute(&self, index: usize, vertex: usize, instance: usize) -> Vec4 {
+        let mut value = [0.0, 0.0, 0.0, 1.0];
+        let Some(attribute) = self.vertex_arrays.get(&self.current_vertex_array).map(|vao| vao[index]) else {
+            return Vec4::from_array(value);
+        };
+        if !attribute.enabled {
+            return Vec4::from_array(value);
+        }
+
+        let element = match attribute.divisor {
+            0 => vertex,
+            divisor => instance / divisor as usize,
+        };
+        let size = attribute.size as usize;
+        let stride = if attribute.stride == 0 {
+            size * 4
+        } else {
+            attribute.stride
+        };
+        let start = attribute.offset + element * stride;
+        let data = &self.buffers[&attribute.buffer];
+        for (component, value) in value.iter_mut().take(size).enumerate() {
+            let at = start + component * 4;
+            *value = f32::from_ne_bytes(data[at..at + 4].try_into().unwrap());
+        }
+        Vec4::from_array(value)
+    }
+
+    fn uniform_flag(&self, program: &Program, name: &str) -> bool {
+        self.uniform(program, name).first().is_some_and(|&value| value != 0.0)
+    }
+
+    fn attachment(&self, framebuffer: u32) -> Option<Attachment> {
+        self.framebuffers.get(&framebuffer).copied().flatten()
+    }
+
+    /// Width and height of the color buffer of `framebuffer`.
+    fn color_size(&self, framebuffer: u32) -> (usize, usize) {
+        match self.attachment(framebuffer) {
+            None => (self.width, self.height),
+            Some(Attachment::Texture(texture)) => {
+                let texture = &self.textures[&texture];
+                (texture.width, texture.height)
+            }
+            Some(Attachment::Renderbuffer(renderbuffer)) => {
+                let renderbuffer = &self.renderbuffers[&renderbuffer];
+                (renderbuffer.width, renderbuffer.height)
+            }
+        }
+    }
+
+    fn read_pixel(&self, framebuffer: u32, index: usize) -> [u8; 4] {
+        match self.attachment(framebuffer) {
+            None => self.pixels[index],
+            Some(Attachment::Texture(texture)) => to_bytes(self.textures[&texture].texels[index]),
+            Some(Attachment::Renderbuffer(renderbuffer)) => self.renderbuffers[&renderbuffer].pixels[index],
+        }
+    }
+
+    fn write_pixel(&mut self, framebuffer: u32, index: usize, pixel: [u8; 4]) {
+        match self.attachment(framebuffer) {
+            None => self.pixels[index] = pixel,
+            Some(Attachment::Texture(texture)) => {
+                let texture = self.textures.get_mut(&texture).unwrap();
+                let mut texel = Vec4::from_array(pixel.map(|channel| channel as f32 / 255.0));
+                if !texture.alpha {
+                    texel.w = 1.0;
+                }
+                texture.texels[index] = texel;
+            }
+            Some(Attachment::Renderbuffer(renderbuffer)) => {
+                self.renderbuffers.get_mut(&renderbuffer).unwrap().pixels[index] = pixel;
+            }
+        }
+    }
+
+    /// Copies a rectangle of the read framebuffer into one of the draw framebuffer, scaling with
+    /// the nearest pixel.
+    fn blit(&mut self, source: [i32; 4], destination: [i32; 4]) {
+        let (read, draw) = (self.read_framebuffer, self.draw_framebuffer);
+        let (source_width, _) = self.color_size(read);
+        let (destination_width, _) = self.color_size(draw);
+        let [sx0, sy0, sx1, sy1] = source;
+        let [dx0, dy0, dx1, dy1] = destination;
+
+        for y in dy0..dy1 {
+            for x in dx0..dx1 {
+                let sx = sx0 + (x - dx0) * (sx1 - sx0) / (dx1 - dx0);
+                let sy = sy0 + (y - dy0) * (sy1 - sy0) / (dy1 - dy0);
+                let pixel = self.read_pixel(read, sy as usize * source_width + sx as usize);
+                self.write_pixel(draw, y as usize * destination_width + x as usize, pixel);
+            }
+        }
+    }
+
+    fn shade_vertex(&self, pipeline: Pipeline, program: &Program, vertex: usize, instance: usize) -> Vertex {
+        let projection = self.uniform_matrix(program, "projection");
+        let position = self.attribute(0, vertex, instance);
+        let corner = vec2(position.x, position.y);
+        let on_plane = |xy: Vec2| vec4(xy.x, xy.y, 0.0, 1.0);
+
+        match pipeline {
+            Pipeline::Sprite => Vertex {
+                position: projection * self.uniform_matrix(program, "model") * on_plane(corner),
+                uv: uv_in_rect(self.uniform(program, "uvRect"), corner),
+                color: self.uniform_color(program, "spriteColor"),
+            },
+            Pipeline::Batch => Vertex {
+                position: projection * on_plane(corner),
+                uv: self.attribute(1, vertex, instance).truncate().truncate(),
+                color: self.attribute(2, vertex, instance).truncate().extend(1.0),
+            },
+            Pipeline::Instanced => {
+                let model = Mat4::from_cols(
+                    self.attribute(1, vertex, instance),
+                    self.attribute(2, vertex, instance),
+                    self.attribute(3, vertex, instance),
+                    self.attribute(4, vertex, instance),
+                );
+                let uv_rect = self.attribute(6, vertex, instance);
+                Vertex {
+                    position: projection * model * on_plane(corner),
+                    uv: uv_in_rect(&uv_rect.to_array(), corner),
+                    color: self.attribute(5, vertex, instance),
+                }
+            }
+            Pipeline::Text => Vertex {
+                position: projection * on_plane(corner),
+                uv: vec2(position.z, position.w),
+                color: self.uniform_color(program, "textColor"),
+            },
+            Pipeline::Particle => {
+                let particle = self.attribute(1, vertex, instance);
+                let center = vec2(particle.x, particle.y);
+                Vertex {
+                    position: projection * on_plane(center + (corner - 0.5) * particle.z),
+                    uv: uv_in_rect(self.uniform(program, "uvRect"), corner),
+                    color: self.attribute(2, vertex, instance),
+                }
+            }
+            Pipeline::PostProcess => {
+                let time = self.uniform(program, "time").first().copied().unwrap_or(0.0);
+                let uv = corner * 0.5 + 0.5;
+                let uv = if self.uniform_flag(program, "chaos") {
+                    uv + vec2(time.sin(), time.cos()) * 0.3
+                } else if self.uniform_flag(program, "confuse") {
+                    1.0 - uv
+                } else {
+                    uv
+                };
+                let mut position = on_plane(corner);
+                if self.uniform_flag(program, "shake") {
+                    position.x += (time * 10.0).cos() * 0.01;
+                    position.y += (time * 15.0).cos() * 0.01;
+                }
+                Vertex {
+                    position,
+                    uv,
+                    color: Vec4::ONE,
+                }
+            }
+        }
+    }
+
+    fn shade_fragment(&self, pipeline: Pipeline, program: &Program, texture: &Texture, fragment: Fragment) -> Vec4 {
+        let sample = |offset: Vec2| texture.sample(fragment.uv + offset, fragment.filter);
+        if pipeline != Pipeline::PostProcess {
+            return fragment.color * sample(Vec2::ZERO);
+        }
+
+        let convolve = |kernel: [f32; 9]| {
+            let mut color = Vec4::ZERO;
+            for (i, weight) in kernel.iter().enumerate() {
+                let offset = vec2((i % 3) as f32 - 1.0, 1.0 - (i / 3) as f32) * POST_PROCESS_OFFSET;
+                color += sample(offset) * *weight;
+            }
+            color.truncate().extend(1.0)
+        };
+        if self.uniform_flag(program, "chaos") {
+            convolve(EDGE_KERNEL)
+        } else if self.uniform_flag(program, "confuse") {
+            (1.0 - sample(Vec2::ZERO).truncate()).extend(1.0)
+        } else if self.uniform_flag(program, "shake") {
+            convolve(BLUR_KERNEL)
+        } else {
+            sample(Vec2::ZERO)
+        }
+    }
+
+    fn draw(&mut self, mode: GLenum, first: usize, count: usize, instance_count: usize) {
+        assert_eq!(mode, gl::TRIANGLES, "The software backend only draws triangles");
+
+        let program = &self.programs[&self.current_program];
+        let pipeline = program
+            .pipeline
+            .unwrap_or_else(|| panic!("Program {} is not linked", self.current_program));
+        let unit = match *self.uniform(program, "image") {
+            [unit] => unit as usize,
+            _ => 0,
+        };
+        let texture = &self.textures[&self.texture_units[unit]];
+
+        let mut triangles = Vec::with_capacity(instance_count * count / 3);
+        for instance in 0..instance_count {
+            let vertices: Vec<Vertex> = (first..first + count)
+                .map(|vertex| self.shade_vertex(pipeline, program, vertex, instance))
+                .collect();
+            triangles.extend(
+                vertices
+                    .chunks_exact(3)
+                    .map(|triangle| [triangle[0], triangle[1], triangle[2]]),
+            );
+        }
+
+        let mut fragments = Vec::new();
+        for triangle in triangles {
+            self.rasterize(triangle, texture, &mut fragments);
+        }
+        let colors: Vec<(usize, Vec4)> = fragments
+            .into_iter()
+            .map(|fragment| {
+                (
+                    fragment.index,
+                    self.shade_fragment(pipeline, program, texture, fragment),
+                )
+            })
+            .collect();
+
+        let framebuffer = self.draw_framebuffer;
+        for (index, color) in colors {
+            let blended = self.blend(self.read_pixel(framebuffer, index), color);
+            self.write_pixel(framebuffer, index, blended);
+        }
+    }
+
+    /// Collects the pixels `triangle` covers, sampled at pixel centres with the top-left fill rule
+    /// so quads sharing an edge never touch a pixel twice.
+    fn rasterize(&self, triangle: [Vertex; 3], texture: &Texture, fragments: &mut Vec<Fragment>) {
+        let (target_width, target_height) = self.color_size(self.draw_framebuffer);
+        let (x, y, width, height) = self.viewport;
+        let to_window = |vertex: &Vertex| {
+            let ndc = vertex.position.truncate().truncate() / vertex.position.w;
+            vec2(
+                x as f32 + (ndc.x + 1.0) * 0.5 * width as f32,
+                y as f32 + (ndc.y + 1.0) * 0.5 * height as f32,
+            )
+        };
+        let mut corners = triangle.map(|vertex| to_window(&vertex));
+        let mut triangle = triangle;
+
+        let area = edge(corners[0], corners[1], corners[2]);
+        if area == 0.0 {
+            return;
+        }
+        if area < 0.0 {
+            corners.swap(1, 2);
+            triangle.swap(1, 2);
+        }
+        let area = area.abs();
+
+        // texels per pixel decides between the minification and magnification filter
+        let texture_size = vec2(texture.width as f32, texture.height as f32);
+        let (du, dv) = uv_gradients(&corners, &triangle, area);
+        let footprint = (du * texture_size).length().max((dv * texture_size).length());
+        let filter = if footprint > 1.0 {
+            texture.min_filter
+        } else {
+            texture.mag_filter
+        };
+
+        let min = corners[0].min(corners[1]).min(corners[2]).floor().max(Vec2::ZERO);
+        let max = corners[0]
+            .max(corners[1])
+            .max(corners[2])
+            .ceil()
+            .min(vec2(target_width as f32, target_height as f32));
+
+        for py in min.y as usize..max.y as usize {
+            for px in min.x as usize..max.x as usize {
+                let center = vec2(px as f32 + 0.5, py as f32 + 0.5);
+                let weights = [
+                    edge(corners[1], corners[2], center),
+                    edge(corners[2], corners[0], center),
+                    edge(corners[0], corners[1], center),
+                ];
+                let covered = weights.iter().enumerate().all(|(i, &weight)| {
+                    weight > 0.0 || (weight == 0.0 && is_top_left(corners[(i + 1) % 3], corners[(i + 2) % 3]))
+                });
+                if !covered {
+                    continue;
+                }
+
+                let [w0, w1, w2] = weights.map(|weight| weight / area);
+                let uv = triangle[0].uv * w0 + triangle[1].uv * w1 + triangle[2].uv * w2;
+                let color = triangle[0].color * w0 + triangle[1].color * w1 + triangle[2].color * w2;
+                fragments.push(Fragment {
+                    index: py * target_width + px,
+                    uv,
+                    color,
+                    filter,
+                });
+            }
+        }
+    }
+
+    fn blend(&self, destination: [u8; 4], source: Vec4) -> [u8; 4] {
+        let source = source.clamp(Vec4::ZERO, Vec4::ONE);
+        if !self.blend {
+            return to_bytes(source);
+        }
+
+        let destination = Vec4::from_array(destination.map(|channel| channel as f32 / 255.0));
+        let factor = |factor: GLenum| match factor {
+            gl::ZERO => Vec4::ZERO,
+            gl::ONE => Vec4::ONE,
+            gl::SRC_ALPHA => Vec4::splat(source.w),
+            gl::ONE_MINUS_SRC_ALPHA => Vec4::splat(1.0 - source.w),
+            gl::DST_ALPHA => Vec4::splat(destination.w),
+            gl::ONE_MINUS_DST_ALPHA => Vec4::splat(1.0 - destination.w),
+            _ => panic!("Unsupported blend factor: {:#x}", factor),
+        };
+        let (source_factor, destination_factor) = self.blend_func;
+        to_bytes(source * factor(source_factor) + destination * factor(destination_factor))
+    }
+}
+
+/// `mix(uvRect.xy, uvRect.zw, corner)`, with an unset `uvRect` reading as zeros like in GL.
+fn uv_in_rect(uv_rect: &[f32], corner: Vec2) -> Vec2 {
+    match *uv_rect {
+        [min_u, min_v, max_u, max_v] => vec2(min_u, min_v) + (vec2(max_u, max_v) - vec2(min_u, min_v)) * corner,
+        _ => Vec2::ZERO,
+    }
+}
+
+fn edge(a: Vec2, b: Vec2, point: Vec2) -> f32 {
+    (b - a).perp_dot(point - a)
+}
+
+/// Top and left edges of a counter-clockwise triangle with y pointing up.
+fn is_top_left(a: Vec2, b: Vec2) -> bool {
+    let direction = b - a;
+    (direction.y == 0.0 && direction.x < 0.0) || direction.y < 0.0
+}
+
+/// How much the uvs change per pixel along x and y, constant across a triangle.
+fn uv_gradients(corners: &[Vec2; 3], triangle: &[Vertex; 3], area: f32) -> (Vec2, Vec2) {
+    let (e1, e2) = (corners[1] - corners[0], corners[2] - corners[0]);
+    let (t1, t2) = (triangle[1].uv - triangle[0].uv, triangle[2].uv - triangle[0].uv);
+    let du = (t1 * e2.y - t2 * e1.y) / area;
+    let dv = (t2 * e1.x - t1 * e2.x) / area;
+    (du, dv)
+}
+
+fn to_bytes(color: Vec4) -> [u8; 4] {
+    color
+        .clamp(Vec4::ZERO, Vec4::ONE)
+        .to_array()
+        .map(|channel| (channel * 255.0).round() as u8)
+}
+
+/// Renders on the CPU into an RGBA framebuffer, for machines without a GPU.
+///
+/// Runs ports of the sprite, batch, instanced, text, particle and post-processing shaders, picked by
+/// the label each program was given. Draws filled triangles with the same projection,
+/// texture sampling, tinting and alpha blending the GPU would, within a rounding error, into the
+/// window or a texture or renderbuffer attached to a framebuffer.
+pub struct SoftwareBackend {
+    state: Rc<RefCell<State>>,
+}
+
+impl SoftwareBackend {
+    /// Sets the color attachment of the framebuffer bound to `target`, only the first is supported.
+    fn attach(&mut self, target: GLenum, attachment: GLenum, color: Attachment) {
+        assert_eq!(
+            attachment,
+            gl::COLOR_ATTACHMENT0,
+            "The software backend only supports one color attachment"
+        );
+        let mut state = self.state.borrow_mut();
+        let framebuffer = if target == gl::READ_FRAMEBUFFER {
+            state.read_framebuffer
+        } else {
+            state.draw_framebuffer
+        };
+        if let Some(current) = state.framebuffers.get_mut(&framebuffer) {
+            *current = Some(color);
+        }
+    }
+
+    /// Makes a new software backend with a `width` by `height` framebuffer current for this thread
+    /// and returns a handle to read the framebuffer back.
+    pub fn install(width: usize, height: usize) -> Framebuffer {
+        let state = Rc::new(RefCell::new(State {
+            width,
+            height,
+            pixels: vec![[0, 0, 0, 0]; width * height],
+            viewport: (0, 0, width as i32, height as i32),
+            blend_func: (gl::ONE, gl::ZERO),
+            ..State::default()
+        }));
+        set_backend(Box::new(SoftwareBackend {
+            state: Rc::clone(&state),
+        }));
+        Framebuffer { state }
+    }
+}
+
+/// Read access to the framebuffer of an installed `SoftwareBackend`.
+#[derive(Clone)]
+pub struct Framebuffer {
+    state: Rc<RefCell<State>>,
+}
+
+impl Framebuffer {
+    pub fn width(&self) -> usize {
+        self.state.borrow().width
+    }
+
+    pub fn height(&self) -> usize {
+        self.state.borrow().height
+    }
+
+    /// The pixel at `x`, `y` counted from the top left corner, like on screen.
+    pub fn pixel(&self, x: usize, y: usize) -> [u8; 4] {
+        let state = self.state.borrow();
+        state.pixels[(state.height - 1 - y) * state.width + x]
+    }
+
+    /// Every pixel as RGBA bytes, rows from top to bottom.
+    pub fn to_rgba(&self) -> Vec<u8> {
+        let state = self.state.borrow();
+        state
+            .pixels
+            .chunks_exact(state.width)
+            .rev()
+            .flat_map(|row| row.iter().flatten().copied())
+            .collect()
+    }
+
+    pub fn save_png(&self, path: &Path) {
+        let mut png = Vec::new();
+        let mut encoder = png::Encoder::new(&mut png, self.width() as u32, self.height() as u32);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header().unwrap();
+        writer.write_image_data(&self.to_rgba()).unwrap();
+        writer.finish().unwrap();
+        std::fs::write(path, png).unwrap_or_else(|error| panic!("Error writing {}: {}", path.display(), error));
+    }
+}
+
+impl GlBackend for SoftwareBackend {
+    fn create_shader(&mut self, shader_type: GLenum) -> u32 {
+        let mut state = self.state.borrow_mut();
+        let id = state.next_id();
+        state.shaders.insert(
+            id,
+            Shader {
+                shader_type,
+                source: String::new(),
+            },
+        );
+        id
+    }
+
+    fn shader_source(&mut self, shader: u32, source: &str) {
+        if let Some(shader) = self.state.borrow_mut().shaders.get_mut(&shader) {
+            shader.source = source.to_string();
+        }
+    }
+
+    fn compile_shader(&mut self, _shader: u32) {}
+
+    fn get_shader_iv(&mut self, _shader: u32, _pname: GLenum) -> i32 {
+        gl::TRUE as i32
+    }
+
+    fn get_shader_info_log(&mut self, _shader: u32) -> String {
+        String::new()
+    }
+
+    fn delete_shader(&mut self, shader: u32) {
+        self.state.borrow_mut().shaders.remove(&shader);
+    }
+
+    fn create_program(&mut self) -> u32 {
+        let mut state = self.state.borrow_mut();
+        let id = state.next_id();
+        state.programs.insert(id, Program::default());
+        id
+    }
+
+    fn attach_shader(&mut self, program: u32, shader: u32) {
+        if let Some(program) = self.state.borrow_mut().programs.get_mut(&program) {
+            program.shaders.push(shader);
+        }
+    }
+
+    fn link_program(&mut self, program: u32) {
+        let mut state = self.state.borrow_mut();
+        let state = &mut *state;
+        if let Some(program) = state.programs.get_mut(&program) {
+            let shaders = || program.shaders.iter().filter_map(|shader| state.shaders.get(shader));
+            program.interface = Interface::of(shaders().map(|shader| (shader.shader_type, shader.source.as_str())));
+            program.pipeline = None;
+            program.info_log = match Pipeline::named(&program.label) {
+                None => format!(
+                    "The software backend has no port of the program labelled '{}'",
+                    program.label
+                ),
+                Some(pipeline) => match pipeline
+                    .inputs()
+                    .iter()
+                    .find(|input| program.interface.attribute(input).is_none())
+                {
+                    Some(input) => format!("The port of '{}' reads the missing input {}", program.label, input),
+                    None => {
+                        program.pipeline = Some(pipeline);
+                        String::new()
+                    }
+                },
+            };
+        }
+    }
+
+    fn object_label(&mut self, identifier: GLenum, name: u32, label: &str) {
+        if identifier != gl::PROGRAM {
+            return;
+        }
+        if let Some(program) = self.state.borrow_mut().programs.get_mut(&name) {
+            program.label = label.to_string();
+        }
+    }
+
+    fn get_program_iv(&mut self, program: u32, pname: GLenum) -> i32 {
+        let state = self.state.borrow();
+        let Some(program) = state.programs.get(&program) else {
+            return gl::FALSE as i32;
+        };
+        match pname {
+            gl::LINK_STATUS => program.pipeline.is_some() as i32,
+            gl::ACTIVE_UNIFORMS => program.interface.uniforms.len() as i32,
+            _ => gl::TRUE as i32,
+        }
+    }
+
+    fn get_program_info_log(&mut self, program: u32) -> String {
+        let state = self.state.borrow();
+        state
+            .programs
+            .get(&program)
+            .map(|program| program.info_log.clone())
+            .unwrap_or_default()
+    }
+
+    fn use_program(&mut self, program: u32) {
+        self.state.borrow_mut().current_program = program;
+    }
+
+    fn get_uniform_location(&mut self, program: u32, name: &str) -> i32 {
+        let mut state = self.state.borrow_mut();
+        let state = &mut *state;
+        let Some(program) = state.programs.get_mut(&program) else {
+            return -1;
+        };
+        *program.uniforms.entry(name.to_string()).or_insert_with(|| {
+            state.last_uniform += 1;
+            state.last_uniform
+        })
+    }
+
+    fn get_active_uniform(&mut self, program: u32, index: u32) -> (String, i32, GLenum) {
+        let state = self.state.borrow();
+        let uniform = &state.programs[&program].interface.uniforms[index as usize];
+        (uniform.name.clone(), uniform.size, uniform.type_)
+    }
+
+    fn delete_program(&mut self, program: u32) {
+        let mut state = self.state.borrow_mut();
+        if let Some(program) = state.programs.remove(&program) {
+            for location in program.uniforms.values() {
+                state.uniforms.remove(location);
+            }
+        }
+    }
+
+    fn uniform_1f(&mut self, location: i32, f: f32) {
+        self.state.borrow_mut().uniforms.insert(location, vec![f]);
+    }
+
+    fn uniform_3f(&mut self, location: i32, f1: f32, f2: f32, f3: f32) {
+        self.state.borrow_mut().uniforms.insert(location, vec![f1, f2, f3]);
+    }
+
+    fn uniform_4f(&mut self, location: i32, f1: f32, f2: f32, f3: f32, f4: f32) {
+        self.state.borrow_mut().uniforms.insert(location, vec![f1, f2, f3, f4]);
+    }
+
+    fn uniform_1i(&mut self, location: i32, i: i32) {
+        self.state.borrow_mut().uniforms.insert(location, vec![i as f32]);
+    }
+
+    fn uniform_matrix4fv(&mut self, location: i32, _count: i32, transpose: bool, matrix: &[f32]) {
+        let mut matrix = Mat4::from_cols_slice(&matrix[..16]);
+        if transpose {
+            matrix = matrix.transpose();
+        }
+        self.state
+            .borrow_mut()
+            .uniforms
+            .insert(location, matrix.to_cols_array().to_vec());
+    }
+
+    fn gen_buffer(&mut self) -> u32 {
+        let mut state = self.state.borrow_mut();
+        let id = state.next_id();
+        state.buffers.insert(id, Vec::new());
+        id
+    }
+
+    fn bind_buffer(&mut self, target: GLenum, buffer: u32) {
+        if target == gl::ARRAY_BUFFER {
+            self.state.borrow_mut().array_buffer = buffer;
+        }
+    }
+
+    fn delete_buffer(&mut self, buffer: u32) {
+        self.state.borrow_mut().buffers.remove(&buffer);
+    }
+
+    unsafe fn buffer_data(&mut self, target: GLenum, size: usize, data: *const c_void, _usage: GLenum) {
+        let mut state = self.state.borrow_mut();
+        if target != gl::ARRAY_BUFFER {
+            return;
+        }
+        let bytes = if data.is_null() {
+            vec![0; size]
+        } else {
+            slice::from_raw_parts(data.cast::<u8>(), size).to_vec()
+        };
+        let buffer = state.array_buffer;
+        state.buffers.insert(buffer, bytes);
+    }
+
+    unsafe fn buffer_sub_data(&mut self, target: GLenum, offset: usize, size: usize, data: *const c_void) {
+        let mut state = self.state.borrow_mut();
+        if target != gl::ARRAY_BUFFER {
+            return;
+        }
+        let buffer = state.array_buffer;
+        let bytes = state.buffers.get_mut(&buffer).expect("No buffer bound");
+        bytes[offset..offset + size].copy_from_slice(slice::from_raw_parts(data.cast::<u8>(), size));
+    }
+
+    fn gen_vertex_array(&mut self) -> u32 {
+        let mut state = self.state.borrow_mut();
+        let id = state.next_id();
+        state.vertex_arrays.insert(id, Default::default());
+        id
+    }
+
+    fn bind_vertex_array(&mut self, vao: u32) {
+        self.state.borrow_mut().current_vertex_array = vao;
+    }
+
+    fn delete_vertex_array(&mut self, vao: u32) {
+        self.state.borrow_mut().vertex_arrays.remove(&vao);
+    }
+
+    fn vertex_attrib_pointer(
+        &mut self,
+        index: u32,
+        size: i32,
+        type_: GLenum,
+        _normalized: bool,
+        stride: usize,
+        offset: usize,
+    ) {
+        assert_eq!(type_, gl::FLOAT, "The software backend only reads float attributes");
+        let mut state = self.state.borrow_mut();
+        let buffer = state.array_buffer;
+        let vao = state.current_vertex_array;
+        if let Some(attributes) = state.vertex_arrays.get_mut(&vao) {
+            let attribute = &mut attributes[index as usize];
+            (attribute.buffer, attribute.size, attribute.stride, attribute.offset) = (buffer, size, stride, offset);
+        }
+    }
+
+    fn enable_vertex_attrib_array(&mut self, index: u32) {
+        let mut state = self.state.borrow_mut();
+        let vao = state.current_vertex_array;
+        if let Some(attributes) = state.vertex_arrays.get_mut(&vao) {
+            attributes[index as usize].enabled = true;
+        }
+    }
+
+    fn vertex_attrib_divisor(&mut self, index: u32, divisor: u32) {
+        let mut state = self.state.borrow_mut();
+        let vao = state.current_vertex_array;
+        if let Some(attributes) = state.vertex_arrays.get_mut(&vao) {
+            attributes[index as usize].divisor = divisor;
+        }
+    }
+
+    fn gen_texture(&mut self) -> u32 {
+        let mut state = self.state.borrow_mut();
+        let id = state.next_id();
+        state.textures.insert(
+            id,
+            Texture {
+                wrap_s: gl::REPEAT,
+                wrap_t: gl::REPEAT,
+                min_filter: gl::NEAREST_MIPMAP_LINEAR,
+                mag_filter: gl::LINEAR,
+                ..Texture::default()
+            },
+        );
+        id
+    }
+
+    fn bind_texture(&mut self, target: GLenum, texture: u32) {
+        if target == gl::TEXTURE_2D {
+            let mut state = self.state.borrow_mut();
+            let unit = state.active_texture;
+            state.texture_units[unit] = texture;
+        }
+    }
+
+    fn delete_texture(&mut self, texture: u32) {
+        self.state.borrow_mut().textures.remove(&texture);
+    }
+
+    fn tex_image_2d(
+        &mut self,
+        _target: GLenum,
+        _level: i32,
+        internal_format: GLenum,
+        width: i32,
+        height: i32,
+        format: GLenum,
+        _type: GLenum,
+        data: &[u8],
+    ) {
+        let channels = match format {
+            gl::RED => 1,
+            gl::RGB => 3,
+            gl::RGBA => 4,
+            _ => panic!("Unsupported texture format: {:#x}", format),
+        };
+        let alpha = internal_format == gl::RGBA;
+        let texels = if data.is_empty() {
+            vec![vec4(0.0, 0.0, 0.0, 1.0); (width * height) as usize]
+        } else {
+            data.chunks_exact(channels)
+                .map(|texel| {
+                    let channel = |i: usize| texel.get(i).map_or(0.0, |&value| value as f32 / 255.0);
+                    vec4(channel(0), channel(1), channel(2), if alpha { channel(3) } else { 1.0 })
+                })
+                .collect()
+        };
+
+        let mut state = self.state.borrow_mut();
+        let unit = state.active_texture;
+        let bound = state.texture_units[unit];
+        if let Some(texture) = state.textures.get_mut(&bound) {
+            (texture.width, texture.height, texture.texels) = (width as usize, height as usize, texels);
+            texture.alpha = alpha;
+        }
+    }
+
+    fn tex_parameter_i(&mut self, _target: GLenum, pname: GLenum, param: GLenum) {
+        let mut state = self.state.borrow_mut();
+        let unit = state.active_texture;
+        let bound = state.texture_units[unit];
+        if let Some(texture) = state.textures.get_mut(&bound) {
+            match pname {
+                gl::TEXTURE_WRAP_S => texture.wrap_s = param,
+                gl::TEXTURE_WRAP_T => texture.wrap_t = param,
+                gl::TEXTURE_MIN_FILTER => texture.min_filter = param,
+                gl::TEXTURE_MAG_FILTER => texture.mag_filter = param,
+                _ => {}
+            }
+        }
+    }
+
+    fn active_texture(&mut self, texture: GLenum) {
+        self.state.borrow_mut().active_texture = (texture - gl::TEXTURE0) as usize;
+    }
+
+    fn gen_framebuffer(&mut self) -> u32 {
+        let mut state = self.state.borrow_mut();
+        let id = state.next_id();
+        state.framebuffers.insert(id, None);
+        id
+    }
+
+    fn bind_framebuffer(&mut self, target: GLenum, framebuffer: u32) {
+        let mut state = self.state.borrow_mut();
+        if target == gl::FRAMEBUFFER || target == gl::DRAW_FRAMEBUFFER {
+            state.draw_framebuffer = framebuffer;
+        }
+        if target == gl::FRAMEBUFFER || target == gl::READ_FRAMEBUFFER {
+            state.read_framebuffer = framebuffer;
+        }
+    }
+
+    fn delete_framebuffer(&mut self, framebuffer: u32) {
+        self.state.borrow_mut().framebuffers.remove(&framebuffer);
+    }
+
+    fn framebuffer_texture_2d(
+        &mut self,
+        target: GLenum,
+        attachment: GLenum,
+        _texture_target: GLenum,
+        texture: u32,
+        _level: i32,
+    ) {
+        self.attach(target, attachment, Attachment::Texture(texture));
+    }
+
+    fn framebuffer_renderbuffer(
+        &mut self,
+        target: GLenum,
+        attachment: GLenum,
+        _renderbuffer_target: GLenum,
+        renderbuffer: u32,
+    ) {
+        self.attach(target, attachment, Attachment::Renderbuffer(renderbuffer));
+    }
+
+    fn check_framebuffer_status(&mut self, target: GLenum) -> GLenum {
+        let state = self.state.borrow();
+        let framebuffer = if target == gl::READ_FRAMEBUFFER {
+            state.read_framebuffer
+        } else {
+            state.draw_framebuffer
+        };
+        if framebuffer == 0 || state.attachment(framebuffer).is_some() {
+            gl::FRAMEBUFFER_COMPLETE
+        } else {
+            gl::FRAMEBUFFER_INCOMPLETE_MISSING_ATTACHMENT
+        }
+    }
+
+    fn blit_framebuffer(&mut self, source: [i32; 4], destination: [i32; 4], mask: GLenum, _filter: GLenum) {
+        if mask & gl::COLOR_BUFFER_BIT != 0 {
+            self.state.borrow_mut().blit(source, destination);
+        }
+    }
+
+    fn gen_renderbuffer(&mut self) -> u32 {
+        let mut state = self.state.borrow_mut();
+        let id = state.next_id();
+        state.renderbuffers.insert(id, Renderbuffer::default());
+        id
+    }
+
+    fn bind_renderbuffer(&mut self, _target: GLenum, renderbuffer: u32) {
+        self.state.borrow_mut().bound_renderbuffer = renderbuffer;
+    }
+
+    fn delete_renderbuffer(&mut self, renderbuffer: u32) {
+        self.state.borrow_mut().renderbuffers.remove(&renderbuffer);
+    }
+
+    fn renderbuffer_storage_multisample(
+        &mut self,
+        _target: GLenum,
+        _samples: i32,
+        _internal_format: GLenum,
+        width: i32,
+        height: i32,
+    ) {
+        let mut state = self.state.borrow_mut();
+        let bound = state.bound_renderbuffer;
+        if let Some(renderbuffer) = state.renderbuffers.get_mut(&bound) {
+            *renderbuffer = Renderbuffer {
+                width: width as usize,
+                height: height as usize,
+                pixels: vec![[0, 0, 0, 0]; (width * height) as usize],
+            };
+        }
+    }
+
+    fn clear_color(&mut self, r: f32, g: f32, b: f32, a: f32) {
+        self.state.borrow_mut().clear_color = vec4(r, g, b, a);
+    }
+
+    fn clear(&mut self, mask: GLenum) {
+        if mask & gl::COLOR_BUFFER_BIT != 0 {
+            let mut state = self.state.borrow_mut();
+            let color = to_bytes(state.clear_color);
+            let framebuffer = state.draw_framebuffer;
+            let (width, height) = state.color_size(framebuffer);
+            for index in 0..width * height {
+                state.write_pixel(framebuffer, index, color);
+            }
+        }
+    }
+
+    fn viewport(&mut self, x: i32, y: i32, width: i32, height: i32) {
+        self.state.borrow_mut().viewport = (x, y, width, height);
+    }
+
+    fn draw_arrays(&mut self, mode: GLenum, first: i32, count: usize) {
+        self.state.borrow_mut().draw(mode, first as usize, count, 1);
+    }
+
+    fn draw_arrays_instanced(&mut self, mode: GLenum, first: i32, count: usize, instance_count: usize) {
+        self.state
+            .borrow_mut()
+            .draw(mode, first as usize, count, instance_count);
+    }
+
+    fn blend_func(&mut self, s_factor: GLenum, d_factor: GLenum) {
+        self.state.borrow_mut().blend_func = (s_factor, d_factor);
+    }
+
+    fn enable(&mut self, cap: GLenum) {
+        if cap == gl::BLEND {
+            self.state.borrow_mut().blend = true;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use crate::{
+        game::preprocessor::ShaderSource,
+        opengl::{
+            blend_func, blit_framebuffer, buffer_data, check_framebuffer_status, clear, clear_color, create_program,
+            create_shader, draw_arrays, enable, enable_vertex_attrib_array, framebuffer_renderbuffer, gen_buffers,
+            gen_framebuffers, gen_renderbuffers, gen_textures, gen_vertex_arrays, renderbuffer_storage_multisample,
+            tex_image_2d, tex_parameter_i, vertex_attrib_pointer, Framebuffer as GlFramebuffer, Program,
+        },
+        util::read_file,
+    };
+
+    use super::*;
+
+    fn link(label: &str, vertex_source: &str) -> Program {
+        let vertex_shader = create_shader(gl::VERTEX_SHADER);
+        vertex_shader.source(vertex_source);
+        let program = create_program();
+        program.attach_shader(&vertex_shader);
+        program.set_label(label);
+        program.link();
+        program
+    }
+
+    #[test]
+    fn programs_link_by_label_when_they_declare_what_the_port_reads() {
+        let _window = SoftwareBackend::install(1, 1);
+        let source = read_file(Path::new("src/shaders/sprite.vert")).unwrap();
+        assert!(link("sprite", &source).get(gl::LINK_STATUS));
+
+        // the same source under a name with no port, and with the inputs of another port
+        let unknown = link("bloom", &source);
+        assert!(!unknown.get(gl::LINK_STATUS));
+        assert_eq!(
+            unknown.get_info_log(),
+            "The software backend has no port of the program labelled 'bloom'"
+        );
+        let mislabelled = link("sprite_batch", &source);
+        assert!(!mislabelled.get(gl::LINK_STATUS));
+        assert_eq!(
+            mislabelled.get_info_log(),
+            "The port of 'sprite_batch' reads the missing input position"
+        );
+    }
+
+    /// Draws `quads` given as (min, max) corners with the batch shaders and a single texel texture.
+    fn draw_quads(quads: &[(Vec2, Vec2)], texel: [u8; 4]) {
+        let program = link(
+            "sprite_batch",
+            &ShaderSource::load(Path::new("src/shaders/sprite_batch.vert"), &[])
+                .unwrap()
+                .text,
+        );
+        assert!(program.get(gl::LINK_STATUS));
+
+        program.use_();
+        let projection = Mat4::orthographic_lh(0.0, 4.0, 4.0, 0.0, -1.0, 1.0);
+        program
+            .get_uniform_location("projection")
+            .set_matrix4fv(1, false, &projection.to_cols_array());
+
+        let [texture] = gen_textures::<1>();
+        texture.bind(gl::TEXTURE_2D);
+        tex_image_2d(
+            gl::TEXTURE_2D,
+            0,
+            gl::RGBA,
+            1,
+            1,
+            0,
+            gl::RGBA,
+            gl::UNSIGNED_BYTE,
+            &texel,
+        );
+
+        let mut vertices: Vec<f32> = Vec::new();
+        for (min, max) in quads {
+            for corner in [
+                vec2(0.0, 1.0),
+                vec2(1.0, 0.0),
+                vec2(0.0, 0.0),
+                vec2(0.0, 1.0),
+                vec2(1.0, 1.0),
+                vec2(1.0, 0.0),
+            ] {
+                let position = *min + (*max - *min) * corner;
+                vertices.extend_from_slice(&[position.x, position.y, corner.x, corner.y, 1.0, 1.0, 1.0]);
+            }
+        }
+
+        let [vao] = gen_vertex_arrays::<1>();
+        let [vbo] = gen_buffers::<1>();
+        vao.bind();
+        vbo.bind(gl::ARRAY_BUFFER);
+        buffer_data(gl::ARRAY_BUFFER, &vertices, gl::STATIC_DRAW);
+        let stride = 7 * 4;
+        vertex_attrib_pointer(0, 2, gl::FLOAT, false, stride, 0);
+        enable_vertex_attrib_array(0);
+        vertex_attrib_pointer(1, 2, gl::FLOAT, false, stride, 2 * 4);
+        enable_vertex_attrib_array(1);
+        vertex_attrib_pointer(2, 3, gl::FLOAT, false, stride, 4 * 4);
+        enable_vertex_attrib_array(2);
+
+        draw_arrays(gl::TRIANGLES, 0, vertices.len() / 7);
+    }
+
+    #[test]
+    fn clear_fills_the_framebuffer() {
+        let framebuffer = SoftwareBackend::install(3, 2);
+        clear_color(1.0, 0.5, 0.0, 1.0);
+        clear(gl::COLOR_BUFFER_BIT);
+
+        assert_eq!(framebuffer.to_rgba(), [255, 128, 0, 255].repeat(6));
+    }
+
+    #[test]
+    fn draws_a_quad_with_the_top_left_corner_at_the_origin() {
+        let framebuffer = SoftwareBackend::install(4, 4);
+        clear_color(0.0, 0.0, 0.0, 1.0);
+        clear(gl::COLOR_BUFFER_BIT);
+        draw_quads(&[(vec2(0.0, 0.0), vec2(2.0, 1.0))], [255, 255, 255, 255]);
+
+        let white = [255, 255, 255, 255];
+        let black = [0, 0, 0, 255];
+        assert_eq!(framebuffer.pixel(0, 0), white);
+        assert_eq!(framebuffer.pixel(1, 0), white);
+        assert_eq!(framebuffer.pixel(2, 0), black);
+        assert_eq!(framebuffer.pixel(0, 1), black);
+    }
+
+    #[test]
+    fn shared_edges_are_blended_once() {
+        let framebuffer = SoftwareBackend::install(4, 4);
+        clear_color(0.0, 0.0, 0.0, 1.0);
+        clear(gl::COLOR_BUFFER_BIT);
+        enable(gl::BLEND);
+        blend_func(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
+        draw_quads(
+            &[(vec2(0.0, 0.0), vec2(2.0, 4.0)), (vec2(2.0, 0.0), vec2(4.0, 4.0))],
+            [255, 255, 255, 128],
+        );
+
+        assert_eq!(framebuffer.to_rgba(), [128, 128, 128, 191].repeat(16));
+    }
+
+    #[test]
+    fn draws_into_the_bound_framebuffer_and_blits_it_to_the_window() {
+        let window = SoftwareBackend::install(2, 2);
+        clear_color(0.0, 0.0, 0.0, 1.0);
+        clear(gl::COLOR_BUFFER_BIT);
+
+        let [framebuffer] = gen_framebuffers::<1>();
+        let [renderbuffer] = gen_renderbuffers::<1>();
+        framebuffer.bind(gl::FRAMEBUFFER);
+        assert_eq!(
+            check_framebuffer_status(gl::FRAMEBUFFER),
+            gl::FRAMEBUFFER_INCOMPLETE_MISSING_ATTACHMENT
+        );
+        renderbuffer.bind();
+        renderbuffer_storage_multisample(4, gl::RGB8, 2, 2);
+        framebuffer_renderbuffer(gl::FRAMEBUFFER, gl::COLOR_ATTACHMENT0, &renderbuffer);
+        assert_eq!(check_framebuffer_status(gl::FRAMEBUFFER), gl::FRAMEBUFFER_COMPLETE);
+
+        clear_color(1.0, 0.0, 0.0, 1.0);
+        clear(gl::COLOR_BUFFER_BIT);
+        GlFramebuffer::unbind(gl::FRAMEBUFFER);
+        assert_eq!(window.to_rgba(), [0, 0, 0, 255].repeat(4));
+
+        framebuffer.bind(gl::READ_FRAMEBUFFER);
+        blit_framebuffer([0, 0, 2, 2], [0, 0, 2, 2], gl::COLOR_BUFFER_BIT, gl::NEAREST);
+        assert_eq!(window.to_rgba(), [255, 0, 0, 255].repeat(4));
+    }
+
+    #[test]
+    fn post_processing_confuse_flips_and_inverts_the_scene() {
+        let window = SoftwareBackend::install(2, 2);
+        let program = link(
+            "post_process",
+            &read_file(Path::new("src/shaders/post_process.vert")).unwrap(),
+        );
+        assert!(program.get(gl::LINK_STATUS));
+
+        // red on the left, blue on the right
+        let [texture] = gen_textures::<1>();
+        texture.bind(gl::TEXTURE_2D);
+        let texels = [255, 0, 0, 0, 0, 255].repeat(2);
+        tex_image_2d(gl::TEXTURE_2D, 0, gl::RGB, 2, 2, 0, gl::RGB, gl::UNSIGNED_BYTE, &texels);
+        tex_parameter_i(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::NEAREST);
+        tex_parameter_i(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::NEAREST);
+
+        let vertices: [f32; 12] = [-1.0, -1.0, 1.0, 1.0, -1.0, 1.0, -1.0, -1.0, 1.0, -1.0, 1.0, 1.0];
+        let [vao] = gen_vertex_arrays::<1>();
+        let [vbo] = gen_buffers::<1>();
+        vao.bind();
+        vbo.bind(gl::ARRAY_BUFFER);
+        buffer_data(gl::ARRAY_BUFFER, &vertices, gl::STATIC_DRAW);
+        vertex_attrib_pointer(0, 2, gl::FLOAT, false, 2 * 4, 0);
+        enable_vertex_attrib_array(0);
+
+        program.use_();
+        draw_arrays(gl::TRIANGLES, 0, 6);
+        assert_eq!(window.pixel(0, 0), [255, 0, 0, 255]);
+        assert_eq!(window.pixel(1, 1), [0, 0, 255, 255]);
+
+        program.get_uniform_location("confuse").set_1i(1);
+        draw_arrays(gl::TRIANGLES, 0, 6);
+        assert_eq!(window.pixel(0, 0), [255, 255, 0, 255]);
+        assert_eq!(window.pixel(1, 1), [0, 255, 255, 255]);
+    }
+}
@@ -0,0 +1,11 @@
+#version 330 core
+
+in vec2 TexCoords;
+in vec4 ParticleColor;
+out vec4 color;
+
+uniform sampler2D image;
+
+void main() {
+    color = ParticleColor * texture(image, TexCoords);
+}
@@ -0,0 +1,17 @@
+#version 330 core
+
+layout(location = 0) in vec2 vertex;
+layout(location = 1) in vec3 particle; // per instance, centre in xy and size in z
+layout(location = 2) in vec4 color; // per instance
+
+out vec2 TexCoords;
+out vec4 ParticleColor;
+
+uniform mat4 projection;
+uniform vec4 uvRect;
+
+void main() {
+    TexCoords = mix(uvRect.xy, uvRect.zw, vertex);
+    ParticleColor = color;
+    gl_Position = projection * vec4(particle.xy + (vertex - 0.5) * particle.z, 0.0, 1.0);
+}
@@ -0,0 +1,46 @@
+#version 330 core
+
+in vec2 TexCoords;
+out vec4 color;
+
+uniform sampler2D image;
+uniform bool chaos;
+uniform bool confuse;
+uniform bool shake;
+
+const float offset = 1.0 / 300.0;
+const vec2 offsets[9] = vec2[](
+    vec2(-offset,  offset), vec2(0.0,  offset), vec2(offset,  offset),
+    vec2(-offset,  0.0),    vec2(0.0,  0.0),    vec2(offset,  0.0),
+    vec2(-offset, -offset), vec2(0.0, -offset), vec2(offset, -offset)
+);
+const float edge_kernel[9] = float[](
+    -1.0, -1.0, -1.0,
+    -1.0,  8.0, -1.0,
+    -1.0, -1.0, -1.0
+);
+const float blur_kernel[9] = float[](
+    1.0 / 16.0, 2.0 / 16.0, 1.0 / 16.0,
+    2.0 / 16.0, 4.0 / 16.0, 2.0 / 16.0,
+    1.0 / 16.0, 2.0 / 16.0, 1.0 / 16.0
+);
+
+vec3 convolve(float kernel[9]) {
+    vec3 sum = vec3(0.0);
+    for (int i = 0; i < 9; i++) {
+        sum += vec3(texture(image, TexCoords + offsets[i])) * kernel[i];
+    }
+    return sum;
+}
+
+void main() {
+    if (chaos) {
+        color = vec4(convolve(edge_kernel), 1.0);
+    } else if (confuse) {
+        color = vec4(1.0 - texture(image, TexCoords).rgb, 1.0);
+    } else if (shake) {
+        color = vec4(convolve(blur_kernel), 1.0);
+    } else {
+        color = texture(image, TexCoords);
+    }
+}
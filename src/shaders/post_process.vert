@@ -0,0 +1,29 @@
+#version 330 core
+
+layout(location = 0) in vec2 vertex; // full screen quad in normalized device coordinates
+
+out vec2 TexCoords;
+
+uniform bool chaos;
+uniform bool confuse;
+uniform bool shake;
+uniform float time;
+
+void main() {
+    gl_Position = vec4(vertex, 0.0, 1.0);
+    vec2 uv = vertex * 0.5 + 0.5;
+    if (chaos) {
+        // the texture repeats, so the screen swirls around
+        float strength = 0.3;
+        TexCoords = uv + vec2(sin(time), cos(time)) * strength;
+    } else if (confuse) {
+        TexCoords = vec2(1.0 - uv.x, 1.0 - uv.y);
+    } else {
+        TexCoords = uv;
+    }
+    if (shake) {
+        float strength = 0.01;
+        gl_Position.x += cos(time * 10.0) * strength;
+        gl_Position.y += cos(time * 15.0) * strength;
+    }
+}
@@ -0,0 +1,5 @@
+// What every sprite vertex shader passes on to sprite_batch.frag.
+out vec2 TexCoords;
+out vec4 SpriteColor;
+
+uniform mat4 projection;
@@ -6,8 +6,9 @@ out vec2 TexCoords;
 
 uniform mat4 model;
 uniform mat4 projection;
+uniform vec4 uvRect; // the part of the texture to draw, min in xy and max in zw
 
 void main() {
-    TexCoords = vertex;
+    TexCoords = mix(uvRect.xy, uvRect.zw, vertex);
     gl_Position =  projection * model * vec4(vertex, 0.0, 1.0);
 }
\ No newline at end of file
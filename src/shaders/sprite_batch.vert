@@ -0,0 +1,26 @@
+#version 330 core
+
+#include "sprite.glsl"
+
+#ifdef INSTANCED
+layout(location = 0) in vec2 vertex;
+layout(location = 1) in mat4 model; // per instance, occupies locations 1-4
+layout(location = 5) in vec4 color; // per instance
+layout(location = 6) in vec4 uvRect; // per instance, min in xy and max in zw
+
+void main() {
+    TexCoords = mix(uvRect.xy, uvRect.zw, vertex);
+    SpriteColor = color;
+    gl_Position = projection * model * vec4(vertex, 0.0, 1.0);
+}
+#else
+layout(location = 0) in vec2 position;
+layout(location = 1) in vec2 texCoords;
+layout(location = 2) in vec3 color;
+
+void main() {
+    TexCoords = texCoords;
+    SpriteColor = vec4(color, 1.0);
+    gl_Position = projection * vec4(position, 0.0, 1.0);
+}
+#endif
@@ -1,5 +1,197 @@
-use std::{fs, path::Path};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
 
-pub fn read_file(file_path: &Path) -> String {
-    fs::read_to_string(file_path).unwrap_or_else(|_| panic!("Failed to read file: {}", file_path.display()))
+use crate::error::{Error, Result};
+
+pub fn read_file(file_path: &Path) -> Result<String> {
+    fs::read_to_string(file_path).map_err(|source| Error::Io {
+        path: file_path.to_path_buf(),
+        source,
+    })
+}
+
+/// Remembers when a file was last modified, to notice when it is edited.
+#[derive(Debug)]
+pub struct FileWatch {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+}
+
+impl FileWatch {
+    pub fn new(path: &Path) -> FileWatch {
+        FileWatch {
+            path: path.to_path_buf(),
+            modified: modified(path),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// True once for every change since the last call. A missing file does not count as a change,
+    /// editors often delete and write a file back while saving.
+    pub fn changed(&mut self) -> bool {
+        let modified = modified(&self.path);
+        if modified.is_none() || modified == self.modified {
+            return false;
+        }
+
+        self.modified = modified;
+        true
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+}
+
+/// A small, seedable random number generator (xorshift64*), so a seed always gives the same
+/// sequence and tests can be deterministic.
+#[derive(Debug, Clone)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Rng {
+        // the state must never be zero
+        Rng {
+            state: (seed ^ 0x9e37_79b9_7f4a_7c15).max(1),
+        }
+    }
+
+    /// Seeded from the clock, for when the sequence does not need to be repeatable.
+    pub fn from_time() -> Rng {
+        let nanos = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map_or(0, |time| time.as_nanos() as u64);
+        Rng::new(nanos)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        self.state.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    /// A number in `0.0..1.0`.
+    pub fn next_f32(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    /// A number in `min..max`.
+    pub fn range(&mut self, min: f32, max: f32) -> f32 {
+        min + (max - min) * self.next_f32()
+    }
+}
+
+/// Splits the time between frames into steps of the same length, so a simulation runs the same
+/// whatever the frame rate. Time short of a whole step carries over to the next frame.
+#[derive(Debug, Clone)]
+pub struct FixedTimestep {
+    step: f32,
+    max_delta: f32,
+    accumulator: f32,
+}
+
+impl FixedTimestep {
+    pub fn new(step: f32, max_delta: f32) -> FixedTimestep {
+        FixedTimestep {
+            step,
+            max_delta,
+            accumulator: 0.0,
+        }
+    }
+
+    /// Adds the seconds since the last frame and returns how many steps to simulate for them.
+    /// A frame longer than `max_delta`, after a stall or a breakpoint, only counts as
+    /// `max_delta`, so the simulation falls behind instead of trying to catch up all at once.
+    pub fn advance(&mut self, delta: f32) -> u32 {
+        self.accumulator += delta.clamp(0.0, self.max_delta);
+        let mut steps = 0;
+        while self.accumulator >= self.step {
+            self.accumulator -= self.step;
+            steps += 1;
+        }
+        steps
+    }
+
+    /// How far into the next step the frame is, from 0 to 1, to interpolate what is drawn.
+    pub fn alpha(&self) -> f32 {
+        self.accumulator / self.step
+    }
+}
+
+/// A directory of its own under the system's temporary directory, for a test to write files
+/// into. It is removed with everything in it on drop.
+#[cfg(test)]
+pub struct TempDir(PathBuf);
+
+#[cfg(test)]
+impl TempDir {
+    pub fn new(name: &str) -> TempDir {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        // tests run in parallel, and the process id tells apart runs of the tests at the same time
+        static COUNT: AtomicUsize = AtomicUsize::new(0);
+        let count = COUNT.fetch_add(1, Ordering::Relaxed);
+        let path = std::env::temp_dir().join(format!("{}-{}-{}", name, std::process::id(), count));
+        // left over by a run that was killed before it could clean up
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).unwrap();
+        TempDir(path)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn temporary_directories_are_unique_and_removed_on_drop() {
+        let first = TempDir::new("util-test");
+        let second = TempDir::new("util-test");
+        assert_ne!(first.path(), second.path());
+
+        fs::write(first.path().join("file"), "contents").unwrap();
+        let path = first.path().to_path_buf();
+        drop(first);
+        assert!(!path.exists());
+        assert!(second.path().is_dir());
+    }
+
+    #[test]
+    fn carries_the_time_short_of_a_step_over() {
+        let mut timestep = FixedTimestep::new(0.25, 1.0);
+        assert_eq!(timestep.advance(0.125), 0);
+        assert_eq!(timestep.alpha(), 0.5);
+        assert_eq!(timestep.advance(0.5), 2);
+        assert_eq!(timestep.alpha(), 0.5);
+        assert_eq!(timestep.advance(0.125), 1);
+        assert_eq!(timestep.alpha(), 0.0);
+    }
+
+    #[test]
+    fn long_frames_count_as_the_maximum_delta() {
+        let mut timestep = FixedTimestep::new(0.25, 1.0);
+        assert_eq!(timestep.advance(60.0), 4);
+        assert_eq!(timestep.alpha(), 0.0);
+        assert_eq!(timestep.advance(-1.0), 0);
+    }
 }